@@ -1,3 +1,4 @@
+pub mod explorer;
 pub mod model;
 pub mod node;
 pub mod types;
@@ -0,0 +1,290 @@
+use async_trait::async_trait;
+use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::ergo_chain_types::autolykos_pow_scheme::AutolykosSolution;
+use ergo_lib::ergo_chain_types::{ADDigest, BlockId, Digest32, Header, Votes};
+use isahc::{AsyncReadResponseExt, HttpClient};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::model::{BlockTransactions, FullBlock};
+use crate::client::node::{ErgoNetwork, Error};
+use crate::client::types::{with_path, Url};
+
+/// Client of the Ergo Explorer API, an alternative to a synced full node.
+/// Blocks and mempool txs are converted from the explorer representation
+/// and checked to match the ids reported by the explorer.
+#[derive(Clone)]
+pub struct ErgoExplorerHttpClient {
+    pub client: HttpClient,
+    pub base_url: Url,
+}
+
+impl ErgoExplorerHttpClient {
+    pub fn new(client: HttpClient, base_url: Url) -> Self {
+        Self { client, base_url }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let mut resp = self.client.get_async(with_path(&self.base_url, path)).await?;
+        if resp.status().is_success() {
+            Ok(resp.json::<T>().await?)
+        } else {
+            Err(Error::UnsuccessfulRequest(format!(
+                "expected 200 from {}, got {:?}",
+                path,
+                resp.status()
+            )))
+        }
+    }
+
+    /// Height of the best block indexed by the explorer.
+    pub async fn fetch_height(&self) -> Result<u32, Error> {
+        Ok(self.get::<NetworkState>("/api/v1/networkState").await?.height)
+    }
+
+    /// Up to `limit` most recent headers, the best one goes first.
+    pub async fn fetch_last_headers(&self, limit: usize) -> Result<Vec<Header>, Error> {
+        let path = format!(
+            "/api/v1/blocks/headers?offset=0&limit={}&sortBy=height&sortDirection=desc",
+            limit
+        );
+        Ok(self
+            .get::<Items<ExplorerHeader>>(&path)
+            .await?
+            .items
+            .into_iter()
+            .map(Header::from)
+            .collect())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Items<A> {
+    items: Vec<A>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetworkState {
+    height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExplorerVotes {
+    Encoded(Votes),
+    Bytes([u8; 3]),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerHeader {
+    id: BlockId,
+    parent_id: BlockId,
+    version: u8,
+    timestamp: u64,
+    height: u32,
+    n_bits: u64,
+    ad_proofs_root: Digest32,
+    state_root: ADDigest,
+    transactions_root: Digest32,
+    extension_hash: Digest32,
+    pow_solutions: AutolykosSolution,
+    votes: ExplorerVotes,
+}
+
+impl From<ExplorerHeader> for Header {
+    fn from(h: ExplorerHeader) -> Self {
+        Header {
+            version: h.version,
+            id: h.id,
+            parent_id: h.parent_id,
+            ad_proofs_root: h.ad_proofs_root,
+            state_root: h.state_root,
+            transaction_root: h.transactions_root,
+            timestamp: h.timestamp,
+            n_bits: h.n_bits,
+            height: h.height,
+            extension_root: h.extension_hash,
+            autolykos_solution: h.pow_solutions,
+            votes: match h.votes {
+                ExplorerVotes::Encoded(votes) => votes,
+                ExplorerVotes::Bytes(bytes) => Votes(bytes),
+            },
+        }
+    }
+}
+
+/// Explorer reports either the full proof or only its bytes, depending on the endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExplorerSpendingProof {
+    #[serde(rename_all = "camelCase")]
+    Full {
+        proof_bytes: Option<String>,
+        extension: Option<serde_json::Value>,
+    },
+    Bytes(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerInput {
+    #[serde(alias = "id")]
+    box_id: String,
+    spending_proof: Option<ExplorerSpendingProof>,
+    extension: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerDataInput {
+    #[serde(alias = "id")]
+    box_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerTransaction {
+    id: String,
+    inputs: Vec<ExplorerInput>,
+    #[serde(default)]
+    data_inputs: Vec<ExplorerDataInput>,
+    outputs: Vec<serde_json::Value>,
+}
+
+impl TryFrom<ExplorerTransaction> for Transaction {
+    type Error = Error;
+
+    fn try_from(tx: ExplorerTransaction) -> Result<Self, Self::Error> {
+        let inputs = tx
+            .inputs
+            .into_iter()
+            .map(|input| {
+                let (proof_bytes, extension) = match input.spending_proof {
+                    Some(ExplorerSpendingProof::Full {
+                        proof_bytes,
+                        extension,
+                    }) => (proof_bytes, extension),
+                    Some(ExplorerSpendingProof::Bytes(proof_bytes)) => (Some(proof_bytes), None),
+                    None => (None, None),
+                };
+                json!({
+                    "boxId": input.box_id,
+                    "spendingProof": {
+                        "proofBytes": proof_bytes.unwrap_or_default(),
+                        "extension": extension.or(input.extension).unwrap_or_else(|| json!({})),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        let data_inputs = tx
+            .data_inputs
+            .into_iter()
+            .map(|input| json!({ "boxId": input.box_id }))
+            .collect::<Vec<_>>();
+        let restored = serde_json::from_value::<Transaction>(json!({
+            "id": tx.id,
+            "inputs": inputs,
+            "dataInputs": data_inputs,
+            "outputs": tx.outputs,
+        }))?;
+        // Id is derived from the tx itself, so a mismatch means some data was lost in conversion.
+        if String::from(restored.id()) != tx.id {
+            return Err(Error::UnsuccessfulRequest(format!(
+                "tx [{}] can't be restored from explorer data",
+                tx.id
+            )));
+        }
+        Ok(restored)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerBlock {
+    header: ExplorerHeader,
+    block_transactions: Vec<ExplorerTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplorerBlockSummary {
+    block: ExplorerBlock,
+}
+
+#[async_trait(?Send)]
+impl ErgoNetwork for ErgoExplorerHttpClient {
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, Error> {
+        let block_id = self.get_block_id_at(height).await?;
+        let header_id = base16::encode_lower(&block_id.0 .0);
+        let ExplorerBlockSummary { block } = self
+            .get::<ExplorerBlockSummary>(&format!("/api/v1/blocks/{}", header_id))
+            .await?;
+        let transactions = block
+            .block_transactions
+            .into_iter()
+            .map(Transaction::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FullBlock {
+            header: Header::from(block.header),
+            block_transactions: BlockTransactions { transactions },
+        })
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, Error> {
+        let blocks = self
+            .get::<Vec<BlockId>>(&format!("/api/v0/blocks/at/{}", height))
+            .await?;
+        blocks.first().cloned().ok_or(Error::NoBlock)
+    }
+
+    async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, Error> {
+        self.get::<Items<ExplorerTransaction>>(&format!(
+            "/api/v0/transactions/unconfirmed?offset={:?}&limit={:?}",
+            offset, limit
+        ))
+        .await?
+        .items
+        .into_iter()
+        .map(Transaction::try_from)
+        .collect()
+    }
+
+    async fn get_best_height(&self) -> Result<u32, Error> {
+        self.fetch_height().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergo_chain_types::{Header, Votes};
+
+    use crate::client::explorer::ExplorerHeader;
+
+    #[test]
+    fn header_votes_in_both_encodings() {
+        let header = |votes: &str| {
+            format!(
+                r#"{{
+                "id": "{id}", "parentId": "{id}", "version": 2, "timestamp": 1, "height": 10,
+                "nBits": 117794167, "adProofsRoot": "{id}", "transactionsRoot": "{id}", "extensionHash": "{id}",
+                "stateRoot": "{id}00",
+                "powSolutions": {{
+                    "pk": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                    "w": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                    "n": "0000000000003105",
+                    "d": 0
+                }},
+                "votes": {votes}
+            }}"#,
+                id = "a".repeat(64),
+                votes = votes,
+            )
+        };
+        for votes in ["\"010203\"", "[1, 2, 3]"] {
+            let header = serde_json::from_str::<ExplorerHeader>(&header(votes)).unwrap();
+            let header = Header::from(header);
+            assert_eq!(header.height, 10);
+            assert_eq!(header.votes, Votes([1, 2, 3]));
+        }
+    }
+}
@@ -1,13 +1,18 @@
+use std::cell::Cell;
+
 use async_trait::async_trait;
+use chrono::Utc;
 use derive_more::From;
 use ergo_lib::chain::transaction::Transaction;
 use ergo_lib::ergo_chain_types::{BlockId, Header};
 use isahc::{AsyncReadResponseExt, HttpClient};
 use log::trace;
+use prometheus::{IntGauge, Registry};
 use thiserror::Error;
 
 use crate::client::model::{ApiInfo, FullBlock};
 use crate::client::types::Url;
+use crate::ChainUpgrade;
 
 use super::model::BlockTransactions;
 use super::types::with_path;
@@ -27,6 +32,8 @@ pub enum Error {
 #[async_trait(?Send)]
 pub trait ErgoNetwork {
     async fn get_block_at(&self, height: u32) -> Result<FullBlock, Error>;
+    /// Id of the block at the given height of the best chain.
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, Error>;
     async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, Error>;
     async fn get_best_height(&self) -> Result<u32, Error>;
 }
@@ -46,46 +53,47 @@ impl ErgoNodeHttpClient {
 #[async_trait(?Send)]
 impl ErgoNetwork for ErgoNodeHttpClient {
     async fn get_block_at(&self, height: u32) -> Result<FullBlock, Error> {
+        let block_id = self.get_block_id_at(height).await?;
+        let header_id = base16::encode_lower(&block_id.0 .0);
+        let transactions_path = format!("/blocks/{}/transactions", header_id);
+        let mut resp = self
+            .client
+            .get_async(with_path(&self.base_url, &transactions_path))
+            .await?;
+        let block_transactions = if resp.status().is_success() {
+            resp.json::<BlockTransactions>().await?
+        } else {
+            return Err(Error::UnsuccessfulRequest(
+                "expected 200 from /blocks/_/transactions".into(),
+            ));
+        };
+
+        let header_path = format!("/blocks/{}/header", header_id);
+        let mut resp = self
+            .client
+            .get_async(with_path(&self.base_url, &header_path))
+            .await?;
+        let header = if resp.status().is_success() {
+            resp.json::<Header>().await?
+        } else {
+            return Err(Error::UnsuccessfulRequest(
+                "expected 200-200 from /blocks/_/header".into(),
+            ));
+        };
+        Ok(FullBlock {
+            header,
+            block_transactions,
+        })
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, Error> {
         let blocks = self
             .client
             .get_async(with_path(&self.base_url, &format!("/blocks/at/{}", height)))
             .await?
             .json::<Vec<BlockId>>()
             .await?;
-        if !blocks.is_empty() {
-            let header_id = base16::encode_lower(&blocks[0].0 .0);
-            let transactions_path = format!("/blocks/{}/transactions", header_id);
-            let mut resp = self
-                .client
-                .get_async(with_path(&self.base_url, &transactions_path))
-                .await?;
-            let block_transactions = if resp.status().is_success() {
-                resp.json::<BlockTransactions>().await?
-            } else {
-                return Err(Error::UnsuccessfulRequest(
-                    "expected 200 from /blocks/_/transactions".into(),
-                ));
-            };
-
-            let header_path = format!("/blocks/{}/header", header_id);
-            let mut resp = self
-                .client
-                .get_async(with_path(&self.base_url, &header_path))
-                .await?;
-            let header = if resp.status().is_success() {
-                resp.json::<Header>().await?
-            } else {
-                return Err(Error::UnsuccessfulRequest(
-                    "expected 200-200 from /blocks/_/header".into(),
-                ));
-            };
-            Ok(FullBlock {
-                header,
-                block_transactions,
-            })
-        } else {
-            Err(Error::NoBlock)
-        }
+        blocks.first().cloned().ok_or(Error::NoBlock)
     }
 
     async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, Error> {
@@ -142,6 +150,11 @@ where
         self.inner.get_block_at(height).await
     }
 
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, Error> {
+        trace!(target: "ergo_network", "get_block_id_at(height: {})", height);
+        self.inner.get_block_id_at(height).await
+    }
+
     async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, Error> {
         trace!(target: "ergo_network", "fetch_mempool(offset: {}, limit: {})", offset, limit);
         self.inner.fetch_mempool(offset, limit).await
@@ -152,3 +165,90 @@ where
         self.inner.get_best_height().await
     }
 }
+
+/// Node tip is re-requested at most once per this period while syncing.
+const TIP_REFRESH_SECS: i64 = 10;
+
+/// Exposes progress of the chain sync driven by the wrapped client as Prometheus gauges.
+pub struct ErgoNetworkMetrics<R> {
+    inner: R,
+    sync_height: IntGauge,
+    tip_height: IntGauge,
+    tip_distance: IntGauge,
+    tip_refreshed_at: Cell<i64>,
+}
+
+impl<R> ErgoNetworkMetrics<R> {
+    pub fn wrap(client: R, registry: &Registry) -> Self {
+        let sync_height = IntGauge::new("chain_sync_height", "Height of the last synced block").unwrap();
+        let tip_height =
+            IntGauge::new("chain_sync_node_tip_height", "Best height known to the node").unwrap();
+        let tip_distance = IntGauge::new(
+            "chain_sync_tip_distance",
+            "Number of blocks chain sync is behind the node tip",
+        )
+        .unwrap();
+        registry.register(Box::new(sync_height.clone())).unwrap();
+        registry.register(Box::new(tip_height.clone())).unwrap();
+        registry.register(Box::new(tip_distance.clone())).unwrap();
+        Self {
+            inner: client,
+            sync_height,
+            tip_height,
+            tip_distance,
+            tip_refreshed_at: Cell::new(0),
+        }
+    }
+
+    /// Track sync progress from upgrades emitted by chain sync, as blocks may be fetched
+    /// ahead of (and out of order with) what has actually been synced.
+    pub fn observe_upgrade(&self, upgrade: &ChainUpgrade) {
+        match upgrade {
+            ChainUpgrade::RollForward(blk) => self.sync_height.set(blk.height as i64),
+            ChainUpgrade::RollBackward(blk) => self.sync_height.set(blk.height.saturating_sub(1) as i64),
+        }
+        self.update_distance();
+    }
+
+    fn observe_tip(&self, tip: u32) {
+        self.tip_height.set(tip as i64);
+        self.update_distance();
+    }
+
+    fn update_distance(&self) {
+        self.tip_distance
+            .set((self.tip_height.get() - self.sync_height.get()).max(0));
+    }
+}
+
+#[async_trait(?Send)]
+impl<R> ErgoNetwork for ErgoNetworkMetrics<R>
+where
+    R: ErgoNetwork,
+{
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, Error> {
+        let now = Utc::now().timestamp();
+        if now - self.tip_refreshed_at.get() >= TIP_REFRESH_SECS {
+            // Claim the refresh up front so that concurrent prefetches don't repeat it.
+            self.tip_refreshed_at.set(now);
+            let _ = self.get_best_height().await;
+        }
+        self.inner.get_block_at(height).await
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, Error> {
+        self.inner.get_block_id_at(height).await
+    }
+
+    async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, Error> {
+        self.inner.fetch_mempool(offset, limit).await
+    }
+
+    async fn get_best_height(&self) -> Result<u32, Error> {
+        let res = self.inner.get_best_height().await;
+        if let Ok(tip) = res {
+            self.observe_tip(tip);
+        }
+        res
+    }
+}
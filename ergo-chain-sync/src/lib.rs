@@ -1,16 +1,19 @@
 use std::cell::{Cell, RefCell};
-use std::cmp::max;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
 use std::rc::Rc;
 use std::sync::Once;
 use std::time::Duration;
 
 use async_stream::stream;
+use futures::future::join_all;
 use futures::Stream;
 use futures_timer::Delay;
 use log::{error, info, trace};
 use pin_project::pin_project;
 
 use crate::cache::chain_cache::ChainCache;
+use crate::client::model::FullBlock;
 use crate::client::node::{ErgoNetwork, Error};
 use crate::model::Block;
 
@@ -69,10 +72,20 @@ where
         starting_height: u32,
         tip_reached_signal: Option<&'a Once>,
     ) -> ChainSync<TClient, TCache> {
-        ChainSync::init(starting_height, self.client, self.cache, tip_reached_signal).await
+        ChainSync::init(
+            starting_height,
+            self.client,
+            self.cache,
+            tip_reached_signal,
+            NO_PREFETCH,
+        )
+        .await
     }
 }
 
+/// Blocks are fetched one by one.
+pub const NO_PREFETCH: usize = 1;
+
 #[pin_project]
 pub struct ChainSync<'a, TClient, TCache> {
     starting_height: u32,
@@ -82,6 +95,12 @@ pub struct ChainSync<'a, TClient, TCache> {
     #[pin]
     delay: Cell<Option<Delay>>,
     tip_reached_signal: Option<&'a Once>,
+    /// Max number of heights fetched concurrently until the tip is reached.
+    prefetch_window: usize,
+    /// Blocks fetched ahead, ordered by height.
+    prefetched: RefCell<VecDeque<FullBlock>>,
+    /// Once the tip is reached blocks are fetched one by one.
+    caught_up: Cell<bool>,
 }
 
 impl<'a, TClient, TCache> ChainSync<'a, TClient, TCache>
@@ -94,6 +113,7 @@ where
         client: &'a TClient,
         mut cache: TCache,
         tip_reached_signal: Option<&'a Once>,
+        prefetch_window: usize,
     ) -> ChainSync<'a, TClient, TCache> {
         let best_block = cache.get_best_block().await;
         let start_at = if let Some(best_block) = best_block {
@@ -111,6 +131,70 @@ where
             })),
             delay: Cell::new(None),
             tip_reached_signal,
+            prefetch_window: max(prefetch_window, NO_PREFETCH),
+            prefetched: RefCell::new(VecDeque::new()),
+            caught_up: Cell::new(false),
+        }
+    }
+
+    /// Take the block at the given height from the prefetched ones, fetching it if not there.
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, Error> {
+        if let Some(blk) = self.take_prefetched(height) {
+            return Ok(blk);
+        }
+        if self.prefetch_window > NO_PREFETCH && !self.caught_up.get() {
+            self.prefetch(height).await;
+            if let Some(blk) = self.take_prefetched(height) {
+                return Ok(blk);
+            }
+        }
+        let res = self.client.get_block_at(height).await;
+        if let Err(Error::NoBlock) = res {
+            self.caught_up.set(true);
+        }
+        res
+    }
+
+    fn take_prefetched(&self, height: u32) -> Option<FullBlock> {
+        let mut prefetched = self.prefetched.borrow_mut();
+        match prefetched.front() {
+            Some(blk) if blk.header.height == height => prefetched.pop_front(),
+            Some(_) => {
+                // Sync went another way (e.g. rolled back), so prefetched blocks are useless.
+                prefetched.clear();
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Concurrently fetch blocks from the given height up to the tip, within the prefetch window.
+    async fn prefetch(&self, from_height: u32) {
+        let tip = match self.client.get_best_height().await {
+            Ok(tip) => tip,
+            Err(e) => {
+                error!(target: "chain_sync", "prefetch: {}", e);
+                return;
+            }
+        };
+        if tip <= from_height {
+            return;
+        }
+        let to_height = min(tip, from_height + self.prefetch_window as u32 - 1);
+        trace!(target: "chain_sync", "Prefetching heights [{}, {}]", from_height, to_height);
+        let blocks = join_all((from_height..=to_height).map(|height| self.client.get_block_at(height))).await;
+        let mut prefetched = self.prefetched.borrow_mut();
+        // Keep blocks up to the first failed height so that they are contiguous.
+        for blk in blocks {
+            match blk {
+                Ok(blk) => prefetched.push_back(blk),
+                Err(e) => {
+                    if !matches!(e, Error::NoBlock) {
+                        error!(target: "chain_sync", "prefetch: {}", e);
+                    }
+                    break;
+                }
+            }
         }
     }
 
@@ -120,7 +204,7 @@ where
     async fn try_upgrade(&self) -> Option<ChainUpgrade> {
         let next_height = { self.state.borrow().next_height };
         trace!(target: "chain_sync", "Processing height [{}]", next_height);
-        match self.client.get_block_at(next_height).await {
+        match self.get_block_at(next_height).await {
             Ok(api_blk) => {
                 info!(
                     target: "chain_sync",
@@ -9,14 +9,13 @@ use ergo_lib::chain::ergo_box::box_builder::{ErgoBoxCandidateBuilder, ErgoBoxCan
 
 use ergo_lib::chain::transaction::{Transaction, TxIoVec};
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
-use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder, NetworkPrefix};
+use ergo_lib::ergotree_ir::chain::address::Address;
 use ergo_lib::ergotree_ir::chain::ergo_box::box_value::{BoxValue, BoxValueError};
 use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisterId};
 use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount, TokenId};
 use ergo_lib::ergotree_ir::serialization::SigmaParsingError;
 use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::{ProveDlog, SigmaProp};
 use ergo_lib::wallet::box_selector::{BoxSelector, BoxSelectorError, SimpleBoxSelector};
-use ergo_lib::wallet::signing::TxSigningError;
 use ergo_lib::wallet::tx_builder::TxBuilderError;
 use isahc::prelude::Configurable;
 use isahc::{AsyncReadResponseExt, HttpClient};
@@ -34,12 +33,13 @@ use thiserror::Error;
 use ergo_chain_sync::client::types::{with_path, Url};
 use spectrum_offchain::transaction::TransactionCandidate;
 use spectrum_offchain_lm::data::pool::{Pool, ProgramConfig};
-use spectrum_offchain_lm::ergo::{NanoErg, MAX_VALUE, MIN_SAFE_BOX_VALUE, MIN_SAFE_FAT_BOX_VALUE};
-use spectrum_offchain_lm::prover::{SeedPhrase, SigmaProver, Wallet};
+use spectrum_offchain_lm::ergo::{NanoErg, Network, MAX_VALUE, MIN_SAFE_BOX_VALUE, MIN_SAFE_FAT_BOX_VALUE};
+use spectrum_offchain_lm::prover::{SeedPhrase, SigmaProver, SigningError, Wallet};
 
 pub struct Explorer {
     pub client: HttpClient,
     pub base_url: Url,
+    pub network: Network,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,7 +54,7 @@ impl Explorer {
                 &self.base_url,
                 &format!(
                     "/api/v1/boxes/unspent/byAddress/{}",
-                    AddressEncoder::encode_address_as_string(NetworkPrefix::Mainnet, addr),
+                    self.network.encode_address(addr),
                 ),
             ))
             .await
@@ -80,7 +80,7 @@ pub enum Error {
     #[error("tx builder error: {0}")]
     TxBuilder(TxBuilderError),
     #[error("tx signing error: {0}")]
-    TxSigning(TxSigningError),
+    TxSigning(SigningError),
     #[error("utxo error: {0:?}")]
     Utxo(UtxoError),
     #[error("pool validation error: {0:?}")]
@@ -98,6 +98,11 @@ pub struct DeployPoolConfig {
     num_epochs_to_delegate: u64,
     operator_funding_secret: SeedPhrase,
     max_number_expected_participants: u64,
+    /// Mainnet if omitted.
+    #[serde(default)]
+    network: Network,
+    /// Explorer of the configured network is used if omitted.
+    explorer_url: Option<Url>,
 }
 
 #[tokio::main]
@@ -116,10 +121,14 @@ async fn main() {
                 ))
                 .build()
                 .unwrap();
-            let explorer_url = Url::try_from(String::from("https://api.ergoplatform.com")).unwrap();
+            let explorer_url = config
+                .explorer_url
+                .clone()
+                .unwrap_or_else(|| config.network.default_explorer_url());
             let explorer = Explorer {
                 client: client.clone(),
                 base_url: explorer_url,
+                network: config.network,
             };
             let node = ErgoNodeHttpClient::new(client, config.node_addr.clone());
             match deploy_pool(config, &node, explorer).await {
@@ -142,8 +151,8 @@ async fn main() {
             }
         }
 
-        Command::GenerateNewWallet => {
-            generate_new_wallet();
+        Command::GenerateNewWallet { network } => {
+            generate_new_wallet(network);
         }
     }
 }
@@ -670,15 +679,12 @@ fn find_box_with_token(boxes: &[ErgoBox], token_id: &TokenId) -> Option<ErgoBox>
         .cloned()
 }
 
-fn generate_new_wallet() {
+fn generate_new_wallet(network: Network) {
     let mnemonic = generate_wallet::generate_bip39_mnemonic();
     println!("Mnemonic seed phrase(KEEP IT SAFE!): {}", mnemonic);
     let (_prover, funding_addr) = Wallet::try_from_seed(SeedPhrase::from(mnemonic)).expect("Invalid seed");
 
-    println!(
-        "Wallet's address: {}",
-        AddressEncoder::encode_address_as_string(NetworkPrefix::Mainnet, &funding_addr)
-    );
+    println!("Wallet's address: {}", network.encode_address(&funding_addr));
 }
 
 #[derive(Debug)]
@@ -778,5 +784,9 @@ enum Command {
         /// Path to the YAML configuration file.
         config_path: String,
     },
-    GenerateNewWallet,
+    GenerateNewWallet {
+        #[arg(long, short, default_value = "mainnet")]
+        /// Network to encode the address for, `mainnet` or `testnet`.
+        network: Network,
+    },
 }
@@ -0,0 +1,418 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use futures::channel::{mpsc, oneshot};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{error, info};
+use serde::Serialize;
+
+use spectrum_offchain::backlog::data::{BacklogEntry, OrderStage, OrderWeight, Suspension};
+use spectrum_offchain::data::{Has, OnChainOrder};
+use spectrum_offchain::executor::OrderType;
+use spectrum_offchain::tx_tracker::TxStatus;
+
+use crate::data::bundle::StakingBundle;
+use crate::data::funding::DistributionFunding;
+use crate::data::order::Order;
+use crate::data::pool::{Pool, ProgramConfig};
+use crate::data::{AsBox, BundleId, FundingId, OrderId, PoolId};
+use crate::ergo::NanoErg;
+use crate::scheduler::data::{Tick, UpcomingTick};
+
+pub mod process;
+
+/// Read-only queries to the state of the bot.
+#[derive(Debug, Clone)]
+pub enum AdminQuery {
+    /// All orders in the backlog along with their stage, weight and failure history.
+    Backlog,
+    /// All known states of the given pool.
+    Pool(PoolId),
+    /// Bundles of the given pool that are eligible for compounding in the given epoch.
+    Bundles { pool_id: PoolId, epoch_ix: u32 },
+    /// Ticks waiting to be processed by the scheduler.
+    Schedule,
+    /// All known LM programs.
+    Programs,
+    /// Funding boxes available for compounding, boxes selected for txs in flight are left out.
+    Funding,
+    /// Status of the given submitted transaction.
+    Tx(TxId),
+}
+
+/// Operator interventions. Available only to authenticated clients.
+#[derive(Debug, Clone)]
+pub enum AdminCommand {
+    /// Remove the given order from the backlog.
+    RemoveOrder(OrderId),
+    /// Move the given suspended order back to pending.
+    RetryOrder(OrderId),
+    /// Override weight of the given order.
+    SetOrderWeight(OrderId, OrderWeight),
+    /// Re-enqueue compounding of the given epoch of the pool.
+    RescheduleTick { pool_id: PoolId, epoch_ix: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub enum AdminAction {
+    Query(AdminQuery),
+    Command(AdminCommand),
+}
+
+pub struct AdminRequest {
+    pub action: AdminAction,
+    pub reply: oneshot::Sender<AdminResponse>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum AdminResponse {
+    Orders(Vec<OrderView>),
+    Pool(PoolStates),
+    Bundles(Vec<BundleView>),
+    Ticks(Vec<TickView>),
+    Tick(TickView),
+    Programs(Vec<ProgramView>),
+    Funding(Vec<FundingView>),
+    Tx(TxView),
+    Done,
+    NotFound,
+}
+
+/// An entity along with the box it is represented by on-chain.
+#[derive(Serialize)]
+pub struct EntityView<T> {
+    #[serde(rename = "box")]
+    pub bx: ErgoBox,
+    pub entity: T,
+}
+
+impl<T> From<AsBox<T>> for EntityView<T> {
+    fn from(AsBox(bx, entity): AsBox<T>) -> Self {
+        Self { bx, entity }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OrderView {
+    pub order_id: String,
+    pub pool_id: String,
+    pub order_type: String,
+    pub stage: &'static str,
+    pub weight: OrderWeight,
+    /// Failure history of a suspended order.
+    pub suspension: Option<Suspension>,
+}
+
+impl From<BacklogEntry<Order>> for OrderView {
+    fn from(entry: BacklogEntry<Order>) -> Self {
+        let (stage, suspension) = match entry.stage {
+            OrderStage::Pending => ("pending", None),
+            OrderStage::Suspended(suspension) => ("suspended", Some(suspension)),
+            OrderStage::Progressing(_) => ("progressing", None),
+        };
+        Self {
+            order_id: entry.order.get_self_ref().to_string(),
+            pool_id: entry.order.get_entity_ref().to_string(),
+            order_type: format!("{:?}", entry.order.get::<OrderType>()),
+            stage,
+            weight: entry.weight,
+            suspension,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PoolStates {
+    pub confirmed: Option<EntityView<Pool>>,
+    pub unconfirmed: Option<EntityView<Pool>>,
+    pub predicted: Option<EntityView<Pool>>,
+    /// The state the executor would use as of now.
+    pub resolved: Option<EntityView<Pool>>,
+}
+
+#[derive(Serialize)]
+pub struct BundleView {
+    pub bundle_id: String,
+    pub state: Option<EntityView<StakingBundle>>,
+}
+
+impl BundleView {
+    pub fn new(bundle_id: BundleId, state: Option<AsBox<StakingBundle>>) -> Self {
+        Self {
+            bundle_id: bundle_id.to_string(),
+            state: state.map(EntityView::from),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TickView {
+    pub pool_id: String,
+    pub epoch_ix: u32,
+    pub height: u32,
+    pub deferred_until: Option<i64>,
+}
+
+impl From<UpcomingTick> for TickView {
+    fn from(UpcomingTick { tick, deferred_until }: UpcomingTick) -> Self {
+        Self {
+            pool_id: tick.pool_id.to_string(),
+            epoch_ix: tick.epoch_ix,
+            height: tick.height,
+            deferred_until,
+        }
+    }
+}
+
+impl From<Tick> for TickView {
+    fn from(tick: Tick) -> Self {
+        Self::from(UpcomingTick {
+            tick,
+            deferred_until: None,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProgramView {
+    pub pool_id: String,
+    pub conf: ProgramConfig,
+}
+
+#[derive(Serialize)]
+pub struct FundingView {
+    pub id: FundingId,
+    pub erg_value: NanoErg,
+}
+
+impl From<AsBox<DistributionFunding>> for FundingView {
+    fn from(AsBox(_, df): AsBox<DistributionFunding>) -> Self {
+        Self {
+            id: df.id,
+            erg_value: df.erg_value,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TxView {
+    pub tx_id: String,
+    pub status: TxStatus,
+}
+
+/// Serve the admin API at the given `addr`. Requests are forwarded to `requests`
+/// and answered by `admin_request_stream`.
+/// Commands require `Authorization: Bearer <token>` and are disabled if no `token` is configured.
+pub async fn serve_admin_api(
+    addr: SocketAddr,
+    token: Option<String>,
+    requests: mpsc::UnboundedSender<AdminRequest>,
+) {
+    let token = Arc::new(token);
+    let make_svc = make_service_fn(move |_| {
+        let requests = requests.clone();
+        let token = Arc::clone(&token);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle(req, Arc::clone(&token), requests.clone())
+            }))
+        }
+    });
+    info!(target: "admin", "Serving admin API at {}", addr);
+    if let Err(err) = Server::bind(&addr).serve(make_svc).await {
+        error!(target: "admin", "Admin API server failed: {}", err);
+    }
+}
+
+async fn handle(
+    req: Request<Body>,
+    token: Arc<Option<String>>,
+    requests: mpsc::UnboundedSender<AdminRequest>,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path();
+    let action = if req.method() == Method::GET {
+        parse_query(path).map(AdminAction::Query)
+    } else {
+        match token.as_deref() {
+            None => Err(StatusCode::FORBIDDEN),
+            Some(token) if !is_authorized(&req, token) => Err(StatusCode::UNAUTHORIZED),
+            Some(_) => parse_command(req.method(), path).map(AdminAction::Command),
+        }
+    };
+    let action = match action {
+        Ok(action) => action,
+        Err(code) => return Ok(status(code)),
+    };
+    let (reply, response) = oneshot::channel();
+    if requests.unbounded_send(AdminRequest { action, reply }).is_err() {
+        return Ok(status(StatusCode::SERVICE_UNAVAILABLE));
+    }
+    let resp = match response.await {
+        Ok(AdminResponse::Done) => status(StatusCode::NO_CONTENT),
+        Ok(AdminResponse::NotFound) => status(StatusCode::NOT_FOUND),
+        Ok(resp) => json(&resp),
+        Err(_) => status(StatusCode::SERVICE_UNAVAILABLE),
+    };
+    Ok(resp)
+}
+
+fn is_authorized(req: &Request<Body>, token: &str) -> bool {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+        .unwrap_or(false)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn parse_query(path: &str) -> Result<AdminQuery, StatusCode> {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["backlog"] => Ok(AdminQuery::Backlog),
+        ["pools", pool_id] => parse_pool_id(pool_id).map(AdminQuery::Pool),
+        ["pools", pool_id, "bundles", epoch_ix] => {
+            let pool_id = parse_pool_id(pool_id)?;
+            let epoch_ix = epoch_ix.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            Ok(AdminQuery::Bundles { pool_id, epoch_ix })
+        }
+        ["schedule"] => Ok(AdminQuery::Schedule),
+        ["programs"] => Ok(AdminQuery::Programs),
+        ["funding"] => Ok(AdminQuery::Funding),
+        ["txs", tx_id] => Digest32::try_from(tx_id.to_string())
+            .map(|d| AdminQuery::Tx(TxId(d)))
+            .map_err(|_| StatusCode::BAD_REQUEST),
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+fn parse_command(method: &Method, path: &str) -> Result<AdminCommand, StatusCode> {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    match (method, segments.as_slice()) {
+        (&Method::DELETE, ["backlog", order_id]) => parse_order_id(order_id).map(AdminCommand::RemoveOrder),
+        (&Method::POST, ["backlog", order_id, "retry"]) => {
+            parse_order_id(order_id).map(AdminCommand::RetryOrder)
+        }
+        (&Method::POST, ["backlog", order_id, "weight", weight]) => {
+            let order_id = parse_order_id(order_id)?;
+            let weight = weight.parse::<u64>().map_err(|_| StatusCode::BAD_REQUEST)?;
+            Ok(AdminCommand::SetOrderWeight(order_id, OrderWeight::from(weight)))
+        }
+        (&Method::POST, ["pools", pool_id, "ticks", epoch_ix]) => {
+            let pool_id = parse_pool_id(pool_id)?;
+            let epoch_ix = epoch_ix.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            Ok(AdminCommand::RescheduleTick { pool_id, epoch_ix })
+        }
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+fn parse_pool_id(s: &str) -> Result<PoolId, StatusCode> {
+    Digest32::try_from(s.to_string())
+        .map(|d| PoolId::from(TokenId::from(d)))
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+fn parse_order_id(s: &str) -> Result<OrderId, StatusCode> {
+    Digest32::try_from(s.to_string())
+        .map(OrderId::from)
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+fn json<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec(value) {
+        Ok(bytes) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(bytes))
+            .unwrap(),
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap(),
+    }
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    Response::builder().status(code).body(Body::empty()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergo_chain_types::Digest32;
+    use ergo_lib::ergotree_ir::chain::token::TokenId;
+    use hyper::{Method, StatusCode};
+
+    use crate::admin::{constant_time_eq, parse_command, parse_query, AdminCommand, AdminQuery};
+    use crate::data::PoolId;
+
+    #[test]
+    fn parse_bundles_query() {
+        let pool_id = PoolId::from(TokenId::from(Digest32::from([1u8; 32])));
+        let path = format!("/pools/{}/bundles/3", pool_id);
+        match parse_query(&path) {
+            Ok(AdminQuery::Bundles {
+                pool_id: pid,
+                epoch_ix,
+            }) => {
+                assert_eq!(pid, pool_id);
+                assert_eq!(epoch_ix, 3);
+            }
+            other => panic!("Unexpected query: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_tx_query() {
+        let tx_id = "2".repeat(64);
+        assert!(matches!(
+            parse_query(&format!("/txs/{}", tx_id)),
+            Ok(AdminQuery::Tx(id)) if String::from(id) == tx_id
+        ));
+        assert!(matches!(parse_query("/txs/xyz"), Err(StatusCode::BAD_REQUEST)));
+    }
+
+    #[test]
+    fn reject_malformed_pool_id() {
+        assert!(matches!(parse_query("/pools/xyz"), Err(StatusCode::BAD_REQUEST)));
+        assert!(matches!(parse_query("/unknown"), Err(StatusCode::NOT_FOUND)));
+    }
+
+    #[test]
+    fn parse_commands() {
+        let id = "1".repeat(64);
+        assert!(matches!(
+            parse_command(&Method::DELETE, &format!("/backlog/{}", id)),
+            Ok(AdminCommand::RemoveOrder(_))
+        ));
+        assert!(matches!(
+            parse_command(&Method::POST, &format!("/backlog/{}/weight/10", id)),
+            Ok(AdminCommand::SetOrderWeight(_, _))
+        ));
+        assert!(matches!(
+            parse_command(&Method::POST, &format!("/pools/{}/ticks/2", id)),
+            Ok(AdminCommand::RescheduleTick { epoch_ix: 2, .. })
+        ));
+        assert!(matches!(
+            parse_command(&Method::GET, &format!("/backlog/{}/retry", id)),
+            Err(StatusCode::NOT_FOUND)
+        ));
+    }
+
+    #[test]
+    fn compare_tokens() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret1"));
+    }
+}
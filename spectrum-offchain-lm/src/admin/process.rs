@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use log::info;
+use tokio::sync::Mutex;
+
+use spectrum_offchain::backlog::Backlog;
+use spectrum_offchain::box_resolver::persistence::EntityRepo;
+use spectrum_offchain::box_resolver::resolve_entity_state;
+use spectrum_offchain::tx_tracker::TxTracker;
+
+use crate::admin::{
+    AdminAction, AdminCommand, AdminQuery, AdminRequest, AdminResponse, BundleView, EntityView, FundingView,
+    OrderView, PoolStates, ProgramView, TickView, TxView,
+};
+use crate::bundle::{resolve_bundle_state, BundleRepo};
+use crate::data::order::Order;
+use crate::data::pool::Pool;
+use crate::data::AsBox;
+use crate::funding::FundingRepo;
+use crate::program::ProgramRepo;
+use crate::scheduler::ScheduleRepo;
+
+/// Answers admin queries and applies admin commands against the state of the bot.
+/// Requests are processed in line with the rest of the app, so repos are only ever touched from one task.
+pub fn admin_request_stream<'a, S, TBacklog, TPools, TBundles, TSchedules, TPrograms, TFunding, TTracker>(
+    upstream: S,
+    backlog: Arc<Mutex<TBacklog>>,
+    pools: Arc<Mutex<TPools>>,
+    bundles: Arc<Mutex<TBundles>>,
+    schedules: Arc<Mutex<TSchedules>>,
+    programs: Arc<Mutex<TPrograms>>,
+    funding: Arc<Mutex<TFunding>>,
+    tx_tracker: Arc<Mutex<TTracker>>,
+) -> impl Stream<Item = ()> + 'a
+where
+    S: Stream<Item = AdminRequest> + 'a,
+    TBacklog: Backlog<Order> + 'a,
+    TPools: EntityRepo<AsBox<Pool>> + 'a,
+    TBundles: BundleRepo + 'a,
+    TSchedules: ScheduleRepo + 'a,
+    TPrograms: ProgramRepo + 'a,
+    TFunding: FundingRepo + 'a,
+    TTracker: TxTracker + 'a,
+{
+    upstream.then(move |AdminRequest { action, reply }| {
+        let backlog = Arc::clone(&backlog);
+        let pools = Arc::clone(&pools);
+        let bundles = Arc::clone(&bundles);
+        let schedules = Arc::clone(&schedules);
+        let programs = Arc::clone(&programs);
+        let funding = Arc::clone(&funding);
+        let tx_tracker = Arc::clone(&tx_tracker);
+        async move {
+            let resp = match action {
+                AdminAction::Query(AdminQuery::Backlog) => {
+                    let orders = backlog.lock().await.find_entries().await;
+                    AdminResponse::Orders(orders.into_iter().map(OrderView::from).collect())
+                }
+                AdminAction::Query(AdminQuery::Pool(pool_id)) => {
+                    let (confirmed, unconfirmed, predicted) = {
+                        let repo = pools.lock().await;
+                        (
+                            repo.get_last_confirmed(pool_id).await,
+                            repo.get_last_unconfirmed(pool_id).await,
+                            repo.get_last_predicted(pool_id).await,
+                        )
+                    };
+                    let resolved = resolve_entity_state::<AsBox<Pool>, _>(pool_id, pools).await;
+                    if confirmed.is_some() || unconfirmed.is_some() || predicted.is_some() {
+                        AdminResponse::Pool(PoolStates {
+                            confirmed: confirmed.map(|c| EntityView::from(c.0)),
+                            unconfirmed: unconfirmed.map(|u| EntityView::from(u.0)),
+                            predicted: predicted.map(|p| EntityView::from(p.0)),
+                            resolved: resolved.map(EntityView::from),
+                        })
+                    } else {
+                        AdminResponse::NotFound
+                    }
+                }
+                AdminAction::Query(AdminQuery::Bundles { pool_id, epoch_ix }) => {
+                    let bundle_ids = bundles.lock().await.select(pool_id, epoch_ix).await;
+                    let mut views = Vec::new();
+                    for bundle_id in bundle_ids {
+                        let state = resolve_bundle_state(bundle_id, Arc::clone(&bundles)).await;
+                        views.push(BundleView::new(bundle_id, state));
+                    }
+                    AdminResponse::Bundles(views)
+                }
+                AdminAction::Query(AdminQuery::Schedule) => {
+                    let ticks = schedules.lock().await.upcoming().await;
+                    AdminResponse::Ticks(ticks.into_iter().map(TickView::from).collect())
+                }
+                AdminAction::Query(AdminQuery::Programs) => {
+                    let progs = programs.lock().await.get_all().await;
+                    AdminResponse::Programs(
+                        progs
+                            .into_iter()
+                            .map(|(pool_id, conf)| ProgramView {
+                                pool_id: pool_id.to_string(),
+                                conf,
+                            })
+                            .collect(),
+                    )
+                }
+                AdminAction::Query(AdminQuery::Funding) => {
+                    let funds = funding.lock().await.get_available().await;
+                    AdminResponse::Funding(funds.into_iter().map(FundingView::from).collect())
+                }
+                AdminAction::Query(AdminQuery::Tx(tx_id)) => match tx_tracker.lock().await.status(tx_id) {
+                    Some(status) => AdminResponse::Tx(TxView {
+                        tx_id: String::from(tx_id),
+                        status,
+                    }),
+                    None => AdminResponse::NotFound,
+                },
+                AdminAction::Command(cmd) => {
+                    info!(target: "admin", "Applying {:?}", cmd);
+                    match cmd {
+                        AdminCommand::RemoveOrder(order_id) => {
+                            let mut backlog = backlog.lock().await;
+                            if backlog.exists(order_id).await {
+                                backlog.remove(order_id).await;
+                                AdminResponse::Done
+                            } else {
+                                AdminResponse::NotFound
+                            }
+                        }
+                        AdminCommand::RetryOrder(order_id) => {
+                            done_or_not_found(backlog.lock().await.unsuspend(order_id).await)
+                        }
+                        AdminCommand::SetOrderWeight(order_id, weight) => {
+                            done_or_not_found(backlog.lock().await.set_weight(order_id, weight).await)
+                        }
+                        AdminCommand::RescheduleTick { pool_id, epoch_ix } => {
+                            match schedules.lock().await.reschedule(pool_id, epoch_ix).await {
+                                Some(tick) => AdminResponse::Tick(TickView::from(tick)),
+                                None => AdminResponse::NotFound,
+                            }
+                        }
+                    }
+                }
+            };
+            // The requester may have gone away, nothing to do then.
+            let _ = reply.send(resp);
+        }
+    })
+}
+
+fn done_or_not_found(done: bool) -> AdminResponse {
+    if done {
+        AdminResponse::Done
+    } else {
+        AdminResponse::NotFound
+    }
+}
@@ -10,6 +10,7 @@ use spectrum_offchain::data::OnChainEntity;
 use crate::data::bundle::{IndexedStakingBundle, StakingBundle};
 use crate::data::{AsBox, BundleId, BundleStateId, PoolId};
 
+pub mod in_memory;
 pub mod process;
 pub mod rocksdb;
 
@@ -188,7 +189,7 @@ mod tests {
     use crate::data::{AsBox, BundleStateId};
     use crate::validators::BUNDLE_VALIDATOR;
 
-    use super::{rocksdb::BundleRepoRocksDB, BundleRepo, StakingBundle};
+    use super::{in_memory::InMemoryBundleRepo, rocksdb::BundleRepoRocksDB, BundleRepo, StakingBundle};
 
     #[tokio::test]
     async fn test_rocksdb_may_exist() {
@@ -220,6 +221,31 @@ mod tests {
         test_bundle_repo_select(client).await;
     }
 
+    #[tokio::test]
+    async fn test_in_memory_may_exist() {
+        test_bundle_repo_may_exist(InMemoryBundleRepo::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_predicted() {
+        test_bundle_repo_predicted(InMemoryBundleRepo::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_confirmed() {
+        test_bundle_repo_confirmed(InMemoryBundleRepo::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_invalidate() {
+        test_bundle_repo_invalidate(InMemoryBundleRepo::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_select() {
+        test_bundle_repo_select(InMemoryBundleRepo::new()).await;
+    }
+
     async fn test_bundle_repo_may_exist<C: BundleRepo>(client: C) {
         let pool_id_0 = PoolId::from(force_any_val::<TokenId>());
         let bundles = gen_bundles(pool_id_0, 0, 3);
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use indexmap::IndexSet;
+use parking_lot::Mutex;
+
+use spectrum_offchain::data::unique_entity::{Confirmed, Predicted, Traced};
+use spectrum_offchain::data::OnChainEntity;
+
+use crate::data::bundle::IndexedStakingBundle;
+use crate::data::{AsBox, BundleId, BundleStateId, PoolId};
+
+use super::{BundleRepo, StakingBundle};
+
+#[derive(Default)]
+struct Inner {
+    states: HashMap<BundleStateId, AsBox<IndexedStakingBundle>>,
+    prediction_links: HashMap<BundleStateId, BundleStateId>,
+    last_predicted: HashMap<BundleId, BundleStateId>,
+    last_confirmed: HashMap<BundleId, BundleStateId>,
+    epoch_index: HashMap<(PoolId, u32), IndexSet<BundleId>>,
+}
+
+impl Inner {
+    fn index_epoch(&mut self, pool_id: PoolId, epoch_ix: u32, bundle_id: BundleId) {
+        self.epoch_index
+            .entry((pool_id, epoch_ix))
+            .or_default()
+            .insert(bundle_id);
+    }
+
+    fn unindex_epoch(&mut self, pool_id: PoolId, epoch_ix: u32, bundle_id: BundleId) {
+        if let Some(bundles) = self.epoch_index.get_mut(&(pool_id, epoch_ix)) {
+            bundles.shift_remove(&bundle_id);
+            if bundles.is_empty() {
+                self.epoch_index.remove(&(pool_id, epoch_ix));
+            }
+        }
+    }
+}
+
+/// In-memory implementation of `BundleRepo` mirroring semantics of `BundleRepoRocksDB`.
+#[derive(Default)]
+pub struct InMemoryBundleRepo {
+    inner: Mutex<Inner>,
+}
+
+impl InMemoryBundleRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait(?Send)]
+impl BundleRepo for InMemoryBundleRepo {
+    async fn select(&self, pool_id: PoolId, epoch_ix: u32) -> Vec<BundleId> {
+        self.inner
+            .lock()
+            .epoch_index
+            .get(&(pool_id, epoch_ix))
+            .map(|bundles| bundles.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    async fn may_exist(&self, sid: BundleStateId) -> bool {
+        self.inner.lock().states.contains_key(&sid)
+    }
+
+    async fn get_state(&self, state_id: BundleStateId) -> Option<AsBox<IndexedStakingBundle>> {
+        self.inner.lock().states.get(&state_id).cloned()
+    }
+
+    async fn invalidate(&self, state_id: BundleStateId) {
+        let mut inner = self.inner.lock();
+        inner.states.remove(&state_id);
+        inner.prediction_links.remove(&state_id);
+    }
+
+    async fn eliminate(&self, bundle_st: IndexedStakingBundle) {
+        let bundle_id = bundle_st.get_self_ref();
+        let mut inner = self.inner.lock();
+        inner.last_confirmed.remove(&bundle_id);
+        inner.last_predicted.remove(&bundle_id);
+        inner.unindex_epoch(bundle_st.bundle.pool_id, bundle_st.lower_epoch_ix, bundle_id);
+    }
+
+    async fn put_confirmed(&self, Confirmed(bundle_state): Confirmed<AsBox<IndexedStakingBundle>>) {
+        let bundle_id = bundle_state.get_self_ref();
+        let sid = bundle_state.get_self_state_ref();
+        let pool_id = bundle_state.1.bundle.pool_id;
+        let epoch_ix = bundle_state.1.lower_epoch_ix;
+        let mut inner = self.inner.lock();
+        inner.index_epoch(pool_id, epoch_ix, bundle_id);
+        inner.unindex_epoch(pool_id, epoch_ix.saturating_sub(1), bundle_id);
+        inner.last_confirmed.insert(bundle_id, sid);
+        inner.states.insert(sid, bundle_state);
+    }
+
+    async fn put_predicted(
+        &self,
+        Traced {
+            state: Predicted(bundle_state),
+            prev_state_id,
+        }: Traced<Predicted<AsBox<IndexedStakingBundle>>>,
+    ) {
+        let bundle_id = bundle_state.get_self_ref();
+        let sid = bundle_state.get_self_state_ref();
+        let mut inner = self.inner.lock();
+        inner.index_epoch(
+            bundle_state.1.bundle.pool_id,
+            bundle_state.1.lower_epoch_ix,
+            bundle_id,
+        );
+        inner.last_predicted.insert(bundle_id, sid);
+        if let Some(prev_sid) = prev_state_id {
+            inner.prediction_links.insert(sid, prev_sid);
+        }
+        inner.states.insert(sid, bundle_state);
+    }
+
+    async fn get_last_confirmed(&self, id: BundleId) -> Option<Confirmed<AsBox<StakingBundle>>> {
+        let inner = self.inner.lock();
+        inner
+            .last_confirmed
+            .get(&id)
+            .and_then(|sid| inner.states.get(sid))
+            .cloned()
+            .map(|as_box| Confirmed(as_box.map(|ib| ib.bundle)))
+    }
+
+    async fn get_last_predicted(&self, id: BundleId) -> Option<Predicted<AsBox<StakingBundle>>> {
+        let inner = self.inner.lock();
+        inner
+            .last_predicted
+            .get(&id)
+            .and_then(|sid| inner.states.get(sid))
+            .cloned()
+            .map(|as_box| Predicted(as_box.map(|ib| ib.bundle)))
+    }
+
+    async fn get_prediction_predecessor(&self, id: BundleStateId) -> Option<BundleStateId> {
+        self.inner.lock().prediction_links.get(&id).copied()
+    }
+}
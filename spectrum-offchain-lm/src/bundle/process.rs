@@ -4,7 +4,7 @@ use futures::{Stream, StreamExt};
 use tokio::sync::Mutex;
 
 use spectrum_offchain::combinators::EitherOrBoth;
-use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate};
+use spectrum_offchain::data::unique_entity::{Confirmed, Predicted, StateUpdate, Traced, Unconfirmed};
 use spectrum_offchain::data::OnChainEntity;
 
 use crate::bundle::BundleRepo;
@@ -38,3 +38,46 @@ where
         }
     })
 }
+
+/// Unconfirmed bundle states are tracked as predictions chained off the state they consume,
+/// so that `resolve_bundle_state` picks them up as long as they link to the confirmed state.
+pub fn unconfirmed_bundle_update_stream<'a, S, TBundles>(
+    upstream: S,
+    bundles: Arc<Mutex<TBundles>>,
+) -> impl Stream<Item = ()> + 'a
+where
+    S: Stream<Item = Unconfirmed<StateUpdate<AsBox<IndexedStakingBundle>>>> + 'a,
+    TBundles: BundleRepo + 'a,
+{
+    upstream.then(move |Unconfirmed(upd)| {
+        let bundles = Arc::clone(&bundles);
+        async move {
+            let bundles = bundles.lock().await;
+            match upd {
+                StateUpdate::Transition(EitherOrBoth::Both(AsBox(_, prev_st), new_state)) => {
+                    bundles
+                        .put_predicted(Traced {
+                            state: Predicted(new_state),
+                            prev_state_id: Some(prev_st.get_self_state_ref()),
+                        })
+                        .await
+                }
+                StateUpdate::Transition(EitherOrBoth::Right(new_state)) => {
+                    bundles
+                        .put_predicted(Traced {
+                            state: Predicted(new_state),
+                            prev_state_id: None,
+                        })
+                        .await
+                }
+                // Bundle is eliminated only once the consuming tx is confirmed.
+                StateUpdate::Transition(EitherOrBoth::Left(_)) => {}
+                StateUpdate::TransitionRollback(EitherOrBoth::Left(AsBox(_, st)))
+                | StateUpdate::TransitionRollback(EitherOrBoth::Both(AsBox(_, st), _)) => {
+                    bundles.invalidate(st.get_self_state_ref()).await
+                }
+                StateUpdate::TransitionRollback(EitherOrBoth::Right(_)) => {}
+            }
+        }
+    })
+}
@@ -1,4 +1,4 @@
-use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder};
+use ergo_lib::ergotree_ir::chain::address::Address;
 use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate, NonMandatoryRegisters};
 use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
 use serde::Deserialize;
@@ -6,7 +6,7 @@ use serde::Deserialize;
 use spectrum_offchain::event_sink::handlers::types::{IntoBoxCandidate, TryFromBoxCtx};
 
 use crate::data::{AsBox, FundingId};
-use crate::ergo::NanoErg;
+use crate::ergo::{NanoErg, Network};
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct DistributionFundingProto {
@@ -58,35 +58,67 @@ pub enum FundingUpdate {
     FundingEliminated(FundingId),
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(try_from = "String")]
-pub struct ExecutorWallet(Address);
+pub struct ExecutorWallet {
+    addr: Address,
+    /// Network the address was encoded for, if it was parsed from its encoded form.
+    network: Option<Network>,
+}
 
 impl ExecutorWallet {
     pub fn ergo_tree(&self) -> ErgoTree {
-        self.0.script().unwrap()
+        self.addr.script().unwrap()
+    }
+
+    /// Make sure the address wasn't encoded for a network other than the given one.
+    pub fn check_network(&self, network: Network) -> Result<(), String> {
+        match self.network {
+            Some(encoded_for) if encoded_for != network => Err(format!(
+                "Address {} is encoded for {:?} while operating in {:?}",
+                encoded_for.encode_address(&self.addr),
+                encoded_for,
+                network
+            )),
+            _ => Ok(()),
+        }
     }
 }
 
+impl PartialEq for ExecutorWallet {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr == other.addr
+    }
+}
+
+impl Eq for ExecutorWallet {}
+
 impl TryFrom<String> for ExecutorWallet {
     type Error = String;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        AddressEncoder::unchecked_parse_address_from_str(&value)
-            .map(Self)
-            .map_err(|err| err.to_string())
+        Network::of_address(&value).map(|(network, addr)| Self {
+            addr,
+            network: Some(network),
+        })
     }
 }
 
 impl From<Address> for ExecutorWallet {
     fn from(addr: Address) -> Self {
-        Self(addr)
+        Self { addr, network: None }
+    }
+}
+
+impl From<ExecutorWallet> for Address {
+    fn from(wallet: ExecutorWallet) -> Self {
+        wallet.addr
     }
 }
 
 impl TryFromBoxCtx<ExecutorWallet> for DistributionFunding {
-    fn try_from_box(bx: ErgoBox, ExecutorWallet(addr): ExecutorWallet) -> Option<Self> {
-        if bx.ergo_tree == addr.script().unwrap() {
+    fn try_from_box(bx: ErgoBox, wallet: ExecutorWallet) -> Option<Self> {
+        if bx.ergo_tree == wallet.ergo_tree() {
             Some(Self {
                 id: FundingId::from(bx.box_id()),
                 prop: bx.ergo_tree,
@@ -103,3 +135,22 @@ impl IntoBoxCandidate for DistributionFunding {
         DistributionFundingProto::from(self).into_candidate(height)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::address::Address;
+    use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+    use sigma_test_util::force_any_val;
+
+    use crate::data::funding::ExecutorWallet;
+    use crate::ergo::Network;
+
+    #[test]
+    fn wallet_is_bound_to_network_it_was_encoded_for() {
+        let addr = Address::P2Pk(force_any_val::<ProveDlog>());
+        let wallet = ExecutorWallet::try_from(Network::Testnet.encode_address(&addr)).unwrap();
+        assert_eq!(wallet, ExecutorWallet::from(addr));
+        assert!(wallet.check_network(Network::Testnet).is_ok());
+        assert!(wallet.check_network(Network::Mainnet).is_err());
+    }
+}
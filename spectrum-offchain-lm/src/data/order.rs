@@ -23,7 +23,7 @@ use spectrum_offchain::data::unique_entity::Predicted;
 use spectrum_offchain::data::{Has, OnChainOrder};
 use spectrum_offchain::domain::TypedAssetAmount;
 use spectrum_offchain::event_sink::handlers::types::{IntoBoxCandidate, TryFromBox};
-use spectrum_offchain::executor::RunOrderError;
+use spectrum_offchain::executor::{OrderType, RunOrderError};
 use spectrum_offchain::transaction::{TransactionCandidate, UnsignedTransactionOps};
 
 use crate::data::assets::{BundleKey, Lq};
@@ -690,6 +690,16 @@ impl OnChainOrder for Order {
     }
 }
 
+impl Has<OrderType> for Order {
+    fn get<U: IsEqual<OrderType>>(&self) -> OrderType {
+        match self {
+            Order::Deposit(_) => OrderType::Deposit,
+            Order::Redeem(_) => OrderType::Redeem,
+            Order::Compound(_) => OrderType::Compound,
+        }
+    }
+}
+
 impl Has<Vec<BundleId>> for Order {
     fn get<U: IsEqual<Vec<BundleId>>>(&self) -> Vec<BundleId> {
         match self {
@@ -715,9 +725,10 @@ impl Weighted for Order {
         match self {
             Order::Deposit(AsBox(_, deposit)) => OrderWeight::from(<u64>::from(deposit.erg_value)),
             Order::Redeem(AsBox(_, redeem)) => OrderWeight::from(<u64>::from(redeem.erg_value)),
-            Order::Compound(compound) => {
-                OrderWeight::from(compound.epoch_ix as u64 * compound.queue_ix as u64 * COMPOUND_BASE_WEIGHT)
-            }
+            // Queue indexes start from 0, so shift them to keep the weight of the first queue non-zero.
+            Order::Compound(compound) => OrderWeight::from(
+                compound.epoch_ix as u64 * (compound.queue_ix as u64 + 1) * COMPOUND_BASE_WEIGHT,
+            ),
         }
     }
 }
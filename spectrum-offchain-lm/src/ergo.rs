@@ -1,12 +1,17 @@
+use std::str::FromStr;
+
 use derive_more::{Add, Display, Div, From, Into, Mul, Sub, Sum};
 use ergo_lib::chain::transaction::prover_result::ProverResult;
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::{ContextExtension, ProofBytes};
+use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder, NetworkPrefix};
 use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
 use ergo_lib::ergotree_ir::ergo_tree::{ErgoTree, ErgoTreeHeader};
 use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::SigmaProp;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
+use ergo_chain_sync::client::types::Url;
+
 /// Max amount of tokens allowed in Ergo.
 pub const MAX_VALUE: u64 = 0x7fffffffffffffff;
 pub const UNIT_VALUE: u64 = 1;
@@ -66,3 +71,64 @@ impl From<NanoErg> for BoxValue {
 pub fn default_sigma_prop_tree(prop: SigmaProp) -> ErgoTree {
     ErgoTree::new(ErgoTreeHeader::v0(false), &prop.into()).unwrap()
 }
+
+/// Ergo network the bot operates in.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn prefix(self) -> NetworkPrefix {
+        match self {
+            Network::Mainnet => NetworkPrefix::Mainnet,
+            Network::Testnet => NetworkPrefix::Testnet,
+        }
+    }
+
+    pub fn encode_address(self, addr: &Address) -> String {
+        AddressEncoder::encode_address_as_string(self.prefix(), addr)
+    }
+
+    /// Parse the given address, failing if it's encoded for another network.
+    pub fn parse_address(self, addr: &str) -> Result<Address, String> {
+        AddressEncoder::new(self.prefix())
+            .parse_address_from_str(addr)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Find out which network the given address is encoded for.
+    pub fn of_address(addr: &str) -> Result<(Self, Address), String> {
+        Network::Mainnet
+            .parse_address(addr)
+            .map(|parsed| (Network::Mainnet, parsed))
+            .or_else(|_| {
+                Network::Testnet
+                    .parse_address(addr)
+                    .map(|parsed| (Network::Testnet, parsed))
+            })
+    }
+
+    pub fn default_explorer_url(self) -> Url {
+        let url = match self {
+            Network::Mainnet => "https://api.ergoplatform.com",
+            Network::Testnet => "https://api-testnet.ergoplatform.com",
+        };
+        Url::try_from(String::from(url)).unwrap()
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            _ => Err(format!("Unknown network {}", s)),
+        }
+    }
+}
@@ -6,8 +6,9 @@ use ergo_lib::chain::transaction::Transaction;
 use futures::{Sink, SinkExt};
 use tokio::sync::Mutex;
 
+use ergo_mempool_sync::MempoolUpdate;
 use spectrum_offchain::combinators::EitherOrBoth;
-use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate};
+use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate, Unconfirmed};
 use spectrum_offchain::data::OnChainEntity;
 use spectrum_offchain::event_sink::handlers::types::TryFromBox;
 use spectrum_offchain::event_sink::types::EventHandler;
@@ -24,53 +25,52 @@ pub struct ConfirmedBundleUpdateHadler<TSink, TBundles, TProgs> {
     pub programs: Arc<Mutex<TProgs>>,
 }
 
-impl<TSink, TBundles, TProgs> ConfirmedBundleUpdateHadler<TSink, TBundles, TProgs>
+async fn extract_transitions<TBundles, TProgs>(
+    bundles: Arc<Mutex<TBundles>>,
+    programs: Arc<Mutex<TProgs>>,
+    tx: Transaction,
+) -> Vec<EitherOrBoth<AsBox<IndexedStakingBundle>, AsBox<IndexedStakingBundle>>>
 where
     TBundles: BundleRepo,
     TProgs: ProgramRepo,
 {
-    async fn extract_transitions(
-        &self,
-        tx: Transaction,
-    ) -> Vec<EitherOrBoth<AsBox<IndexedStakingBundle>, AsBox<IndexedStakingBundle>>> {
-        let mut consumed_bundles = HashMap::<BundleId, AsBox<IndexedStakingBundle>>::new();
-        {
-            let bundles = self.bundles.lock().await;
-            for i in tx.clone().inputs {
-                let state_id = BundleStateId::from(i.box_id);
-                if bundles.may_exist(state_id).await {
-                    if let Some(indexed_bundle) = bundles.get_state(state_id).await {
-                        consumed_bundles.insert(indexed_bundle.get_self_ref(), indexed_bundle);
-                    }
+    let mut consumed_bundles = HashMap::<BundleId, AsBox<IndexedStakingBundle>>::new();
+    {
+        let bundles = bundles.lock().await;
+        for i in tx.clone().inputs {
+            let state_id = BundleStateId::from(i.box_id);
+            if bundles.may_exist(state_id).await {
+                if let Some(indexed_bundle) = bundles.get_state(state_id).await {
+                    consumed_bundles.insert(indexed_bundle.get_self_ref(), indexed_bundle);
                 }
             }
         }
-        let mut created_bundles = HashMap::<BundleId, AsBox<IndexedStakingBundle>>::new();
-        {
-            let programs = self.programs.lock().await;
-            for bx in &tx.outputs {
-                if let Some(bundle) = StakingBundle::try_from_box(bx.clone()) {
-                    let indexed_bundle = if let Some(prog) = programs.get(bundle.pool_id).await {
-                        IndexedBundle::new(bundle, prog)
-                    } else {
-                        // handle initialization bundle
-                        IndexedBundle::init(bundle)
-                    };
-                    created_bundles.insert(indexed_bundle.get_self_ref(), AsBox(bx.clone(), indexed_bundle));
-                }
+    }
+    let mut created_bundles = HashMap::<BundleId, AsBox<IndexedStakingBundle>>::new();
+    {
+        let programs = programs.lock().await;
+        for bx in &tx.outputs {
+            if let Some(bundle) = StakingBundle::try_from_box(bx.clone()) {
+                let indexed_bundle = if let Some(prog) = programs.get(bundle.pool_id).await {
+                    IndexedBundle::new(bundle, prog)
+                } else {
+                    // handle initialization bundle
+                    IndexedBundle::init(bundle)
+                };
+                created_bundles.insert(indexed_bundle.get_self_ref(), AsBox(bx.clone(), indexed_bundle));
             }
         }
-        let consumed_keys = consumed_bundles.keys().cloned().collect::<HashSet<_>>();
-        let created_keys = created_bundles.keys().cloned().collect::<HashSet<_>>();
-        consumed_keys
-            .union(&created_keys)
-            .flat_map(|k| {
-                EitherOrBoth::try_from((consumed_bundles.remove(k), created_bundles.remove(k)))
-                    .map(|x| vec![x])
-                    .unwrap_or(Vec::new())
-            })
-            .collect()
     }
+    let consumed_keys = consumed_bundles.keys().cloned().collect::<HashSet<_>>();
+    let created_keys = created_bundles.keys().cloned().collect::<HashSet<_>>();
+    consumed_keys
+        .union(&created_keys)
+        .flat_map(|k| {
+            EitherOrBoth::try_from((consumed_bundles.remove(k), created_bundles.remove(k)))
+                .map(|x| vec![x])
+                .unwrap_or(Vec::new())
+        })
+        .collect()
 }
 
 #[async_trait(? Send)]
@@ -84,7 +84,9 @@ where
     async fn try_handle(&mut self, ev: LedgerTxEvent) -> Option<LedgerTxEvent> {
         let res = match ev {
             LedgerTxEvent::AppliedTx { tx, timestamp } => {
-                let transitions = self.extract_transitions(tx.clone()).await;
+                let transitions =
+                    extract_transitions(Arc::clone(&self.bundles), Arc::clone(&self.programs), tx.clone())
+                        .await;
                 let is_success = !transitions.is_empty();
                 for tr in transitions {
                     let _ = self.topic.feed(Confirmed(StateUpdate::Transition(tr))).await;
@@ -96,7 +98,9 @@ where
                 }
             }
             LedgerTxEvent::UnappliedTx(tx) => {
-                let transitions = self.extract_transitions(tx.clone()).await;
+                let transitions =
+                    extract_transitions(Arc::clone(&self.bundles), Arc::clone(&self.programs), tx.clone())
+                        .await;
                 let is_success = !transitions.is_empty();
                 for tr in transitions {
                     let _ = self
@@ -115,3 +119,57 @@ where
         res
     }
 }
+
+pub struct UnconfirmedBundleUpdateHandler<TSink, TBundles, TProgs> {
+    pub topic: TSink,
+    pub bundles: Arc<Mutex<TBundles>>,
+    pub programs: Arc<Mutex<TProgs>>,
+}
+
+#[async_trait(?Send)]
+impl<TSink, TBundles, TProgs> EventHandler<MempoolUpdate>
+    for UnconfirmedBundleUpdateHandler<TSink, TBundles, TProgs>
+where
+    TSink: Sink<Unconfirmed<StateUpdate<AsBox<IndexedStakingBundle>>>> + Unpin,
+    TBundles: BundleRepo,
+    TProgs: ProgramRepo,
+{
+    async fn try_handle(&mut self, ev: MempoolUpdate) -> Option<MempoolUpdate> {
+        let res = match ev {
+            MempoolUpdate::TxAccepted(tx) => {
+                let transitions =
+                    extract_transitions(Arc::clone(&self.bundles), Arc::clone(&self.programs), tx.clone())
+                        .await;
+                let is_success = !transitions.is_empty();
+                for tr in transitions {
+                    let _ = self.topic.feed(Unconfirmed(StateUpdate::Transition(tr))).await;
+                }
+                if is_success {
+                    None
+                } else {
+                    Some(MempoolUpdate::TxAccepted(tx))
+                }
+            }
+            MempoolUpdate::TxWithdrawn(tx) => {
+                let transitions =
+                    extract_transitions(Arc::clone(&self.bundles), Arc::clone(&self.programs), tx.clone())
+                        .await;
+                let is_success = !transitions.is_empty();
+                for tr in transitions {
+                    let _ = self
+                        .topic
+                        .feed(Unconfirmed(StateUpdate::TransitionRollback(tr.swap())))
+                        .await;
+                }
+                if is_success {
+                    None
+                } else {
+                    Some(MempoolUpdate::TxWithdrawn(tx))
+                }
+            }
+            ev => Some(ev),
+        };
+        let _ = self.topic.flush().await;
+        res
+    }
+}
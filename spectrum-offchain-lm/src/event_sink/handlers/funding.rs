@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use futures::{Sink, SinkExt};
 use log::trace;
 use tokio::sync::Mutex;
 
-use spectrum_offchain::data::unique_entity::Confirmed;
+use ergo_mempool_sync::MempoolUpdate;
+use spectrum_offchain::data::unique_entity::{Confirmed, Unconfirmed};
 use spectrum_offchain::event_sink::handlers::types::TryFromBoxCtx;
 use spectrum_offchain::event_sink::types::EventHandler;
 use spectrum_offchain::event_source::data::LedgerTxEvent;
@@ -14,10 +16,17 @@ use crate::data::funding::{DistributionFunding, ExecutorWallet, FundingUpdate};
 use crate::data::{AsBox, FundingId};
 use crate::funding::FundingRepo;
 
+/// Recognize a funding box of any of the given `wallets`.
+fn try_funding_from_box(bx: &ErgoBox, wallets: &[ExecutorWallet]) -> Option<DistributionFunding> {
+    wallets
+        .iter()
+        .find_map(|wallet| DistributionFunding::try_from_box(bx.clone(), wallet.clone()))
+}
+
 pub struct ConfirmedFundingHadler<TSink, TRepo> {
     pub topic: TSink,
     pub repo: Arc<Mutex<TRepo>>,
-    pub wallet: ExecutorWallet,
+    pub wallets: Vec<ExecutorWallet>,
 }
 
 #[async_trait(?Send)]
@@ -44,8 +53,7 @@ where
                     }
                 }
                 for bx in &tx.outputs {
-                    if let Some(funding) = DistributionFunding::try_from_box(bx.clone(), self.wallet.clone())
-                    {
+                    if let Some(funding) = try_funding_from_box(bx, &self.wallets) {
                         trace!("New funding box: {:?}", funding.id);
                         is_success = true;
                         let _ = self
@@ -66,8 +74,7 @@ where
             LedgerTxEvent::UnappliedTx(tx) => {
                 let mut is_success = false;
                 for bx in &tx.outputs {
-                    if let Some(funding) = DistributionFunding::try_from_box(bx.clone(), self.wallet.clone())
-                    {
+                    if let Some(funding) = try_funding_from_box(bx, &self.wallets) {
                         is_success = true;
                         let _ = self
                             .topic
@@ -86,3 +93,60 @@ where
         res
     }
 }
+
+pub struct UnconfirmedFundingHandler<TSink> {
+    pub topic: TSink,
+    pub wallets: Vec<ExecutorWallet>,
+}
+
+#[async_trait(?Send)]
+impl<TSink> EventHandler<MempoolUpdate> for UnconfirmedFundingHandler<TSink>
+where
+    TSink: Sink<Unconfirmed<FundingUpdate>> + Unpin,
+{
+    async fn try_handle(&mut self, ev: MempoolUpdate) -> Option<MempoolUpdate> {
+        let res = match ev {
+            MempoolUpdate::TxAccepted(tx) => {
+                let mut is_success = false;
+                for bx in &tx.outputs {
+                    if let Some(funding) = try_funding_from_box(bx, &self.wallets) {
+                        trace!(target: "funding", "New unconfirmed funding box: {:?}", funding.id);
+                        is_success = true;
+                        let _ = self
+                            .topic
+                            .feed(Unconfirmed(FundingUpdate::FundingCreated(AsBox(
+                                bx.clone(),
+                                funding,
+                            ))))
+                            .await;
+                    }
+                }
+                if is_success {
+                    None
+                } else {
+                    Some(MempoolUpdate::TxAccepted(tx))
+                }
+            }
+            MempoolUpdate::TxWithdrawn(tx) => {
+                let mut is_success = false;
+                for bx in &tx.outputs {
+                    if let Some(funding) = try_funding_from_box(bx, &self.wallets) {
+                        is_success = true;
+                        let _ = self
+                            .topic
+                            .feed(Unconfirmed(FundingUpdate::FundingEliminated(funding.id)))
+                            .await;
+                    }
+                }
+                if is_success {
+                    None
+                } else {
+                    Some(MempoolUpdate::TxWithdrawn(tx))
+                }
+            }
+            ev => Some(ev),
+        };
+        let _ = self.topic.flush().await;
+        res
+    }
+}
@@ -6,8 +6,11 @@ use crate::data::FundingId;
 use crate::token_details::get_token_details;
 use async_trait::async_trait;
 use chrono::Utc;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::Transaction;
 use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
 use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::wallet::signing::TransactionContext;
 use futures::{stream, StreamExt};
 use itertools::{EitherOrBoth, Itertools};
 use log::{error, info, trace, warn};
@@ -20,16 +23,18 @@ use spectrum_offchain::box_resolver::resolve_entity_state;
 use spectrum_offchain::data::unique_entity::{Predicted, Traced};
 use spectrum_offchain::data::{Has, OnChainEntity, OnChainOrder};
 use spectrum_offchain::executor::{
-    generate_invalidations, parse_err, Executor, Invalidation, NodeSubmitTxError, OrderType, RunOrderError,
+    generate_invalidations, ExecuteOrder, Executor, Invalidation, OrderType, RunOrderError,
 };
-use spectrum_offchain::network::ErgoNetwork;
+use spectrum_offchain::network::{ErgoNetwork, NodeSubmitTxError};
+use spectrum_offchain::state_context::StateContext;
 use spectrum_offchain::transaction::TransactionCandidate;
+use spectrum_offchain::tx_tracker::Rollback;
 
 use crate::bundle::{resolve_bundle_state, BundleRepo};
 use crate::data::context::ExecutionContext;
 use crate::data::order::Order;
 use crate::data::pool::Pool;
-use crate::data::{AsBox, BundleId, BundleStateId};
+use crate::data::{AsBox, BundleId, BundleStateId, PoolStateId};
 use crate::funding::FundingRepo;
 use crate::prover::SigmaProver;
 
@@ -65,6 +70,10 @@ pub struct OrderExecutor<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TPr
     prover: TProver,
     executor_prop: ErgoTree,
     context_cache: Cell<(u32, i64)>,
+    /// Txs are validated by the dry run network instead, so that failures don't suspend orders.
+    dry_run: bool,
+    /// Shared with the prover and kept in sync with the chain.
+    state_context: StateContext,
 }
 
 const CTX_TTL_SECS: i64 = 30;
@@ -82,6 +91,8 @@ where
         funding_repo: Arc<Mutex<TFunding>>,
         prover: TProver,
         executor_prop: ErgoTree,
+        state_context: StateContext,
+        dry_run: bool,
     ) -> Self {
         Self {
             network,
@@ -92,6 +103,8 @@ where
             prover,
             executor_prop,
             context_cache: Cell::new((0, 0)),
+            dry_run,
+            state_context,
         }
     }
 
@@ -111,10 +124,104 @@ where
             executor_prop: self.executor_prop.clone(),
         }
     }
+
+    /// Evaluate scripts of the signed `tx` against the current state context of the node.
+    fn verify_tx(&self, tx: &Transaction, candidate: &TransactionCandidate) -> Result<(), VerifyTxError> {
+        match self.state_context.try_get() {
+            Some(state_ctx) => verify_tx(tx, candidate, &state_ctx).map_err(VerifyTxError::Rejected),
+            None => Err(VerifyTxError::StateContextUnavailable),
+        }
+    }
+}
+
+impl<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver>
+    OrderExecutor<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver>
+where
+    TBacklog: Backlog<Order>,
+{
+    async fn on_run_error(&self, err: RunOrderError<Order>) {
+        match err {
+            RunOrderError::NonFatal(err, ord) => {
+                warn!(
+                    "Order [{:?}] suspended due to non-fatal error {}",
+                    ord.get_self_ref(),
+                    err
+                );
+                warn!(
+                    target: "offchain_lm",
+                    "Order [{:?}] suspended due to non-fatal error {}",
+                    ord.get_self_ref(),
+                    err
+                );
+                self.backlog.lock().await.suspend(ord, err).await;
+            }
+            RunOrderError::Fatal(err, ord) => {
+                warn!(
+                    "Order [{:?}] dropped due to fatal error {}",
+                    ord.get_self_ref(),
+                    err
+                );
+                warn!(
+                    target: "offchain_lm",
+                    "Order [{:?}] dropped due to fatal error {}",
+                    ord.get_self_ref(),
+                    err
+                );
+                self.backlog.lock().await.remove(ord.get_self_ref()).await;
+            }
+        }
+    }
+
+    async fn on_verify_error(&self, err: VerifyTxError, ord: Order) {
+        match err {
+            VerifyTxError::Rejected(err) => self.on_run_error(err.map(|_| ord)).await,
+            // State context is not obtained from the node yet, so we retry soon.
+            VerifyTxError::StateContextUnavailable => {
+                warn!(
+                    target: "offchain_lm",
+                    "Order [{:?}] returned to backlog as state context is unavailable",
+                    ord.get_self_ref()
+                );
+                self.backlog.lock().await.recharge(ord).await;
+            }
+        }
+    }
+}
+
+/// Reason for a signed tx to fail local verification.
+#[derive(Debug)]
+pub enum VerifyTxError {
+    /// Scripts failed or the tx is inconsistent with its inputs.
+    Rejected(RunOrderError<()>),
+    /// Tx can't be verified until the node provides state context.
+    StateContextUnavailable,
+}
+
+/// Evaluate scripts of all inputs of the signed `tx` against the given state context.
+/// A tx inconsistent with its inputs can never be accepted, while failed scripts may pass
+/// later against another state, e.g. in the next epoch.
+pub fn verify_tx(
+    tx: &Transaction,
+    candidate: &TransactionCandidate,
+    state_ctx: &ErgoStateContext,
+) -> Result<(), RunOrderError<()>> {
+    let tx_ctx = TransactionContext::new(
+        tx.clone(),
+        candidate.inputs.iter().map(|(bx, _)| bx.clone()).collect(),
+        candidate
+            .data_inputs
+            .as_ref()
+            .map(|d| d.to_vec())
+            .unwrap_or_default(),
+    )
+    .map_err(|err| RunOrderError::Fatal(format!("Invalid tx context: {:?}", err), ()))?;
+    tx_ctx
+        .validate(state_ctx)
+        .map_err(|err| RunOrderError::NonFatal(format!("Tx validation failed: {:?}", err), ()))
 }
 
 #[async_trait(?Send)]
-impl<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver> Executor
+impl<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver> ExecuteOrder<Order>
     for OrderExecutor<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver>
 where
     TNetwork: ErgoNetwork,
@@ -124,290 +231,349 @@ where
     TFunding: FundingRepo,
     TProver: SigmaProver,
 {
-    async fn try_execute_next(&mut self) -> Result<(), ()> {
-        let next_ord = {
-            let mut backlog = self.backlog.lock().await;
-            backlog.try_pop().await
-        };
-        if let Some(ord) = next_ord {
-            trace!(target: "offchain_lm", "Order acquired [{:?}]", ord.get_self_ref());
+    async fn execute(&self, ord: Order) -> Result<(), ()> {
+        trace!(target: "offchain_lm", "Order acquired [{:?}]", ord.get_self_ref());
 
-            let entity_id = ord.get_entity_ref();
+        let entity_id = ord.get_entity_ref();
 
-            if let Some(pool) = resolve_entity_state(entity_id, Arc::clone(&self.pool_repo)).await {
-                info!(
-                    target: "offchain_lm",
-                    "Pool for order [{:?}] is [{:?}], pool_state: {}",
-                    ord.get_self_ref(),
-                    pool.get_self_ref(),
-                    pool
-                );
-                info!(
-                    "Pool for order [{:?}] is [{:?}], pool_state: {}",
-                    ord.get_self_ref(),
-                    pool.get_self_ref(),
-                    pool
-                );
-                let conf = pool.1.conf;
-                let bundle_ids = ord.get::<Vec<BundleId>>();
-                let bundle_resolver = Arc::clone(&self.bundle_repo);
-                let bundles = stream::iter(bundle_ids.iter())
-                    .scan((), move |_, bundle_id| {
-                        let bundle_repo = Arc::clone(&bundle_resolver);
-                        async move { resolve_bundle_state(*bundle_id, bundle_repo).await }
-                    })
-                    .collect::<Vec<_>>()
+        if let Some(pool) = resolve_entity_state(entity_id, Arc::clone(&self.pool_repo)).await {
+            info!(
+                target: "offchain_lm",
+                "Pool for order [{:?}] is [{:?}], pool_state: {}",
+                ord.get_self_ref(),
+                pool.get_self_ref(),
+                pool
+            );
+            info!(
+                "Pool for order [{:?}] is [{:?}], pool_state: {}",
+                ord.get_self_ref(),
+                pool.get_self_ref(),
+                pool
+            );
+            let conf = pool.1.conf;
+            let bundle_ids = ord.get::<Vec<BundleId>>();
+            let bundle_resolver = Arc::clone(&self.bundle_repo);
+            let bundles = stream::iter(bundle_ids.iter())
+                .scan((), move |_, bundle_id| {
+                    let bundle_repo = Arc::clone(&bundle_resolver);
+                    async move { resolve_bundle_state(*bundle_id, bundle_repo).await }
+                })
+                .collect::<Vec<_>>()
+                .await;
+            let ctx = self.make_context(pool.box_id()).await;
+            info!("Running against {} with {}", pool, ctx);
+            info!(target: "offchain_lm", "Running against {} with {}", pool, ctx);
+            let run_result = match (ord.clone(), bundles.first().cloned()) {
+                (Order::Deposit(deposit), _) => {
+                    // Try to get token names from node
+                    let token_details = get_token_details(
+                        pool.1.pool_id,
+                        pool.1.budget_rem.token_id,
+                        deposit.1.lq.token_id,
+                        self.network,
+                    )
                     .await;
-                let ctx = self.make_context(pool.box_id()).await;
-                info!("Running against {} with {}", pool, ctx);
-                info!(target: "offchain_lm", "Running against {} with {}", pool, ctx);
-                let run_result = match (ord.clone(), bundles.first().cloned()) {
-                    (Order::Deposit(deposit), _) => {
-                        // Try to get token names from node
-                        let token_details = get_token_details(
-                            pool.1.pool_id,
-                            pool.1.budget_rem.token_id,
-                            deposit.1.lq.token_id,
-                            self.network,
-                        )
+                    deposit
+                        .try_run(pool.clone(), token_details, ctx)
+                        .map(|(tx, next_pool, bundle)| {
+                            (tx, next_pool, vec![bundle], None, OrderType::Deposit, 0)
+                        })
+                        .map_err(|err| err.map(Order::Deposit))
+                }
+                (Order::Redeem(redeem), Some(bundle)) => redeem
+                    .try_run(pool.clone(), bundle, ctx)
+                    .map(|(tx, next_pool, _)| (tx, next_pool, Vec::new(), None, OrderType::Redeem, 0))
+                    .map_err(|err| err.map(Order::Redeem)),
+                (Order::Compound(compound), _) if !bundles.is_empty() => {
+                    let funding = self
+                        .funding_repo
+                        .lock()
+                        .await
+                        .collect(compound.estimated_min_value())
                         .await;
-                        deposit
-                            .try_run(pool.clone(), token_details, ctx)
-                            .map(|(tx, next_pool, bundle)| {
-                                (tx, next_pool, vec![bundle], None, OrderType::Deposit)
+                    if let Ok(funding) = funding {
+                        let num_funding_inputs = funding.len();
+                        compound
+                            .try_run(pool.clone(), (bundles.clone(), funding), ctx)
+                            .map(|(tx, next_pool, (next_bundles, residual_funding))| {
+                                (
+                                    tx,
+                                    next_pool,
+                                    next_bundles,
+                                    residual_funding,
+                                    OrderType::Compound,
+                                    num_funding_inputs,
+                                )
                             })
-                            .map_err(|err| err.map(Order::Deposit))
-                    }
-                    (Order::Redeem(redeem), Some(bundle)) => redeem
-                        .try_run(pool.clone(), bundle, ctx)
-                        .map(|(tx, next_pool, _)| (tx, next_pool, Vec::new(), None, OrderType::Redeem))
-                        .map_err(|err| err.map(Order::Redeem)),
-                    (Order::Compound(compound), _) if !bundles.is_empty() => {
-                        let funding = self
-                            .funding_repo
-                            .lock()
-                            .await
-                            .collect(compound.estimated_min_value())
-                            .await;
-                        if let Ok(funding) = funding {
-                            compound
-                                .try_run(pool.clone(), (bundles.clone(), funding), ctx)
-                                .map(|(tx, next_pool, (next_bundles, residual_funding))| {
-                                    (tx, next_pool, next_bundles, residual_funding, OrderType::Compound)
-                                })
-                                .map_err(|err| err.map(Order::Compound))
-                        } else {
-                            error!("No funding can be found for managed compounding");
-                            return Err(());
-                        }
-                    }
-                    (Order::Compound(_), _) => Err(RunOrderError::Fatal(
-                        "No bundles found for Compound".to_string(),
-                        ord.clone(),
-                    )),
-                    (Order::Redeem(redeem), None) => {
-                        error!("Bundle not found for Redeem [{:?}]", redeem.get_self_ref(),);
+                            .map_err(|err| err.map(Order::Compound))
+                    } else {
+                        error!("No funding can be found for managed compounding");
                         return Err(());
                     }
-                };
-                match run_result {
-                    Ok((tx, next_pool, next_bundles, residual_funding, order_type)) => {
-                        trace!(target: "offchain_lm", "Order [{}] successfully evaluated", ord.get_self_ref());
-                        match self.prover.sign(tx) {
-                            Ok(tx) => {
-                                info!(
-                                    target: "offchain_lm", "Transaction ID for {:?} order [{}] is [{}]",
-                                    order_type,
-                                    ord.get_self_ref(),
-                                    tx.id()
-                                );
-                                info!(
-                                    "Transaction ID for {:?} order [{}] is [{}]",
-                                    order_type,
-                                    ord.get_self_ref(),
-                                    tx.id()
+                }
+                (Order::Compound(_), _) => Err(RunOrderError::Fatal(
+                    "No bundles found for Compound".to_string(),
+                    ord.clone(),
+                )),
+                (Order::Redeem(redeem), None) => {
+                    error!("Bundle not found for Redeem [{:?}]", redeem.get_self_ref(),);
+                    return Err(());
+                }
+            };
+            match run_result {
+                Ok((tx, next_pool, next_bundles, residual_funding, order_type, num_funding_inputs)) => {
+                    trace!(target: "offchain_lm", "Order [{}] successfully evaluated", ord.get_self_ref());
+                    match self.prover.sign(tx.clone()) {
+                        Ok(signed_tx) => {
+                            let candidate = tx;
+                            let tx = signed_tx;
+                            info!(
+                                target: "offchain_lm", "Transaction ID for {:?} order [{}] is [{}]",
+                                order_type,
+                                ord.get_self_ref(),
+                                tx.id()
+                            );
+                            info!(
+                                "Transaction ID for {:?} order [{}] is [{}]",
+                                order_type,
+                                ord.get_self_ref(),
+                                tx.id()
+                            );
+                            for (i, o) in tx.outputs.iter().enumerate() {
+                                trace!(target: "offchain_lm", "tx_output {}: {:?}", i, o.box_id());
+                            }
+                            let verified = if self.dry_run {
+                                Ok(())
+                            } else {
+                                self.verify_tx(&tx, &candidate)
+                            };
+                            if let Err(verify_err) = verified {
+                                self.on_verify_error(verify_err, ord).await;
+                            } else if let Err(submit_err) = self.network.submit_tx(tx.clone()).await {
+                                warn!("Execution failed while submitting tx due to {}", submit_err);
+                                warn!(
+                                    target: "offchain_lm",
+                                    "Execution failed while submitting tx due to {}",
+                                    submit_err
                                 );
-                                for (i, o) in tx.outputs.iter().enumerate() {
-                                    trace!(target: "offchain_lm", "tx_output {}: {:?}", i, o.box_id());
-                                }
-                                if let Err(client_err) = self.network.submit_tx(tx.clone()).await {
-                                    warn!("Execution failed while submitting tx due to {}", client_err);
-                                    warn!(
-                                        target: "offchain_lm",
-                                        "Execution failed while submitting tx due to {}",
-                                        client_err
-                                    );
-                                    match parse_err(&client_err.0) {
-                                        NodeSubmitTxError::MissingInputs(missing_indices) => {
-                                            let invalidations =
-                                                generate_invalidations(order_type, missing_indices);
-
-                                            for i in invalidations {
-                                                match i {
-                                                    Invalidation::Pool => {
-                                                        // We suspend the order and also invalidate
-                                                        // the pool. If the pool is actually
-                                                        // invalid, it's gone.
-                                                        //
-                                                        // Otherwise we are just waiting for
-                                                        // subsequent pool box to be confimed by
-                                                        // the ledger. The program will be brought
-                                                        // back by `ConfirmedProgramUpdateHandler`.
-                                                        self.pool_repo
-                                                            .lock()
-                                                            .await
-                                                            .invalidate(
-                                                                pool.get_self_state_ref(),
-                                                                pool.get_self_ref(),
-                                                            )
-                                                            .await;
-                                                        self.backlog.lock().await.suspend(ord.clone()).await;
-                                                    }
+                                match submit_err {
+                                    NodeSubmitTxError::MissingInputs(ref missing_indices) => {
+                                        let invalidations = generate_invalidations(
+                                            order_type,
+                                            num_funding_inputs,
+                                            missing_indices.clone(),
+                                        );
+                                        let mut order_is_stale = false;
+                                        for i in invalidations {
+                                            match i {
+                                                Invalidation::Pool => {
+                                                    // We suspend the order and also invalidate
+                                                    // the pool. If the pool is actually
+                                                    // invalid, it's gone.
+                                                    //
+                                                    // Otherwise we are just waiting for
+                                                    // subsequent pool box to be confimed by
+                                                    // the ledger. The program will be brought
+                                                    // back by `ConfirmedProgramUpdateHandler`.
+                                                    self.pool_repo
+                                                        .lock()
+                                                        .await
+                                                        .invalidate(
+                                                            pool.get_self_state_ref(),
+                                                            pool.get_self_ref(),
+                                                        )
+                                                        .await;
+                                                }
 
-                                                    Invalidation::Funding => {
-                                                        assert_eq!(order_type, OrderType::Compound);
-                                                        self.funding_repo
-                                                            .lock()
-                                                            .await
-                                                            .remove(FundingId::from(
-                                                                tx.inputs.get(1).unwrap().box_id,
-                                                            ))
-                                                            .await;
+                                                Invalidation::Funding(funding_ix) => {
+                                                    let mut funding_repo = self.funding_repo.lock().await;
+                                                    for ix in funding_ix {
+                                                        // Funding boxes follow the pool box in inputs.
+                                                        if let Some(input) = tx.inputs.get(ix + 1) {
+                                                            funding_repo
+                                                                .remove(FundingId::from(input.box_id))
+                                                                .await;
+                                                        }
                                                     }
+                                                }
 
-                                                    Invalidation::Order => {
-                                                        self.backlog
-                                                            .lock()
-                                                            .await
-                                                            .remove(ord.get_self_ref())
-                                                            .await;
-                                                    }
+                                                Invalidation::Order => order_is_stale = true,
 
-                                                    Invalidation::StakingBundles(bundles_ix) => {
-                                                        for ix in bundles_ix {
-                                                            self.bundle_repo
-                                                                .lock()
-                                                                .await
-                                                                .invalidate(BundleStateId::from(
-                                                                    tx.inputs.get(ix).unwrap().box_id,
-                                                                ))
+                                                Invalidation::StakingBundles(bundles_ix) => {
+                                                    let bundle_repo = self.bundle_repo.lock().await;
+                                                    for ix in bundles_ix {
+                                                        if let Some(bundle) = bundles.get(ix) {
+                                                            bundle_repo
+                                                                .invalidate(bundle.1.get_self_state_ref())
                                                                 .await;
                                                         }
                                                     }
                                                 }
                                             }
                                         }
-                                        NodeSubmitTxError::DoubleSpend => {
-                                            self.backlog.lock().await.suspend(ord).await;
+                                        let mut backlog = self.backlog.lock().await;
+                                        if order_is_stale {
+                                            backlog.remove(ord.get_self_ref()).await;
+                                        } else {
+                                            backlog.suspend(ord, submit_err.to_string()).await;
                                         }
-                                        NodeSubmitTxError::Unhandled => (),
                                     }
-                                } else {
-                                    // Return order to backlog to check for settlement.
-                                    {
+                                    // Node is not able to take the tx right now, so we retry soon.
+                                    NodeSubmitTxError::NodeUnavailable(_)
+                                    | NodeSubmitTxError::MempoolFull => {
+                                        self.backlog.lock().await.recharge(ord).await;
+                                    }
+                                    // Tx is rejected as is, so we retry with backoff until
+                                    // the order is dropped after too many attempts.
+                                    NodeSubmitTxError::DoubleSpend
+                                    | NodeSubmitTxError::ScriptFailure(_)
+                                    | NodeSubmitTxError::CostExceeded
+                                    | NodeSubmitTxError::LowFee
+                                    | NodeSubmitTxError::DustOutput
+                                    | NodeSubmitTxError::Unhandled(_) => {
                                         self.backlog
                                             .lock()
                                             .await
-                                            .check_later(ProgressingOrder {
-                                                order: ord,
-                                                timestamp: Utc::now().timestamp(),
-                                            })
+                                            .suspend(ord, submit_err.to_string())
                                             .await;
                                     }
-                                    self.pool_repo
+                                }
+                            } else {
+                                // Return order to backlog to check for settlement.
+                                {
+                                    self.backlog
                                         .lock()
                                         .await
-                                        .put_predicted(Traced {
-                                            state: next_pool,
-                                            prev_state_id: Some(pool.get_self_state_ref()),
+                                        .check_later(ProgressingOrder {
+                                            order: ord,
+                                            timestamp: Utc::now().timestamp(),
                                         })
                                         .await;
-                                    if let Some(residual_funding) = residual_funding {
-                                        self.funding_repo
-                                            .lock()
-                                            .await
-                                            .put_predicted(residual_funding)
-                                            .await;
-                                    }
-                                    for bundle_st in next_bundles.into_iter().zip_longest(bundles) {
-                                        match bundle_st {
-                                            EitherOrBoth::Both(next_bundle, prev_bundle) => {
-                                                self.bundle_repo
-                                                    .lock()
-                                                    .await
-                                                    .put_predicted(Traced {
-                                                        state: next_bundle.map(|as_box| {
-                                                            as_box.map(|b| IndexedBundle::new(b, conf))
-                                                        }),
-                                                        prev_state_id: Some(
-                                                            prev_bundle.1.get_self_state_ref(),
-                                                        ),
-                                                    })
-                                                    .await
-                                            }
-                                            EitherOrBoth::Left(next_bundle) => {
-                                                self.bundle_repo
-                                                    .lock()
-                                                    .await
-                                                    .put_predicted(Traced {
-                                                        state: next_bundle.map(|as_box| {
-                                                            as_box.map(|b| IndexedBundle::new(b, conf))
-                                                        }),
-                                                        prev_state_id: None,
-                                                    })
-                                                    .await
-                                            }
-                                            EitherOrBoth::Right(_) => {}
+                                }
+                                self.pool_repo
+                                    .lock()
+                                    .await
+                                    .put_predicted(Traced {
+                                        state: next_pool,
+                                        prev_state_id: Some(pool.get_self_state_ref()),
+                                    })
+                                    .await;
+                                if let Some(residual_funding) = residual_funding {
+                                    self.funding_repo
+                                        .lock()
+                                        .await
+                                        .put_predicted(residual_funding)
+                                        .await;
+                                }
+                                for bundle_st in next_bundles.into_iter().zip_longest(bundles) {
+                                    match bundle_st {
+                                        EitherOrBoth::Both(next_bundle, prev_bundle) => {
+                                            self.bundle_repo
+                                                .lock()
+                                                .await
+                                                .put_predicted(Traced {
+                                                    state: next_bundle.map(|as_box| {
+                                                        as_box.map(|b| IndexedBundle::new(b, conf))
+                                                    }),
+                                                    prev_state_id: Some(prev_bundle.1.get_self_state_ref()),
+                                                })
+                                                .await
                                         }
+                                        EitherOrBoth::Left(next_bundle) => {
+                                            self.bundle_repo
+                                                .lock()
+                                                .await
+                                                .put_predicted(Traced {
+                                                    state: next_bundle.map(|as_box| {
+                                                        as_box.map(|b| IndexedBundle::new(b, conf))
+                                                    }),
+                                                    prev_state_id: None,
+                                                })
+                                                .await
+                                        }
+                                        EitherOrBoth::Right(_) => {}
                                     }
                                 }
                             }
-                            Err(signing_err) => {
-                                error!("Failed to sign transaction due to {}", signing_err);
-                                error!(
-                                    target: "offchain_lm",
-                                    "Failed to sign transaction due to {}",
-                                    signing_err
-                                );
-                            }
                         }
-                    }
-                    Err(RunOrderError::NonFatal(err, ord)) => {
-                        warn!(
-                            "Order [{:?}] suspended due to non-fatal error {}",
-                            ord.get_self_ref(),
-                            err
-                        );
-                        warn!(
-                            target: "offchain_lm",
-                            "Order [{:?}] suspended due to non-fatal error {}",
-                            ord.get_self_ref(),
-                            err
-                        );
-                        self.backlog.lock().await.suspend(ord).await;
-                    }
-                    Err(RunOrderError::Fatal(err, ord)) => {
-                        warn!(
-                            "Order [{:?}] dropped due to fatal error {}",
-                            ord.get_self_ref(),
-                            err
-                        );
-                        warn!(
-                            target: "offchain_lm",
-                            "Order [{:?}] dropped due to fatal error {}",
-                            ord.get_self_ref(),
-                            err
-                        );
-                        self.backlog.lock().await.remove(ord.get_self_ref()).await;
+                        Err(signing_err) => {
+                            error!("Failed to sign transaction due to {}", signing_err);
+                            error!(
+                                target: "offchain_lm",
+                                "Failed to sign transaction due to {}",
+                                signing_err
+                            );
+                        }
                     }
                 }
-                return Ok(());
-            } else {
-                warn!("No pool is found for order [{:?}]", ord.get_self_ref());
-                warn!(target: "offchain_lm", "No pool is found for order [{:?}]", ord.get_self_ref());
-                self.backlog.lock().await.remove(ord.get_self_ref()).await;
+                Err(run_err) => self.on_run_error(run_err).await,
             }
+            return Ok(());
+        } else {
+            warn!("No pool is found for order [{:?}]", ord.get_self_ref());
+            warn!(target: "offchain_lm", "No pool is found for order [{:?}]", ord.get_self_ref());
+            self.backlog.lock().await.remove(ord.get_self_ref()).await;
         }
         Err(())
     }
 }
+
+#[async_trait(?Send)]
+impl<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver> Executor
+    for OrderExecutor<'a, TNetwork, TBacklog, TPools, TBundles, TFunding, TProver>
+where
+    TNetwork: ErgoNetwork,
+    TBacklog: Backlog<Order>,
+    TPools: EntityRepo<AsBox<Pool>>,
+    TBundles: BundleRepo,
+    TFunding: FundingRepo,
+    TProver: SigmaProver,
+{
+    async fn try_execute_next(&mut self) -> Result<(), ()> {
+        let next_ord = {
+            let mut backlog = self.backlog.lock().await;
+            backlog.try_pop().await
+        };
+        if let Some(ord) = next_ord {
+            self.execute(ord).await
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Rolls back predicted states of pools, bundles and funding boxes created by a transaction.
+pub struct PredictionsRollback<TPools, TBundles, TFunding> {
+    pub pool_repo: Arc<Mutex<TPools>>,
+    pub bundle_repo: Arc<Mutex<TBundles>>,
+    pub funding_repo: Arc<Mutex<TFunding>>,
+}
+
+#[async_trait(?Send)]
+impl<TPools, TBundles, TFunding> Rollback for PredictionsRollback<TPools, TBundles, TFunding>
+where
+    TPools: EntityRepo<AsBox<Pool>>,
+    TBundles: BundleRepo,
+    TFunding: FundingRepo,
+{
+    async fn rollback(&self, tx: &Transaction) {
+        for bx in tx.outputs.iter() {
+            let box_id = bx.box_id();
+            self.pool_repo
+                .lock()
+                .await
+                .discard_predicted(PoolStateId::from(box_id))
+                .await;
+            self.bundle_repo
+                .lock()
+                .await
+                .invalidate(BundleStateId::from(box_id))
+                .await;
+            self.funding_repo
+                .lock()
+                .await
+                .remove(FundingId::from(box_id))
+                .await;
+        }
+    }
+}
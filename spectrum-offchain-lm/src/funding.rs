@@ -1,9 +1,11 @@
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_std::task::spawn_blocking;
 use async_trait::async_trait;
 use log::trace;
 use nonempty::NonEmpty;
+use prometheus::{IntGauge, Registry};
 use rocksdb::{Direction, IteratorMode, ReadOptions};
 use serde::Serialize;
 
@@ -26,6 +28,10 @@ pub trait FundingRepo {
     /// False positive version of `exists()`.
     async fn may_exist(&self, fid: FundingId) -> bool;
     async fn remove(&mut self, fid: FundingId);
+    /// Get all known funding boxes in the order they are collected.
+    async fn get_all(&self) -> Vec<AsBox<DistributionFunding>>;
+    /// Get funding boxes not selected by `collect()` for a tx in flight, in the order they are collected.
+    async fn get_available(&self) -> Vec<AsBox<DistributionFunding>>;
 }
 
 pub struct FundingRepoTracing<R> {
@@ -73,6 +79,14 @@ where
         self.inner.remove(fid).await;
         trace!(target: "funding", "remove(fid: {:?}) -> ()", fid);
     }
+
+    async fn get_all(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.inner.get_all().await
+    }
+
+    async fn get_available(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.inner.get_available().await
+    }
 }
 
 const FUNDING_KEY_PREFIX: &str = "funding";
@@ -89,6 +103,13 @@ fn funding_key<T: Serialize>(prefix: &str, seq_num: usize, value: u64, id: &T) -
     key_bytes
 }
 
+/// Whether the funding box stored under the given key is selected for a tx in flight.
+fn is_selected(key: &[u8]) -> bool {
+    bincode::deserialize::<(String, usize)>(key)
+        .map(|(_, priority)| priority == SELECTED_PRIORITY)
+        .unwrap_or(false)
+}
+
 mod db_models {
     use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
     use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
@@ -126,6 +147,94 @@ mod db_models {
     }
 }
 
+/// Exposes total ERG value of funding boxes available for collection as a Prometheus gauge.
+/// The gauge is seeded from the wrapped repo, collected boxes are no longer accounted.
+pub struct FundingRepoMetrics<R> {
+    inner: R,
+    funds: HashMap<FundingId, NanoErg>,
+    available: IntGauge,
+}
+
+impl<R> FundingRepoMetrics<R>
+where
+    R: FundingRepo,
+{
+    pub async fn wrap(repo: R, registry: &Registry) -> Self {
+        let available = IntGauge::new("funding_available_nanoerg", "ERG available for compounding").unwrap();
+        registry.register(Box::new(available.clone())).unwrap();
+        let funds = repo
+            .get_available()
+            .await
+            .into_iter()
+            .map(|AsBox(_, df)| (df.id, df.erg_value))
+            .collect();
+        let metrics = Self {
+            inner: repo,
+            funds,
+            available,
+        };
+        metrics.update_available();
+        metrics
+    }
+}
+
+impl<R> FundingRepoMetrics<R> {
+    fn observe(&mut self, df: &DistributionFunding) {
+        self.funds.insert(df.id, df.erg_value);
+        self.update_available();
+    }
+
+    fn update_available(&self) {
+        let total = self.funds.values().map(|erg| u64::from(*erg)).sum::<u64>();
+        self.available.set(total as i64);
+    }
+}
+
+#[async_trait(?Send)]
+impl<R> FundingRepo for FundingRepoMetrics<R>
+where
+    R: FundingRepo,
+{
+    async fn collect(&mut self, target: NanoErg) -> Result<NonEmpty<AsBox<DistributionFunding>>, ()> {
+        let res = self.inner.collect(target).await;
+        if let Ok(funds) = &res {
+            for AsBox(_, df) in funds.iter() {
+                self.funds.remove(&df.id);
+            }
+            self.update_available();
+        }
+        res
+    }
+
+    async fn put_confirmed(&mut self, df: Confirmed<AsBox<DistributionFunding>>) {
+        self.observe(&df.0 .1);
+        self.inner.put_confirmed(df).await;
+    }
+
+    async fn put_predicted(&mut self, df: Predicted<AsBox<DistributionFunding>>) {
+        self.observe(&df.0 .1);
+        self.inner.put_predicted(df).await;
+    }
+
+    async fn may_exist(&self, fid: FundingId) -> bool {
+        self.inner.may_exist(fid).await
+    }
+
+    async fn remove(&mut self, fid: FundingId) {
+        self.funds.remove(&fid);
+        self.update_available();
+        self.inner.remove(fid).await;
+    }
+
+    async fn get_all(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.inner.get_all().await
+    }
+
+    async fn get_available(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.inner.get_available().await
+    }
+}
+
 pub struct FundingRepoRocksDB {
     db: Arc<rocksdb::OptimisticTransactionDB>,
 }
@@ -136,6 +245,25 @@ impl FundingRepoRocksDB {
             db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap()),
         }
     }
+
+    async fn read_funds(&self, include_selected: bool) -> Vec<AsBox<DistributionFunding>> {
+        let db = Arc::clone(&self.db);
+        spawn_blocking(move || {
+            let prefix = bincode::serialize(FUNDING_KEY_PREFIX).unwrap();
+            let mut readopts = ReadOptions::default();
+            readopts.set_iterate_range(rocksdb::PrefixRange(prefix.clone()));
+            db.iterator_opt(IteratorMode::From(&prefix, Direction::Forward), readopts)
+                .filter_map(|res| res.ok())
+                .filter(|(key, _)| include_selected || !is_selected(key))
+                .filter_map(|(_, bytes)| {
+                    bincode::deserialize::<'_, AsBox<db_models::DistributionFunding>>(&bytes)
+                        .ok()
+                        .map(|AsBox(bx, df)| AsBox(bx, DistributionFunding::from(df)))
+                })
+                .collect()
+        })
+        .await
+    }
 }
 
 const CONFIRMED_PRIORITY: usize = 0;
@@ -242,6 +370,89 @@ impl FundingRepo for FundingRepoRocksDB {
         })
         .await
     }
+
+    async fn get_all(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.read_funds(true).await
+    }
+
+    async fn get_available(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.read_funds(false).await
+    }
+}
+
+/// In-memory implementation of `FundingRepo`.
+/// Boxes are ordered by the same keys as in `FundingRepoRocksDB`, so selection order matches.
+#[derive(Default)]
+pub struct InMemoryFundingRepo {
+    funds: BTreeMap<Vec<u8>, AsBox<DistributionFunding>>,
+    index: HashMap<FundingId, Vec<u8>>,
+}
+
+impl InMemoryFundingRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn put(&mut self, priority: usize, df: AsBox<DistributionFunding>) {
+        let id = df.1.id;
+        let key = funding_key(FUNDING_KEY_PREFIX, priority, df.1.erg_value.into(), &id);
+        self.funds.insert(key.clone(), df);
+        self.index.insert(id, key);
+    }
+}
+
+#[async_trait(?Send)]
+impl FundingRepo for InMemoryFundingRepo {
+    async fn collect(&mut self, target: NanoErg) -> Result<NonEmpty<AsBox<DistributionFunding>>, ()> {
+        let mut funds = Vec::new();
+        let mut acc = NanoErg::from(0);
+        for key in self.funds.keys().cloned().collect::<Vec<_>>() {
+            if acc >= target {
+                break;
+            }
+            if let Some(df) = self.funds.remove(&key) {
+                acc = acc + df.1.erg_value;
+                funds.push(df.clone());
+                self.put(SELECTED_PRIORITY, df);
+            }
+        }
+        if acc >= target {
+            Ok(NonEmpty::from_vec(funds).unwrap())
+        } else {
+            trace!(target: "funding", "acc: {}, funds: {:?}", acc, funds);
+            Err(())
+        }
+    }
+
+    async fn put_confirmed(&mut self, Confirmed(df): Confirmed<AsBox<DistributionFunding>>) {
+        self.put(CONFIRMED_PRIORITY, df);
+    }
+
+    async fn put_predicted(&mut self, Predicted(df): Predicted<AsBox<DistributionFunding>>) {
+        self.put(PREDICTED_PRIORITY, df);
+    }
+
+    async fn may_exist(&self, fid: FundingId) -> bool {
+        self.index.contains_key(&fid)
+    }
+
+    async fn remove(&mut self, fid: FundingId) {
+        if let Some(key) = self.index.remove(&fid) {
+            self.funds.remove(&key);
+        }
+    }
+
+    async fn get_all(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.funds.values().cloned().collect()
+    }
+
+    async fn get_available(&self) -> Vec<AsBox<DistributionFunding>> {
+        self.funds
+            .iter()
+            .filter(|(key, _)| !is_selected(key))
+            .map(|(_, df)| df.clone())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -256,6 +467,7 @@ mod test {
     use ergo_lib::ergotree_ir::mir::constant::Constant;
     use ergo_lib::ergotree_ir::mir::expr::Expr;
     use nonempty::NonEmpty;
+    use prometheus::Registry;
     use rand::RngCore;
 
     use spectrum_offchain::data::unique_entity::{Confirmed, Predicted};
@@ -263,7 +475,7 @@ mod test {
     use crate::data::funding::DistributionFunding;
     use crate::data::{AsBox, FundingId};
     use crate::ergo::NanoErg;
-    use crate::funding::{FundingRepo, FundingRepoRocksDB};
+    use crate::funding::{FundingRepo, FundingRepoMetrics, FundingRepoRocksDB, InMemoryFundingRepo};
 
     fn rocks_db_client() -> FundingRepoRocksDB {
         let rnd = rand::thread_rng().next_u32();
@@ -302,7 +514,15 @@ mod test {
 
     #[tokio::test]
     async fn collect_sucess() {
-        let mut client = rocks_db_client();
+        test_collect_sucess(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn collect_sucess_in_memory() {
+        test_collect_sucess(InMemoryFundingRepo::new()).await;
+    }
+
+    async fn test_collect_sucess<C: FundingRepo>(mut client: C) {
         let f1 = funding(0, 1_000_000);
         let f2 = funding(1, 2_000_000);
         let f3 = funding(2, 4_000_000);
@@ -317,7 +537,15 @@ mod test {
 
     #[tokio::test]
     async fn collect_not_enough_funds() {
-        let mut client = rocks_db_client();
+        test_collect_not_enough_funds(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn collect_not_enough_funds_in_memory() {
+        test_collect_not_enough_funds(InMemoryFundingRepo::new()).await;
+    }
+
+    async fn test_collect_not_enough_funds<C: FundingRepo>(mut client: C) {
         let f1 = funding(0, 1_000_000);
         let f2 = funding(1, 2_000_000);
         let f3 = funding(2, 4_000_000);
@@ -331,7 +559,15 @@ mod test {
 
     #[tokio::test]
     async fn collect_confirmed_over_predicted() {
-        let mut client = rocks_db_client();
+        test_collect_confirmed_over_predicted(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn collect_confirmed_over_predicted_in_memory() {
+        test_collect_confirmed_over_predicted(InMemoryFundingRepo::new()).await;
+    }
+
+    async fn test_collect_confirmed_over_predicted<C: FundingRepo>(mut client: C) {
         let f1 = funding(0, 1_000_000);
         let f2 = funding(1, 2_000_000);
         let f3 = funding(2, 4_000_000);
@@ -346,4 +582,56 @@ mod test {
         let res = client.collect(target).await;
         assert_eq!(res, Ok(NonEmpty::from((f1, vec![f3]))));
     }
+
+    #[tokio::test]
+    async fn collected_funds_are_not_available() {
+        test_collected_funds_are_not_available(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn collected_funds_are_not_available_in_memory() {
+        test_collected_funds_are_not_available(InMemoryFundingRepo::new()).await;
+    }
+
+    async fn test_collected_funds_are_not_available<C: FundingRepo>(mut client: C) {
+        let f1 = funding(0, 1_000_000);
+        let f2 = funding(1, 2_000_000);
+        client.put_confirmed(Confirmed(f1.clone())).await;
+        client.put_predicted(Predicted(f2.clone())).await;
+        let res = client.collect(NanoErg::from(1_000_000)).await;
+        assert_eq!(res, Ok(NonEmpty::new(f1)));
+        assert_eq!(client.get_available().await, vec![f2]);
+        assert_eq!(client.get_all().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn metrics_account_only_available_funds() {
+        let mut repo = InMemoryFundingRepo::new();
+        for f in vec![funding(0, 1_000_000), funding(1, 2_000_000)] {
+            repo.put_confirmed(Confirmed(f)).await
+        }
+        let registry = Registry::new();
+        let mut metrics = FundingRepoMetrics::wrap(repo, &registry).await;
+        assert_eq!(metrics.available.get(), 3_000_000);
+        metrics.collect(NanoErg::from(2_000_000)).await.unwrap();
+        assert_eq!(metrics.available.get(), 1_000_000);
+        metrics.put_predicted(Predicted(funding(2, 4_000_000))).await;
+        assert_eq!(metrics.available.get(), 5_000_000);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_not_seeded_with_selected_funds() {
+        let mut repo = InMemoryFundingRepo::new();
+        for f in vec![funding(0, 1_000_000), funding(1, 2_000_000)] {
+            repo.put_confirmed(Confirmed(f)).await
+        }
+        let selected = repo.collect(NanoErg::from(1_000_000)).await.unwrap();
+        assert_eq!(selected.len(), 1);
+        let registry = Registry::new();
+        let metrics = FundingRepoMetrics::wrap(repo, &registry).await;
+        assert_eq!(
+            metrics.available.get(),
+            3_000_000 - u64::from(selected.head.1.erg_value) as i64
+        );
+    }
 }
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use futures::{Stream, StreamExt};
 use tokio::sync::Mutex;
 
-use spectrum_offchain::data::unique_entity::Confirmed;
+use spectrum_offchain::data::unique_entity::{Confirmed, Predicted, Unconfirmed};
 
 use crate::data::funding::FundingUpdate;
 use crate::funding::FundingRepo;
@@ -27,3 +27,30 @@ where
         }
     })
 }
+
+/// Funding boxes created by unconfirmed txs are tracked as predicted, so they can be chained
+/// before the tx is mined. Boxes consumed in mempool are kept until the ledger eliminates them.
+/// Boxes that are already known (e.g. predicted by the executor itself) are left untouched.
+pub fn unconfirmed_funding_update_stream<'a, S, TRepo>(
+    upstream: S,
+    repo: Arc<Mutex<TRepo>>,
+) -> impl Stream<Item = ()> + 'a
+where
+    S: Stream<Item = Unconfirmed<FundingUpdate>> + 'a,
+    TRepo: FundingRepo + 'a,
+{
+    upstream.then(move |Unconfirmed(upd)| {
+        let repo = Arc::clone(&repo);
+        async move {
+            let mut repo = repo.lock().await;
+            match upd {
+                FundingUpdate::FundingCreated(funding) => {
+                    if !repo.may_exist(funding.1.id).await {
+                        repo.put_predicted(Predicted(funding)).await
+                    }
+                }
+                FundingUpdate::FundingEliminated(fid) => repo.remove(fid).await,
+            }
+        }
+    })
+}
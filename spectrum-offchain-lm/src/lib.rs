@@ -1,3 +1,4 @@
+pub mod admin;
 pub mod backlog_stream;
 pub mod bundle;
 pub mod data;
@@ -5,8 +6,10 @@ pub mod ergo;
 pub mod event_sink;
 pub mod executor;
 pub mod funding;
+pub mod metrics;
 pub mod program;
 pub mod prover;
 pub mod scheduler;
+pub mod sweeper;
 mod token_details;
 pub mod validators;
@@ -1,40 +1,57 @@
+use std::net::SocketAddr;
+use std::rc::Rc;
 use std::sync::{Arc, Once};
 
 use clap::{arg, Parser};
-use ergo_lib::ergotree_ir::chain::address::{AddressEncoder, NetworkPrefix};
+use ergo_lib::ergotree_ir::chain::address::Address;
 use futures::channel::mpsc;
 use futures::future::ready;
 use futures::stream::select_all;
 use futures::StreamExt;
 use isahc::{prelude::*, HttpClient};
-use log::info;
+use log::{info, warn};
+use nonempty::NonEmpty;
+use prometheus::Registry;
 use serde::Deserialize;
+use sigma_test_util::force_any_val;
 use tokio::sync::Mutex;
 
+use ergo_chain_sync::cache::chain_cache::InMemoryCache;
 use ergo_chain_sync::cache::rocksdb::ChainCacheRocksDB;
-use ergo_chain_sync::client::node::ErgoNodeHttpClient;
+use ergo_chain_sync::client::explorer::ErgoExplorerHttpClient;
+use ergo_chain_sync::client::node::{ErgoNetworkMetrics as ChainSyncNetworkMetrics, ErgoNodeHttpClient};
 use ergo_chain_sync::client::types::Url;
 use ergo_chain_sync::rocksdb::RocksConfig;
-use ergo_chain_sync::{chain_sync_stream, ChainSync};
+use ergo_chain_sync::{chain_sync_stream, ChainSync, ChainSyncNonInit, NO_PREFETCH};
+use ergo_mempool_sync::{mempool_sync_stream, MempoolSyncConf, MempoolUpdate};
 use spectrum_offchain::backlog::persistence::BacklogStoreRocksDB;
 use spectrum_offchain::backlog::process::backlog_stream;
-use spectrum_offchain::backlog::{BacklogConfig, BacklogService, BacklogTracing};
+use spectrum_offchain::backlog::{BacklogConfig, BacklogMetrics, BacklogService, BacklogTracing};
 use spectrum_offchain::box_resolver::persistence::EntityRepoTracing;
-use spectrum_offchain::box_resolver::process::entity_tracking_stream;
+use spectrum_offchain::box_resolver::process::{entity_tracking_stream, unconfirmed_entity_tracking_stream};
 use spectrum_offchain::box_resolver::rocksdb::EntityRepoRocksDB;
 use spectrum_offchain::data::order::OrderUpdate;
-use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate};
-use spectrum_offchain::event_sink::handlers::entity::ConfirmedUpdateHandler;
+use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate, Unconfirmed};
+use spectrum_offchain::event_sink::handlers::entity::{ConfirmedUpdateHandler, UnconfirmedUpgradeHandler};
 use spectrum_offchain::event_sink::handlers::order::OrderUpdatesHandler;
+use spectrum_offchain::event_sink::handlers::tx::TxTrackerHandler;
 use spectrum_offchain::event_sink::process_events;
 use spectrum_offchain::event_sink::types::{EventHandler, NoopDefaultHandler};
 use spectrum_offchain::event_source::data::LedgerTxEvent;
 use spectrum_offchain::event_source::event_source_ledger;
-use spectrum_offchain::executor::executor_stream;
+use spectrum_offchain::executor::{executor_stream, parallel_executor_stream};
+use spectrum_offchain::network::failover::{FailoverConfig, FailoverNetwork};
+use spectrum_offchain::network::source::ChainDataSource;
+use spectrum_offchain::network::{DryRunConfig, ErgoNetworkDryRun, ErgoNetworkMetrics};
+use spectrum_offchain::state_context::{sync_state_context, StateContext};
 use spectrum_offchain::streaming::boxed;
+use spectrum_offchain::tx_tracker::process::tx_tracker_stream;
+use spectrum_offchain::tx_tracker::{ErgoNetworkTxTracking, TxTrackerConfig, TxTrackerService};
 
+use crate::admin::process::admin_request_stream;
+use crate::admin::{serve_admin_api, AdminRequest};
 use crate::backlog_stream::convert_order_proto;
-use crate::bundle::process::bundle_update_stream;
+use crate::bundle::process::{bundle_update_stream, unconfirmed_bundle_update_stream};
 use crate::bundle::rocksdb::BundleRepoRocksDB;
 use crate::bundle::BundleRepoTracing;
 use crate::data::bundle::IndexedStakingBundle;
@@ -45,18 +62,24 @@ use crate::data::{
     order::{Order, OrderProto},
     OrderId,
 };
-use crate::event_sink::handlers::bundle::ConfirmedBundleUpdateHadler;
-use crate::event_sink::handlers::funding::ConfirmedFundingHadler;
+use crate::ergo::Network;
+use crate::event_sink::handlers::bundle::{ConfirmedBundleUpdateHadler, UnconfirmedBundleUpdateHandler};
+use crate::event_sink::handlers::funding::{ConfirmedFundingHadler, UnconfirmedFundingHandler};
 use crate::event_sink::handlers::program::ConfirmedProgramUpdateHandler;
 use crate::event_sink::handlers::schedule::ConfirmedScheduleUpdateHandler;
-use crate::executor::OrderExecutor;
-use crate::funding::process::funding_update_stream;
-use crate::funding::{FundingRepoRocksDB, FundingRepoTracing};
+use crate::executor::{OrderExecutor, PredictionsRollback};
+use crate::funding::process::{funding_update_stream, unconfirmed_funding_update_stream};
+use crate::funding::{FundingRepoMetrics, FundingRepoRocksDB, FundingRepoTracing};
+use crate::metrics::serve_metrics;
 use crate::program::rocksdb::ProgramRepoRocksDB;
-use crate::prover::{SeedPhrase, Wallet};
+use crate::prover::remote::RemoteSigner;
+use crate::prover::{SigmaProver, SignerConfig, Wallet};
 use crate::scheduler::process::distribution_stream;
-use crate::scheduler::{ScheduleRepoRocksDB, ScheduleRepoTracing};
+use crate::scheduler::{ScheduleRepoMetrics, ScheduleRepoRocksDB, ScheduleRepoTracing};
+use crate::sweeper::process::sweeper_stream;
+use crate::sweeper::{Sweeper, SweeperConfig};
 
+pub mod admin;
 pub mod backlog_stream;
 pub mod bundle;
 pub mod data;
@@ -64,9 +87,11 @@ pub mod ergo;
 pub mod event_sink;
 pub mod executor;
 pub mod funding;
+pub mod metrics;
 pub mod program;
 pub mod prover;
 pub mod scheduler;
+pub mod sweeper;
 mod sink;
 mod token_details;
 pub mod validators;
@@ -76,6 +101,9 @@ async fn main() {
     let args = AppArgs::parse();
     let raw_config = std::fs::read_to_string(args.config_path).expect("Cannot load configuration file");
     let config: AppConfig = serde_yaml::from_str(&raw_config).expect("Invalid configuration file");
+    config.check_network().expect("Invalid configuration file");
+    config.check_admin_token().expect("Invalid configuration file");
+    config.check_sources().expect("Invalid configuration file");
 
     if let Some(log4rs_path) = args.log4rs_path {
         log4rs::init_file(log4rs_path, Default::default()).unwrap();
@@ -90,24 +118,48 @@ async fn main() {
         .build()
         .unwrap();
 
-    let node = ErgoNodeHttpClient::new(client, config.node_addr);
+    let registry = Registry::new_custom(Some("lm".into()), None).unwrap();
+    if let Some(metrics_addr) = config.metrics_addr {
+        tokio::spawn(serve_metrics(metrics_addr, registry.clone()));
+    }
+
+    let nodes = config
+        .node_addr
+        .into_iter()
+        .chain(config.backup_node_addrs.unwrap_or_default())
+        .map(|addr| ChainDataSource::Node(ErgoNodeHttpClient::new(client.clone(), addr)))
+        .chain(
+            config
+                .explorer_addrs
+                .unwrap_or_default()
+                .into_iter()
+                .map(|addr| ChainDataSource::Explorer(ErgoExplorerHttpClient::new(client.clone(), addr))),
+        )
+        .collect::<Vec<_>>();
+    let node = FailoverNetwork::new(nodes, config.node_failover.unwrap_or_default())
+        .expect("Invalid configuration file");
+    let chain_sync_node = ChainSyncNetworkMetrics::wrap(node.clone(), &registry);
     let cache = ChainCacheRocksDB::new(RocksConfig {
         db_path: config.chain_cache_db_path.into(),
     });
     let signal_tip_reached: Once = Once::new();
     let chain_sync = ChainSync::init(
         config.chain_sync_starting_height,
-        &node,
+        &chain_sync_node,
         cache,
         Some(&signal_tip_reached),
+        config.chain_sync_prefetch_window.unwrap_or(NO_PREFETCH),
     )
     .await;
 
     let backlog_store = BacklogStoreRocksDB::new(RocksConfig {
         db_path: config.backlog_store_db_path.into(),
     });
-    let backlog = Arc::new(Mutex::new(BacklogTracing::wrap(
-        BacklogService::new::<Order>(backlog_store, config.backlog_config.clone()).await,
+    let backlog = Arc::new(Mutex::new(BacklogMetrics::<_, OrderId>::wrap(
+        BacklogTracing::wrap(
+            BacklogService::new::<Order>(backlog_store, config.backlog_config.clone()).await,
+        ),
+        &registry,
     )));
     let pools = Arc::new(Mutex::new(EntityRepoTracing::wrap(EntityRepoRocksDB::new(
         RocksConfig {
@@ -122,28 +174,91 @@ async fn main() {
             db_path: config.bundle_repo_db_path.into(),
         },
     ))));
-    let funding = Arc::new(Mutex::new(FundingRepoTracing::wrap(FundingRepoRocksDB::new(
-        RocksConfig {
+    let funding = Arc::new(Mutex::new(FundingRepoMetrics::wrap(
+        FundingRepoTracing::wrap(FundingRepoRocksDB::new(RocksConfig {
             db_path: config.funding_repo_db_path.into(),
+        })),
+        &registry,
+    )
+    .await));
+    let (prover, funding_addrs, state_context) = match config.operator_signer {
+        SignerConfig::Local(secrets) => {
+            let (wallet, addrs) = Wallet::try_from_secrets(secrets).expect("Invalid operator secrets");
+            let state_context = wallet.state_context();
+            (Box::new(wallet) as Box<dyn SigmaProver>, addrs, state_context)
+        }
+        SignerConfig::Remote(conf) => {
+            let addr: Address = conf.funding_addr.clone().into();
+            let signer = RemoteSigner::new(conf).expect("Invalid remote signer config");
+            // Still needed to verify txs before submission.
+            let state_context = StateContext::new(force_any_val());
+            (
+                Box::new(signer) as Box<dyn SigmaProver>,
+                NonEmpty::new(addr),
+                state_context,
+            )
+        }
+    };
+    if let Err(err) = state_context.refresh(&node).await {
+        warn!("Failed to get initial state context due to {}", err);
+    }
+
+    info!("Operating in {:?}", config.network);
+    for addr in funding_addrs.iter() {
+        info!("Funding address is {}", config.network.encode_address(addr));
+    }
+    let funding_wallets = funding_addrs
+        .iter()
+        .cloned()
+        .map(ExecutorWallet::from)
+        .collect::<Vec<_>>();
+
+    if config.dry_run.is_some() {
+        warn!("Dry run is enabled, transactions will not be broadcasted");
+    }
+    let tx_tracker = Arc::new(Mutex::new(TxTrackerService::new(
+        ErgoNetworkDryRun::wrap(node.clone(), config.dry_run.clone()),
+        PredictionsRollback {
+            pool_repo: Arc::clone(&pools),
+            bundle_repo: Arc::clone(&bundles),
+            funding_repo: Arc::clone(&funding),
         },
-    ))));
-    let (prover, funding_addr) = Wallet::try_from_seed(config.operator_funding_secret).expect("Invalid seed");
+        config.tx_tracker_config,
+    )));
+    let tx_tracker_stream = boxed(tx_tracker_stream(
+        Arc::clone(&tx_tracker),
+        std::time::Duration::from_secs(10),
+        &signal_tip_reached,
+    ));
 
-    info!(
-        "Funding address is {}",
-        AddressEncoder::encode_address_as_string(NetworkPrefix::Mainnet, &funding_addr)
+    let executor_node = ErgoNetworkTxTracking::wrap(
+        ErgoNetworkMetrics::wrap(
+            ErgoNetworkDryRun::wrap(node.clone(), config.dry_run.clone()),
+            &registry,
+        ),
+        Arc::clone(&tx_tracker),
     );
-
+    let prover = Rc::new(prover);
     let executor = OrderExecutor::new(
-        &node,
+        &executor_node,
         Arc::clone(&backlog),
         Arc::clone(&pools),
         Arc::clone(&bundles),
         Arc::clone(&funding),
-        prover,
+        Rc::clone(&prover),
         config.operator_reward_addr.ergo_tree(),
+        state_context.clone(),
+        config.dry_run.is_some(),
     );
-    let executor_stream = boxed(executor_stream(executor, &signal_tip_reached));
+    let executor_stream = match config.executor_max_concurrent_pools {
+        Some(max_pools) => boxed(parallel_executor_stream(
+            executor,
+            Arc::clone(&backlog),
+            max_pools,
+            &signal_tip_reached,
+        )),
+        None => boxed(executor_stream(executor, &signal_tip_reached)),
+    };
 
     let default_handler = NoopDefaultHandler;
 
@@ -151,6 +266,13 @@ async fn main() {
     let (pool_snd, pool_recv) = mpsc::unbounded::<Confirmed<StateUpdate<AsBox<Pool>>>>();
     let pool_han = ConfirmedUpdateHandler::<_, AsBox<Pool>, _>::new(pool_snd, Arc::clone(&pools));
     let pool_update_stream = boxed(entity_tracking_stream(pool_recv, Arc::clone(&pools)));
+    let (unconf_pool_snd, unconf_pool_recv) = mpsc::unbounded::<Unconfirmed<StateUpdate<AsBox<Pool>>>>();
+    let unconf_pool_han =
+        UnconfirmedUpgradeHandler::<_, AsBox<Pool>, _>::new(unconf_pool_snd, Arc::clone(&pools));
+    let unconf_pool_update_stream = boxed(unconfirmed_entity_tracking_stream(
+        unconf_pool_recv,
+        Arc::clone(&pools),
+    ));
 
     // bundles
     let (bundle_snd, bundle_recv) = mpsc::unbounded::<Confirmed<StateUpdate<AsBox<IndexedStakingBundle>>>>();
@@ -160,10 +282,26 @@ async fn main() {
         programs: Arc::clone(&programs),
     };
     let bundle_update_stream = boxed(bundle_update_stream(bundle_recv, Arc::clone(&bundles)));
+    let (unconf_bundle_snd, unconf_bundle_recv) =
+        mpsc::unbounded::<Unconfirmed<StateUpdate<AsBox<IndexedStakingBundle>>>>();
+    let unconf_bundle_han = UnconfirmedBundleUpdateHandler {
+        topic: unconf_bundle_snd,
+        bundles: Arc::clone(&bundles),
+        programs: Arc::clone(&programs),
+    };
+    let unconf_bundle_update_stream = boxed(unconfirmed_bundle_update_stream(
+        unconf_bundle_recv,
+        Arc::clone(&bundles),
+    ));
 
     // orders
     let (order_snd, order_recv) = mpsc::unbounded::<OrderUpdate<OrderProto, OrderId>>();
     let order_han = OrderUpdatesHandler::<_, Order, OrderProto, _>::new(
+        order_snd.clone(),
+        Arc::clone(&backlog),
+        config.backlog_config.order_lifespan,
+    );
+    let unconf_order_han = OrderUpdatesHandler::<_, Order, OrderProto, _>::new(
         order_snd,
         Arc::clone(&backlog),
         config.backlog_config.order_lifespan,
@@ -178,23 +316,71 @@ async fn main() {
     let funding_han = ConfirmedFundingHadler {
         topic: funding_snd,
         repo: Arc::clone(&funding),
-        wallet: funding_addr.into(),
+        wallets: funding_wallets.clone(),
     };
     let funding_update_stream = boxed(funding_update_stream(funding_recv, Arc::clone(&funding)));
+    let (unconf_funding_snd, unconf_funding_recv) = mpsc::unbounded::<Unconfirmed<FundingUpdate>>();
+    let unconf_funding_han = UnconfirmedFundingHandler {
+        topic: unconf_funding_snd,
+        wallets: funding_wallets,
+    };
+    let unconf_funding_update_stream = boxed(unconfirmed_funding_update_stream(
+        unconf_funding_recv,
+        Arc::clone(&funding),
+    ));
 
-    let schedules = Arc::new(Mutex::new(ScheduleRepoTracing::wrap(ScheduleRepoRocksDB::new(
-        RocksConfig {
+    // rewards
+    let mut reward_handlers: Vec<Box<dyn EventHandler<LedgerTxEvent>>> = Vec::new();
+    let mut reward_mempool_handlers: Vec<Box<dyn EventHandler<MempoolUpdate>>> = Vec::new();
+    let mut reward_streams = Vec::new();
+    if let Some(sweeper_conf) = config.reward_sweeper {
+        let rewards = Arc::new(Mutex::new(FundingRepoTracing::wrap(FundingRepoRocksDB::new(
+            RocksConfig {
+                db_path: sweeper_conf.reward_repo_db_path.clone().into(),
+            },
+        ))));
+        let (reward_snd, reward_recv) = mpsc::unbounded::<Confirmed<FundingUpdate>>();
+        reward_handlers.push(Box::new(ConfirmedFundingHadler {
+            topic: reward_snd,
+            repo: Arc::clone(&rewards),
+            wallets: vec![config.operator_reward_addr.clone()],
+        }));
+        reward_streams.push(boxed(funding_update_stream(reward_recv, Arc::clone(&rewards))));
+        let (unconf_reward_snd, unconf_reward_recv) = mpsc::unbounded::<Unconfirmed<FundingUpdate>>();
+        reward_mempool_handlers.push(Box::new(UnconfirmedFundingHandler {
+            topic: unconf_reward_snd,
+            wallets: vec![config.operator_reward_addr.clone()],
+        }));
+        reward_streams.push(boxed(unconfirmed_funding_update_stream(
+            unconf_reward_recv,
+            Arc::clone(&rewards),
+        )));
+        let sweep_interval = std::time::Duration::from_secs(sweeper_conf.sweep_interval_secs);
+        let sweeper = Sweeper::new(
+            &executor_node,
+            Arc::clone(&funding),
+            rewards,
+            Rc::clone(&prover),
+            funding_addrs.head.script().unwrap(),
+            sweeper_conf,
+        );
+        reward_streams.push(boxed(sweeper_stream(sweeper, sweep_interval, &signal_tip_reached)));
+    }
+
+    let schedules = Arc::new(Mutex::new(ScheduleRepoMetrics::wrap(
+        ScheduleRepoTracing::wrap(ScheduleRepoRocksDB::new(RocksConfig {
             db_path: config.schedule_repo_db_path.into(),
-        },
-    ))));
+        })),
+        &registry,
+    )));
     let schedule_han = ConfirmedScheduleUpdateHandler {
         schedules: Arc::clone(&schedules),
-        pools,
+        pools: Arc::clone(&pools),
     };
     let scheduler_stream = boxed(distribution_stream(
-        backlog,
-        schedules,
-        bundles,
+        Arc::clone(&backlog),
+        Arc::clone(&schedules),
+        Arc::clone(&bundles),
         &node,
         10, // Note: setting this higher could lead to rejection of compound orders by Ergo Node.
         std::time::Duration::from_secs(60),
@@ -205,27 +391,88 @@ async fn main() {
         programs: Arc::clone(&programs),
     };
 
-    let handlers: Vec<Box<dyn EventHandler<LedgerTxEvent>>> = vec![
+    // admin
+    let (admin_snd, admin_recv) = mpsc::unbounded::<AdminRequest>();
+    if let Some(admin_api_addr) = config.admin_api_addr {
+        tokio::spawn(serve_admin_api(
+            admin_api_addr,
+            config.admin_api_token.map(String::from),
+            admin_snd,
+        ));
+    }
+    let admin_request_stream = boxed(admin_request_stream(
+        admin_recv,
+        backlog,
+        pools,
+        bundles,
+        schedules,
+        programs,
+        funding,
+        Arc::clone(&tx_tracker),
+    ));
+
+    let mut handlers: Vec<Box<dyn EventHandler<LedgerTxEvent>>> = vec![
         Box::new(pool_han),
         Box::new(order_han),
         Box::new(bundle_han),
         Box::new(funding_han),
         Box::new(schedule_han),
         Box::new(program_han),
+        Box::new(TxTrackerHandler::new(Arc::clone(&tx_tracker))),
     ];
+    handlers.extend(reward_handlers);
 
-    let event_source = event_source_ledger(chain_sync_stream(chain_sync));
+    let chain_upgrades = chain_sync_stream(chain_sync).inspect(|upgr| chain_sync_node.observe_upgrade(upgr));
+    let chain_upgrades = boxed(sync_state_context(
+        chain_upgrades,
+        &node,
+        state_context,
+        &signal_tip_reached,
+    ));
+    let event_source = event_source_ledger(chain_upgrades);
     let process_events_stream = boxed(process_events(event_source, handlers, default_handler));
 
-    let mut app = select_all(vec![
+    let mut mempool_handlers: Vec<Box<dyn EventHandler<MempoolUpdate>>> = vec![
+        Box::new(unconf_pool_han),
+        Box::new(unconf_order_han),
+        Box::new(unconf_bundle_han),
+        Box::new(unconf_funding_han),
+        Box::new(TxTrackerHandler::new(tx_tracker)),
+    ];
+    mempool_handlers.extend(reward_mempool_handlers);
+
+    let mempool_sync_conf = MempoolSyncConf {
+        sync_interval: std::time::Duration::from_secs(config.mempool_sync_interval_secs),
+    };
+    let mempool_source = mempool_sync_stream(
+        mempool_sync_conf,
+        ChainSyncNonInit::new(&node, InMemoryCache::new()),
+        &node,
+    )
+    .await;
+    let process_mempool_events_stream = boxed(process_events(
+        mempool_source,
+        mempool_handlers,
+        NoopDefaultHandler,
+    ));
+
+    let mut streams = vec![
         process_events_stream,
+        process_mempool_events_stream,
         executor_stream,
         pool_update_stream,
+        unconf_pool_update_stream,
         backlog_stream,
         bundle_update_stream,
+        unconf_bundle_update_stream,
         funding_update_stream,
+        unconf_funding_update_stream,
         scheduler_stream,
-    ]);
+        admin_request_stream,
+        tx_tracker_stream,
+    ];
+    streams.extend(reward_streams);
+    let mut app = select_all(streams);
 
     loop {
         app.select_next_some().await;
@@ -234,10 +481,23 @@ async fn main() {
 
 #[derive(Deserialize)]
 struct AppConfig<'a> {
-    node_addr: Url,
+    /// Explorers are used as the only chain data source if omitted.
+    node_addr: Option<Url>,
+    /// Nodes to fail over to when `node_addr` is unavailable or lags behind.
+    /// Txs are broadcasted to all healthy nodes.
+    backup_node_addrs: Option<Vec<Url>>,
+    /// Explorers to fail over to after the nodes, or to run without a synced node of your own.
+    explorer_addrs: Option<Vec<Url>>,
+    /// Default failover settings are used if omitted.
+    node_failover: Option<FailoverConfig>,
     http_client_timeout_duration_secs: u32,
     chain_sync_starting_height: u32,
+    /// Fetch up to this many blocks concurrently until the tip is reached.
+    /// Blocks are fetched one by one if omitted.
+    chain_sync_prefetch_window: Option<usize>,
+    mempool_sync_interval_secs: u64,
     backlog_config: BacklogConfig,
+    tx_tracker_config: TxTrackerConfig,
     log4rs_yaml_path: &'a str,
     backlog_store_db_path: &'a str,
     entity_repo_db_path: &'a str,
@@ -247,7 +507,65 @@ struct AppConfig<'a> {
     schedule_repo_db_path: &'a str,
     chain_cache_db_path: &'a str,
     operator_reward_addr: ExecutorWallet,
-    operator_funding_secret: SeedPhrase,
+    operator_signer: SignerConfig,
+    /// Execute orders of up to this many pools concurrently. Orders are executed one by one if omitted.
+    executor_max_concurrent_pools: Option<usize>,
+    /// Address to serve Prometheus metrics at. Metrics are not served if omitted.
+    metrics_addr: Option<SocketAddr>,
+    /// Address to serve the admin API at. The API is disabled if omitted.
+    admin_api_addr: Option<SocketAddr>,
+    /// Bearer token required for admin commands. Commands are disabled if omitted.
+    admin_api_token: Option<&'a str>,
+    /// Log or export transactions instead of broadcasting them. Disabled if omitted.
+    /// Predicted states are rolled back once the tx tracker gives up on the unbroadcasted txs.
+    dry_run: Option<DryRunConfig>,
+    /// Sweep rewards paid to `operator_reward_addr` into funding and to a cold address.
+    /// The signer must hold the key of `operator_reward_addr`. Disabled if omitted.
+    reward_sweeper: Option<SweeperConfig>,
+    /// Network addresses are encoded for. Mainnet if omitted.
+    #[serde(default)]
+    network: Network,
+}
+
+impl<'a> AppConfig<'a> {
+    /// Make sure all configured addresses belong to the configured network.
+    fn check_network(&self) -> Result<(), String> {
+        self.operator_reward_addr.check_network(self.network)?;
+        if let SignerConfig::Remote(conf) = &self.operator_signer {
+            conf.funding_addr.check_network(self.network)?;
+        }
+        if let Some(conf) = &self.reward_sweeper {
+            conf.cold_addr.check_network(self.network)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure admin commands aren't guarded by an empty or placeholder token.
+    fn check_admin_token(&self) -> Result<(), String> {
+        match self.admin_api_token.map(str::trim) {
+            Some(token) if token.is_empty() || (token.starts_with('<') && token.ends_with('>')) => {
+                Err("admin_api_token must be a secret, omit it to disable admin commands".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Make sure there is at least one node or explorer to sync with.
+    fn check_sources(&self) -> Result<(), String> {
+        let has_backup_nodes = self
+            .backup_node_addrs
+            .as_ref()
+            .map_or(false, |addrs| !addrs.is_empty());
+        let has_explorers = self
+            .explorer_addrs
+            .as_ref()
+            .map_or(false, |addrs| !addrs.is_empty());
+        if self.node_addr.is_some() || has_backup_nodes || has_explorers {
+            Ok(())
+        } else {
+            Err("At least one of node_addr, backup_node_addrs or explorer_addrs must be configured".to_string())
+        }
+    }
 }
 
 #[derive(Parser)]
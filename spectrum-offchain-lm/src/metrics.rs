@@ -0,0 +1,43 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{error, info};
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// Serve metrics collected in the given `registry` at `GET /metrics`.
+pub async fn serve_metrics(addr: SocketAddr, registry: Registry) {
+    let make_svc = make_service_fn(move |_| {
+        let registry = registry.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(req, registry.clone()))) }
+    });
+    info!(target: "metrics", "Serving metrics at {}", addr);
+    if let Err(err) = Server::bind(&addr).serve(make_svc).await {
+        error!(target: "metrics", "Metrics server failed: {}", err);
+    }
+}
+
+async fn handle(req: Request<Body>, registry: Registry) -> Result<Response<Body>, Infallible> {
+    let resp = if req.method() == Method::GET && req.uri().path() == "/metrics" {
+        let encoder = TextEncoder::new();
+        let mut buffer = Vec::new();
+        match encoder.encode(&registry.gather(), &mut buffer) {
+            Ok(_) => Response::builder()
+                .header(CONTENT_TYPE, encoder.format_type())
+                .body(Body::from(buffer))
+                .unwrap(),
+            Err(err) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(err.to_string()))
+                .unwrap(),
+        }
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap()
+    };
+    Ok(resp)
+}
@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use crate::data::pool::ProgramConfig;
 use crate::data::PoolId;
 
+pub mod in_memory;
 pub mod rocksdb;
 
 /// Registry of all LM programs known in the network.
@@ -14,4 +15,6 @@ pub trait ProgramRepo {
     async fn get(&self, pool_id: PoolId) -> Option<ProgramConfig>;
     /// Check if given program already exists.
     async fn exists(&self, pool_id: PoolId) -> bool;
+    /// Get all known programs.
+    async fn get_all(&self) -> Vec<(PoolId, ProgramConfig)>;
 }
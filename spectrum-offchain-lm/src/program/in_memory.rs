@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+
+use crate::data::pool::ProgramConfig;
+use crate::data::PoolId;
+use crate::program::ProgramRepo;
+
+/// In-memory implementation of `ProgramRepo`.
+#[derive(Default)]
+pub struct InMemoryProgramRepo {
+    programs: Mutex<HashMap<PoolId, ProgramConfig>>,
+}
+
+impl InMemoryProgramRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ProgramRepo for InMemoryProgramRepo {
+    async fn put(&self, pool_id: PoolId, conf: ProgramConfig) {
+        self.programs.lock().insert(pool_id, conf);
+    }
+
+    async fn get(&self, pool_id: PoolId) -> Option<ProgramConfig> {
+        self.programs.lock().get(&pool_id).copied()
+    }
+
+    async fn exists(&self, pool_id: PoolId) -> bool {
+        self.programs.lock().contains_key(&pool_id)
+    }
+
+    async fn get_all(&self) -> Vec<(PoolId, ProgramConfig)> {
+        self.programs
+            .lock()
+            .iter()
+            .map(|(pid, conf)| (*pid, *conf))
+            .collect()
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use async_std::task::spawn_blocking;
 use async_trait::async_trait;
+use rocksdb::IteratorMode;
 
 use ergo_chain_sync::rocksdb::RocksConfig;
 
@@ -49,4 +50,19 @@ impl ProgramRepo for ProgramRepoRocksDB {
         let key = bincode::serialize(&pool_id).unwrap();
         spawn_blocking(move || db.get(&key).unwrap().is_some()).await
     }
+
+    async fn get_all(&self) -> Vec<(PoolId, ProgramConfig)> {
+        let db = self.db.clone();
+        spawn_blocking(move || {
+            db.iterator(IteratorMode::Start)
+                .filter_map(|res| res.ok())
+                .filter_map(|(key, value)| {
+                    let pool_id = bincode::deserialize(&key).ok()?;
+                    let conf = bincode::deserialize(&value).ok()?;
+                    Some((pool_id, conf))
+                })
+                .collect()
+        })
+        .await
+    }
 }
@@ -1,7 +1,6 @@
 use std::rc::Rc;
 
-use derive_more::{From, Into};
-use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use derive_more::{Display, From, Into};
 use ergo_lib::chain::transaction::prover_result::ProverResult;
 use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
 use ergo_lib::chain::transaction::{Input, Transaction, UnsignedInput};
@@ -10,22 +9,145 @@ use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::{DlogProverIn
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::hint::HintsBag;
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::{ProofBytes, Prover};
 use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::wallet::derivation_path::{ChildIndexHardened, ChildIndexNormal, DerivationPath};
 use ergo_lib::wallet::ext_secret_key::ExtSecretKey;
 use ergo_lib::wallet::mnemonic::Mnemonic;
 use ergo_lib::wallet::secret_key::SecretKey;
 use ergo_lib::wallet::signing::{make_context, TransactionContext, TxSigningError};
+use nonempty::NonEmpty;
 use serde::Deserialize;
 use sigma_test_util::force_any_val;
 
+use spectrum_offchain::state_context::StateContext;
 use spectrum_offchain::transaction::{TransactionCandidate, UnsignedTransactionOps};
 
+use crate::prover::keystore::{KeystoreConfig, WalletSeed};
+use crate::prover::remote::RemoteSignerConfig;
+
+pub mod keystore;
+pub mod remote;
+
+#[derive(Debug, Display)]
+pub enum SigningError {
+    #[display(fmt = "{}", _0)]
+    Local(TxSigningError),
+    #[display(fmt = "Remote signer failed: {}", _0)]
+    Remote(String),
+    #[display(fmt = "Remote signer timed out")]
+    Timeout,
+}
+
+impl From<TxSigningError> for SigningError {
+    fn from(err: TxSigningError) -> Self {
+        Self::Local(err)
+    }
+}
+
 pub trait SigmaProver {
-    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, TxSigningError>;
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError>;
+}
+
+impl<P: SigmaProver + ?Sized> SigmaProver for Box<P> {
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError> {
+        self.as_ref().sign(tx)
+    }
+}
+
+impl<P: SigmaProver + ?Sized> SigmaProver for Rc<P> {
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError> {
+        self.as_ref().sign(tx)
+    }
+}
+
+/// Backend signing txs of the operator.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerConfig {
+    /// Secrets are held in-process, the first derived address is the funding address.
+    Local(Vec<SecretSource>),
+    Remote(RemoteSignerConfig),
 }
 
 #[derive(Deserialize, Into, From)]
 pub struct SeedPhrase(String);
 
+/// Scheme keys of the operator are derived from a seed with.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum Derivation {
+    /// The master key of the seed is used as is. Addresses derived by earlier versions of the bot
+    /// are kept this way, while standard Ergo wallets derive another address from the same mnemonic.
+    Legacy,
+    Eip3(Eip3Derivation),
+}
+
+impl Default for Derivation {
+    fn default() -> Self {
+        Self::Legacy
+    }
+}
+
+/// EIP-3 derivation of addresses `m/44'/429'/account'/0/index`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Eip3Derivation {
+    pub account: u32,
+    /// Index of the first derived address.
+    pub first_index: u32,
+    /// Number of consecutive addresses to derive.
+    pub num_addresses: u32,
+}
+
+impl Default for Eip3Derivation {
+    fn default() -> Self {
+        Self {
+            account: 0,
+            first_index: 0,
+            num_addresses: 1,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SecretSource {
+    /// Mnemonic without password, only the master key is derived.
+    Seed(SeedPhrase),
+    Mnemonic {
+        phrase: SeedPhrase,
+        password: Option<String>,
+        #[serde(default)]
+        derivation: Derivation,
+    },
+    Keystore {
+        #[serde(flatten)]
+        keystore: KeystoreConfig,
+        #[serde(default)]
+        derivation: Derivation,
+    },
+}
+
+impl SecretSource {
+    fn derive_keys(self) -> Result<Vec<(DlogProverInput, Address)>, String> {
+        let (seed, derivation) = match self {
+            SecretSource::Seed(phrase) => (
+                Mnemonic::to_seed(&<String>::from(phrase), ""),
+                Derivation::default(),
+            ),
+            SecretSource::Mnemonic {
+                phrase,
+                password,
+                derivation,
+            } => (
+                Mnemonic::to_seed(&<String>::from(phrase), &password.unwrap_or_default()),
+                derivation,
+            ),
+            SecretSource::Keystore { keystore, derivation } => (keystore.load_seed()?, derivation),
+        };
+        derive_keys(seed, &derivation)
+    }
+}
+
 #[derive(Clone, Deserialize, Into, From)]
 #[serde(try_from = "String")]
 pub struct WalletSecret(DlogProverInput);
@@ -42,37 +164,89 @@ impl TryFrom<String> for WalletSecret {
 
 pub struct Wallet {
     secrets: Vec<PrivateInput>,
-    /// Necessary to use `sign_transaction` function from `sigma-rust`. If we're only signing P2PK
-    /// inputs, then this field can be any arbitrary value.
-    ergo_state_context: ErgoStateContext,
+    /// State context P2PK inputs are signed against. Arbitrary until refreshed from the node.
+    ergo_state_context: StateContext,
+}
+
+/// Derive keys from the `seed` according to the given `derivation`.
+fn derive_keys(seed: WalletSeed, derivation: &Derivation) -> Result<Vec<(DlogProverInput, Address)>, String> {
+    let master = ExtSecretKey::derive_master(seed).map_err(|err| err.to_string())?;
+    match derivation {
+        Derivation::Legacy => key_pair(&master).map(|key| vec![key]),
+        Derivation::Eip3(derivation) => derive_eip3_keys(&master, derivation),
+    }
+}
+
+fn key_pair(sk: &ExtSecretKey) -> Result<(DlogProverInput, Address), String> {
+    if let SecretKey::DlogSecretKey(dpi) = sk.secret_key() {
+        Ok((dpi, Address::P2Pk(sk.public_image())))
+    } else {
+        Err("Invalid seed".to_string())
+    }
+}
+
+/// Derive keys of the given range of addresses the way standard Ergo wallets do.
+fn derive_eip3_keys(
+    master: &ExtSecretKey,
+    derivation: &Eip3Derivation,
+) -> Result<Vec<(DlogProverInput, Address)>, String> {
+    let account = ChildIndexHardened::from_31_bit(derivation.account).map_err(|err| err.to_string())?;
+    let last_index = derivation
+        .first_index
+        .checked_add(derivation.num_addresses)
+        .ok_or("Address index out of range".to_string())?;
+    (derivation.first_index..last_index)
+        .map(|ix| {
+            let index = ChildIndexNormal::normal(ix).map_err(|err| err.to_string())?;
+            let sk = master
+                .derive(DerivationPath::new(account, vec![index]))
+                .map_err(|err| err.to_string())?;
+            key_pair(&sk)
+        })
+        .collect()
 }
 
 impl Wallet {
-    pub fn try_from_seed(seed: SeedPhrase) -> Option<(Self, Address)> {
-        if let Ok(sk) = ExtSecretKey::derive_master(Mnemonic::to_seed(&<String>::from(seed), "")) {
-            if let SecretKey::DlogSecretKey(dpi) = sk.secret_key() {
-                let addr = Address::P2Pk(sk.public_image());
-                let wallet = Self {
-                    secrets: vec![PrivateInput::DlogProverInput(dpi)],
-                    ergo_state_context: force_any_val(),
-                };
-                Some((wallet, addr))
-            } else {
-                None
+    /// Make a wallet holding secrets derived from all the given sources.
+    /// Returns addresses of the secrets in order of derivation along with the wallet.
+    pub fn try_from_secrets(sources: Vec<SecretSource>) -> Result<(Self, NonEmpty<Address>), String> {
+        let mut secrets = Vec::new();
+        let mut addrs = Vec::new();
+        for source in sources {
+            for (dpi, addr) in source.derive_keys()? {
+                secrets.push(PrivateInput::DlogProverInput(dpi));
+                addrs.push(addr);
             }
-        } else {
-            None
         }
+        let addrs = NonEmpty::from_vec(addrs).ok_or("No secrets provided".to_string())?;
+        let wallet = Self {
+            secrets,
+            ergo_state_context: StateContext::new(force_any_val()),
+        };
+        Ok((wallet, addrs))
+    }
+
+    /// Make a wallet holding the master key of the given mnemonic.
+    pub fn try_from_seed(seed: SeedPhrase) -> Option<(Self, Address)> {
+        Self::try_from_secrets(vec![SecretSource::Seed(seed)])
+            .ok()
+            .map(|(wallet, addrs)| (wallet, addrs.head))
     }
+
     pub fn trivial(secrets: Vec<WalletSecret>) -> Self {
         Self {
             secrets: secrets
                 .into_iter()
                 .map(|WalletSecret(pi)| PrivateInput::DlogProverInput(pi))
                 .collect(),
-            ergo_state_context: force_any_val(),
+            ergo_state_context: StateContext::new(force_any_val()),
         }
     }
+
+    /// Handle to keep the state context of the wallet up to date.
+    pub fn state_context(&self) -> StateContext {
+        self.ergo_state_context.clone()
+    }
 }
 
 impl Prover for Wallet {
@@ -85,8 +259,8 @@ impl Prover for Wallet {
     }
 }
 
-impl SigmaProver for Wallet {
-    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, TxSigningError> {
+impl Wallet {
+    fn sign_tx(&self, tx: TransactionCandidate) -> Result<Transaction, TxSigningError> {
         let TransactionCandidate {
             inputs,
             data_inputs,
@@ -116,6 +290,7 @@ impl SigmaProver for Wallet {
             data_inputs.map(|d| d.to_vec()).unwrap_or_else(Vec::new),
         )?;
         let tx = tx_context.spending_tx.clone();
+        let ergo_state_context = self.ergo_state_context.get();
         let message_to_sign = tx.bytes_to_sign()?;
         let signed_inputs = tx.inputs.enumerated().try_mapped(|(idx, input)| {
             let input_box = tx_context
@@ -123,7 +298,7 @@ impl SigmaProver for Wallet {
                 .ok_or(TxSigningError::InputBoxNotFound(idx))?;
             let addr = Address::recreate_from_ergo_tree(&input_box.ergo_tree).unwrap();
             if let Address::P2Pk(_) = addr {
-                let ctx = Rc::new(make_context(&self.ergo_state_context, &tx_context, idx)?);
+                let ctx = Rc::new(make_context(&ergo_state_context, &tx_context, idx)?);
                 let hints_bag = HintsBag::empty();
                 self.prove(
                     &input_box.ergo_tree,
@@ -152,10 +327,16 @@ impl SigmaProver for Wallet {
     }
 }
 
+impl SigmaProver for Wallet {
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError> {
+        Ok(self.sign_tx(tx)?)
+    }
+}
+
 pub struct NoopProver;
 
 impl SigmaProver for NoopProver {
-    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, TxSigningError> {
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError> {
         Ok(tx.into_tx_without_proofs())
     }
 }
@@ -182,9 +363,18 @@ mod tests {
     };
     use sigma_test_util::force_any_val;
 
+    use spectrum_offchain::state_context::StateContext;
     use spectrum_offchain::transaction::TransactionCandidate;
 
-    use super::{SeedPhrase, SigmaProver, Wallet};
+    use super::keystore::{EncryptedSecret, KeystoreConfig};
+    use super::{Derivation, Eip3Derivation, SecretSource, SeedPhrase, SigmaProver, Wallet};
+
+    const PHRASE: &str =
+        "gather obvious bracket ticket uphold quantum quit pistol math direct rural turn west youth acid";
+
+    fn encode(addr: &Address) -> String {
+        AddressEncoder::encode_address_as_string(NetworkPrefix::Mainnet, addr)
+    }
 
     #[test]
     fn test_sigmaprover_sign() {
@@ -197,7 +387,7 @@ mod tests {
 
         let wallet = Wallet {
             secrets: vec![PrivateInput::from(secret_key)],
-            ergo_state_context,
+            ergo_state_context: StateContext::new(ergo_state_context),
         };
 
         let value = force_any_val::<BoxValue>();
@@ -233,14 +423,103 @@ mod tests {
 
     #[test]
     fn gen_pub_key() {
-        let seed_phrase = SeedPhrase(
-            "gather obvious bracket ticket uphold quantum quit pistol math direct rural turn west youth acid"
-                .into(),
+        let (_prover, funding_addr) = Wallet::try_from_seed(SeedPhrase(PHRASE.into())).expect("Invalid seed");
+        assert_eq!(
+            encode(&funding_addr),
+            "9hkfeMZj1p3rBQJhENX5EKczYx3uVTjVPdN825YvdLnwzgxkKvj"
+        );
+    }
+
+    #[test]
+    fn wallet_from_several_secrets() {
+        let keystore_path = std::env::temp_dir().join("lm_test_keystore.json");
+        let keystore = EncryptedSecret::encrypt(&[1u8; 64], "passphrase").unwrap();
+        std::fs::write(&keystore_path, serde_json::to_vec(&keystore).unwrap()).unwrap();
+        std::env::set_var("LM_TEST_KEYSTORE_PASSPHRASE", "passphrase");
+        let (wallet, addrs) = Wallet::try_from_secrets(vec![
+            SecretSource::Seed(SeedPhrase(PHRASE.into())),
+            SecretSource::Keystore {
+                keystore: KeystoreConfig {
+                    path: keystore_path.clone(),
+                    passphrase_env: "LM_TEST_KEYSTORE_PASSPHRASE".into(),
+                },
+                derivation: Derivation::default(),
+            },
+        ])
+        .unwrap();
+        std::fs::remove_file(keystore_path).unwrap();
+        assert_eq!(wallet.secrets.len(), 2);
+        assert_eq!(addrs.len(), 2);
+        assert_eq!(
+            encode(&addrs.head),
+            "9hkfeMZj1p3rBQJhENX5EKczYx3uVTjVPdN825YvdLnwzgxkKvj"
+        );
+    }
+
+    #[test]
+    fn derive_address_range() {
+        let mnemonic = |password: Option<&str>, first_index, num_addresses| SecretSource::Mnemonic {
+            phrase: SeedPhrase(PHRASE.into()),
+            password: password.map(String::from),
+            derivation: Derivation::Eip3(Eip3Derivation {
+                account: 0,
+                first_index,
+                num_addresses,
+            }),
+        };
+        let (_, addrs) = Wallet::try_from_secrets(vec![mnemonic(None, 0, 3)]).unwrap();
+        assert_eq!(
+            addrs.iter().map(encode).collect::<Vec<_>>(),
+            vec![
+                "9f2W9AnYnDcpMkBoy26Q7PvxL8y8B6UmbSEZ39g6rQEi1tqJAtA",
+                "9f97pvwHrQRpE3vsZK9qgS5CCszmhAh2XmTCEEtnmRGGaUxpzgG",
+                "9hfwC5Nbb42riAjyCBZoyzKborhcuiPvyvs1kZVzqnCfcoB8b2c",
+            ]
+        );
+        let (_, shifted_addrs) = Wallet::try_from_secrets(vec![mnemonic(None, 1, 2)]).unwrap();
+        assert_eq!(shifted_addrs.into_iter().collect::<Vec<_>>(), addrs.tail);
+        let (_, protected_addrs) = Wallet::try_from_secrets(vec![mnemonic(Some("password"), 0, 1)]).unwrap();
+        assert_eq!(
+            encode(&protected_addrs.head),
+            "9iNYzBbjvBPEUrAuPFfpaxrbJKGCPAu4DPn7TKYE5AuAjdWfqYM"
+        );
+    }
+
+    #[test]
+    fn derive_eip3_test_vector() {
+        let (_, addrs) = Wallet::try_from_secrets(vec![SecretSource::Mnemonic {
+            phrase: SeedPhrase(
+                "slow silly start wash bundle suffer bulb ancient height spin express remind today effort helmet"
+                    .into(),
+            ),
+            password: None,
+            derivation: Derivation::Eip3(Eip3Derivation::default()),
+        }])
+        .unwrap();
+        assert_eq!(
+            encode(&addrs.head),
+            "9eatpGQdYNjTi5ZZLK7Bo7C3ms6oECPnxbQTRn6sDcBNLMYSCa8"
+        );
+    }
+
+    #[test]
+    fn legacy_derivation_is_default() {
+        let conf = format!("phrase: \"{}\"", PHRASE);
+        let source: SecretSource = serde_yaml::from_str(&conf).unwrap();
+        let (_, addrs) = Wallet::try_from_secrets(vec![source]).unwrap();
+        assert_eq!(
+            encode(&addrs.head),
+            "9hkfeMZj1p3rBQJhENX5EKczYx3uVTjVPdN825YvdLnwzgxkKvj"
+        );
+        let conf = format!(
+            "phrase: \"{}\"\nderivation:\n  scheme: eip3\n  first_index: 2",
+            PHRASE
         );
-        let (_prover, funding_addr) = Wallet::try_from_seed(seed_phrase).expect("Invalid seed");
-        println!(
-            "funding_address: {:?}",
-            AddressEncoder::encode_address_as_string(NetworkPrefix::Mainnet, &funding_addr)
+        let source: SecretSource = serde_yaml::from_str(&conf).unwrap();
+        let (_, addrs) = Wallet::try_from_secrets(vec![source]).unwrap();
+        assert_eq!(
+            encode(&addrs.head),
+            "9hfwC5Nbb42riAjyCBZoyzKborhcuiPvyvs1kZVzqnCfcoB8b2c"
         );
     }
 }
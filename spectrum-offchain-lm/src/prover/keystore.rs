@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde::{Deserialize, Serialize};
+
+/// Seed the master key of a wallet is derived from.
+pub type WalletSeed = [u8; 64];
+
+const PRF: &str = "HmacSHA256";
+const NUM_ITERATIONS: usize = 128000;
+const KEY_LEN_BITS: usize = 256;
+const SALT_LEN: usize = 32;
+const IV_LEN: usize = 16;
+const AUTH_TAG_LEN: usize = 16;
+
+/// Encrypted keystore file with the passphrase taken from the given env var.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeystoreConfig {
+    pub path: PathBuf,
+    pub passphrase_env: String,
+}
+
+impl KeystoreConfig {
+    pub fn load_seed(&self) -> Result<WalletSeed, String> {
+        let passphrase = std::env::var(&self.passphrase_env)
+            .map_err(|_| format!("Keystore passphrase is not set in ${}", self.passphrase_env))?;
+        let file = std::fs::read(&self.path).map_err(|err| format!("Failed to read keystore: {}", err))?;
+        let secret = serde_json::from_slice::<EncryptedSecret>(&file)
+            .map_err(|err| format!("Malformed keystore: {}", err))?;
+        secret.decrypt(&passphrase)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub prf: String,
+    pub c: usize,
+    #[serde(rename = "dkLen")]
+    pub dk_len: usize,
+}
+
+/// Wallet seed encrypted with AES-GCM under a key derived from a passphrase with PBKDF2.
+/// The format is the one of the Ergo node's secret storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedSecret {
+    pub cipher_text: String,
+    pub salt: String,
+    pub iv: String,
+    pub auth_tag: String,
+    pub cipher_params: CipherParams,
+}
+
+impl EncryptedSecret {
+    pub fn encrypt(seed: &WalletSeed, passphrase: &str) -> Result<Self, String> {
+        let cipher_params = CipherParams {
+            prf: PRF.to_string(),
+            c: NUM_ITERATIONS,
+            dk_len: KEY_LEN_BITS,
+        };
+        let mut salt = [0u8; SALT_LEN];
+        let mut iv = [0u8; IV_LEN];
+        rand_bytes(&mut salt).map_err(|err| err.to_string())?;
+        rand_bytes(&mut iv).map_err(|err| err.to_string())?;
+        let key = derive_key(passphrase, &salt, &cipher_params)?;
+        let mut auth_tag = [0u8; AUTH_TAG_LEN];
+        let cipher_text = encrypt_aead(Cipher::aes_256_gcm(), &key, Some(&iv), &[], seed, &mut auth_tag)
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            cipher_text: base16::encode_lower(&cipher_text),
+            salt: base16::encode_lower(&salt),
+            iv: base16::encode_lower(&iv),
+            auth_tag: base16::encode_lower(&auth_tag),
+            cipher_params,
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<WalletSeed, String> {
+        if self.cipher_params.prf != PRF {
+            return Err(format!("Unsupported PRF {}", self.cipher_params.prf));
+        }
+        let decode =
+            |field: &str| base16::decode(field).map_err(|err| format!("Malformed keystore: {}", err));
+        let key = derive_key(passphrase, &decode(&self.salt)?, &self.cipher_params)?;
+        let seed = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&decode(&self.iv)?),
+            &[],
+            &decode(&self.cipher_text)?,
+            &decode(&self.auth_tag)?,
+        )
+        .map_err(|_| "Failed to decrypt keystore, wrong passphrase?".to_string())?;
+        WalletSeed::try_from(seed.as_slice()).map_err(|_| "Keystore holds no valid seed".to_string())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: &CipherParams) -> Result<Vec<u8>, String> {
+    if params.dk_len != KEY_LEN_BITS {
+        return Err(format!("Unsupported key length {}", params.dk_len));
+    }
+    let mut key = vec![0u8; params.dk_len / 8];
+    pbkdf2_hmac(
+        passphrase.as_bytes(),
+        salt,
+        params.c,
+        MessageDigest::sha256(),
+        &mut key,
+    )
+    .map_err(|err| err.to_string())?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prover::keystore::{EncryptedSecret, WalletSeed};
+
+    #[test]
+    fn decrypts_encrypted_seed() {
+        let seed: WalletSeed = [7u8; 64];
+        let secret = EncryptedSecret::encrypt(&seed, "passphrase").unwrap();
+        assert_eq!(secret.decrypt("passphrase").unwrap(), seed);
+        assert!(secret.decrypt("wrong").is_err());
+    }
+}
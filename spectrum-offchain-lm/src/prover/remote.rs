@@ -0,0 +1,200 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::{Transaction, UnsignedInput};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use isahc::config::Configurable;
+use isahc::error::ErrorKind;
+use isahc::{HttpClient, ReadResponseExt, Request};
+use serde::{Deserialize, Serialize};
+
+use ergo_chain_sync::client::types::{with_path, Url};
+use spectrum_offchain::transaction::TransactionCandidate;
+
+use crate::data::funding::ExecutorWallet;
+use crate::prover::{SigmaProver, SigningError};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteSignerConfig {
+    /// Base URL of the signing service.
+    pub url: Url,
+    /// Reach the service through this Unix socket instead of TCP.
+    pub unix_socket: Option<PathBuf>,
+    /// Env var holding the API key of the service.
+    pub api_key_env: Option<String>,
+    /// Address of the key funding executions, held by the service.
+    pub funding_addr: ExecutorWallet,
+    /// Max time to connect to the service, 5 seconds if omitted.
+    pub connect_timeout_secs: Option<u64>,
+    /// Max time to wait for a signed tx, 30 seconds if omitted.
+    pub timeout_secs: Option<u64>,
+}
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Request compatible with `/wallet/transaction/sign` of the Ergo node.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignRequest {
+    tx: UnsignedTransaction,
+    inputs_raw: Vec<String>,
+    data_inputs_raw: Vec<String>,
+}
+
+/// Delegates signing to a remote service holding the secrets.
+/// Blocks the calling thread until the service responds or the request times out.
+pub struct RemoteSigner {
+    client: HttpClient,
+    url: Url,
+    api_key: Option<String>,
+}
+
+impl RemoteSigner {
+    pub fn new(conf: RemoteSignerConfig) -> Result<Self, String> {
+        let mut builder = HttpClient::builder()
+            .connect_timeout(Duration::from_secs(
+                conf.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+            ))
+            .timeout(Duration::from_secs(
+                conf.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ));
+        if let Some(socket) = conf.unix_socket {
+            builder = builder.unix_socket(socket);
+        }
+        let client = builder.build().map_err(|err| err.to_string())?;
+        let api_key = conf
+            .api_key_env
+            .map(|var| std::env::var(&var).map_err(|_| format!("Signer API key is not set in ${}", var)))
+            .transpose()?;
+        Ok(Self {
+            client,
+            url: conf.url,
+            api_key,
+        })
+    }
+}
+
+fn encode_boxes<'a>(boxes: impl Iterator<Item = &'a ErgoBox>) -> Vec<String> {
+    boxes
+        .map(|bx| base16::encode_lower(&bx.sigma_serialize_bytes().unwrap()))
+        .collect()
+}
+
+impl SigmaProver for RemoteSigner {
+    fn sign(&self, tx: TransactionCandidate) -> Result<Transaction, SigningError> {
+        let TransactionCandidate {
+            inputs,
+            data_inputs,
+            output_candidates,
+        } = tx;
+        let unsigned_tx = UnsignedTransaction::new_from_vec(
+            inputs
+                .iter()
+                .map(|(bx, ext)| UnsignedInput {
+                    box_id: bx.box_id(),
+                    extension: ext.clone(),
+                })
+                .collect(),
+            data_inputs
+                .iter()
+                .flat_map(|d| d.iter().map(|bx| bx.box_id().into()))
+                .collect(),
+            output_candidates.to_vec(),
+        )
+        .map_err(|err| SigningError::Remote(err.to_string()))?;
+        let body = SignRequest {
+            tx: unsigned_tx,
+            inputs_raw: encode_boxes(inputs.iter().map(|(bx, _)| bx)),
+            data_inputs_raw: encode_boxes(data_inputs.iter().flat_map(|d| d.iter())),
+        };
+        let mut req = Request::post(with_path(&self.url, "/wallet/transaction/sign"))
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = &self.api_key {
+            req = req.header("api_key", api_key);
+        }
+        let req = req
+            .body(serde_json::to_vec(&body).unwrap())
+            .map_err(|err| SigningError::Remote(err.to_string()))?;
+        let mut res = self.client.send(req).map_err(|err| match err.kind() {
+            ErrorKind::Timeout => SigningError::Timeout,
+            _ => SigningError::Remote(err.to_string()),
+        })?;
+        if res.status().is_success() {
+            res.json::<Transaction>()
+                .map_err(|err| SigningError::Remote(format!("Malformed response: {}", err)))
+        } else {
+            Err(SigningError::Remote(format!(
+                "expected 200 from /wallet/transaction/sign, got {:?}: {}",
+                res.status(),
+                res.text().unwrap_or_default()
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    use ergo_lib::chain::transaction::{TxId, TxIoVec};
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+    use ergo_lib::ergotree_ir::chain::address::Address;
+    use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate, NonMandatoryRegisters};
+    use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+    use sigma_test_util::force_any_val;
+
+    use spectrum_offchain::transaction::TransactionCandidate;
+
+    use crate::data::funding::ExecutorWallet;
+    use crate::prover::remote::{RemoteSigner, RemoteSignerConfig};
+    use crate::prover::{SigmaProver, SigningError};
+
+    #[test]
+    fn signer_which_never_responds_times_out() {
+        // Connections are accepted by the OS, but requests are never read.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let wallet = ExecutorWallet::from(Address::P2Pk(force_any_val::<ProveDlog>()));
+        let signer = RemoteSigner::new(RemoteSignerConfig {
+            url: format!("http://{}", listener.local_addr().unwrap())
+                .parse()
+                .unwrap(),
+            unix_socket: None,
+            api_key_env: None,
+            funding_addr: wallet.clone(),
+            connect_timeout_secs: Some(1),
+            timeout_secs: Some(1),
+        })
+        .unwrap();
+        let input = ErgoBox::new(
+            1_000_000u64.try_into().unwrap(),
+            wallet.ergo_tree(),
+            None,
+            NonMandatoryRegisters::empty(),
+            0,
+            force_any_val::<TxId>(),
+            0,
+        )
+        .unwrap();
+        let output = ErgoBoxCandidate {
+            value: 1_000_000u64.try_into().unwrap(),
+            ergo_tree: wallet.ergo_tree(),
+            tokens: None,
+            additional_registers: NonMandatoryRegisters::empty(),
+            creation_height: 0,
+        };
+        let tx = TransactionCandidate::new(
+            TxIoVec::from_vec(vec![(input, ContextExtension::empty())]).unwrap(),
+            None,
+            TxIoVec::from_vec(vec![output]).unwrap(),
+        );
+        let started_at = Instant::now();
+        let res = signer.sign(tx);
+        assert!(matches!(res, Err(SigningError::Timeout)));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        drop(listener);
+    }
+}
@@ -1,16 +1,18 @@
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_std::task::spawn_blocking;
 use async_trait::async_trait;
 use chrono::Utc;
 use log::trace;
+use prometheus::{IntCounter, Registry};
 use rocksdb::{Direction, IteratorMode, ReadOptions};
 
 use ergo_chain_sync::rocksdb::RocksConfig;
 use spectrum_offchain::binary::{prefixed_key, raw_prefixed_key};
 
 use crate::data::PoolId;
-use crate::scheduler::data::{PoolSchedule, Tick};
+use crate::scheduler::data::{PoolSchedule, Tick, UpcomingTick};
 
 pub mod data;
 pub mod process;
@@ -30,6 +32,11 @@ pub trait ScheduleRepo {
     async fn defer(&mut self, tick: Tick, until: i64);
     /// Eliminate all references to the given pool.
     async fn clean(&mut self, pool_id: PoolId);
+    /// Get all pending ticks followed by deferred ones.
+    async fn upcoming(&self) -> Vec<UpcomingTick>;
+    /// Re-enqueue compounding of the given epoch of the pool.
+    /// Returns `None` if the pool is unknown or the epoch is out of the program.
+    async fn reschedule(&mut self, pool_id: PoolId, epoch_ix: u32) -> Option<Tick>;
 }
 
 pub struct ScheduleRepoTracing<R> {
@@ -78,6 +85,75 @@ where
         self.inner.clean(pool_id).await;
         trace!(target: "schedules", "clean({}) -> ()", pool_id);
     }
+
+    async fn upcoming(&self) -> Vec<UpcomingTick> {
+        self.inner.upcoming().await
+    }
+
+    async fn reschedule(&mut self, pool_id: PoolId, epoch_ix: u32) -> Option<Tick> {
+        trace!(target: "schedules", "reschedule(pool_id: {}, epoch_ix: {})", pool_id, epoch_ix);
+        let res = self.inner.reschedule(pool_id, epoch_ix).await;
+        trace!(target: "schedules", "reschedule(pool_id: {}, epoch_ix: {}) -> {:?}", pool_id, epoch_ix, res);
+        res
+    }
+}
+
+/// Counts ticks deferred by the scheduler and ticks removed once fully processed.
+pub struct ScheduleRepoMetrics<R> {
+    inner: R,
+    ticks_deferred: IntCounter,
+    ticks_processed: IntCounter,
+}
+
+impl<R> ScheduleRepoMetrics<R> {
+    pub fn wrap(repo: R, registry: &Registry) -> Self {
+        let ticks_deferred = IntCounter::new("scheduler_ticks_deferred_total", "Ticks deferred").unwrap();
+        let ticks_processed =
+            IntCounter::new("scheduler_ticks_processed_total", "Ticks fully processed").unwrap();
+        registry.register(Box::new(ticks_deferred.clone())).unwrap();
+        registry.register(Box::new(ticks_processed.clone())).unwrap();
+        Self {
+            inner: repo,
+            ticks_deferred,
+            ticks_processed,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<R> ScheduleRepo for ScheduleRepoMetrics<R>
+where
+    R: ScheduleRepo,
+{
+    async fn update_schedule(&mut self, schedule: PoolSchedule) -> Result<(), ProgramExhausted> {
+        self.inner.update_schedule(schedule).await
+    }
+
+    async fn peek(&mut self) -> Option<Tick> {
+        self.inner.peek().await
+    }
+
+    async fn remove(&mut self, tick: Tick) {
+        self.inner.remove(tick).await;
+        self.ticks_processed.inc();
+    }
+
+    async fn defer(&mut self, tick: Tick, until: i64) {
+        self.inner.defer(tick, until).await;
+        self.ticks_deferred.inc();
+    }
+
+    async fn clean(&mut self, pool_id: PoolId) {
+        self.inner.clean(pool_id).await
+    }
+
+    async fn upcoming(&self) -> Vec<UpcomingTick> {
+        self.inner.upcoming().await
+    }
+
+    async fn reschedule(&mut self, pool_id: PoolId, epoch_ix: u32) -> Option<Tick> {
+        self.inner.reschedule(pool_id, epoch_ix).await
+    }
 }
 
 pub struct ScheduleRepoRocksDB {
@@ -235,6 +311,213 @@ impl ScheduleRepo for ScheduleRepoRocksDB {
         })
         .await
     }
+
+    async fn upcoming(&self) -> Vec<UpcomingTick> {
+        let db = Arc::clone(&self.db);
+        spawn_blocking(move || {
+            let get_tick = |pid: PoolId| {
+                db.get(prefixed_key(SCHEDULE_PREFIX, &pid))
+                    .unwrap()
+                    .and_then(|bs| bincode::deserialize::<PoolSchedule>(&bs).ok())
+                    .and_then(|sc| Tick::try_from(sc).ok())
+            };
+            let mut upcoming = Vec::new();
+            let ticks_prefix = bincode::serialize(TICKS_PREFIX).unwrap();
+            let mut readopts = ReadOptions::default();
+            readopts.set_iterate_range(rocksdb::PrefixRange(ticks_prefix.clone()));
+            for (bs, _) in db
+                .iterator_opt(IteratorMode::From(&ticks_prefix, Direction::Forward), readopts)
+                .filter_map(|res| res.ok())
+            {
+                if let Some(tick) = destructure_tick_key(&bs).and_then(get_tick) {
+                    upcoming.push(UpcomingTick {
+                        tick,
+                        deferred_until: None,
+                    });
+                }
+            }
+            let deferred_ticks_prefix = bincode::serialize(DEFERRED_TICKS_PREFIX).unwrap();
+            let mut readopts = ReadOptions::default();
+            readopts.set_iterate_range(rocksdb::PrefixRange(deferred_ticks_prefix.clone()));
+            for (bs, deferred_until) in db
+                .iterator_opt(
+                    IteratorMode::From(&deferred_ticks_prefix, Direction::Forward),
+                    readopts,
+                )
+                .filter_map(|res| res.ok())
+            {
+                let deferred_until = bincode::deserialize::<i64>(&deferred_until).ok();
+                if let Some(tick) = destructure_deferred_tick_key(&bs).and_then(get_tick) {
+                    upcoming.push(UpcomingTick { tick, deferred_until });
+                }
+            }
+            upcoming
+        })
+        .await
+    }
+
+    async fn reschedule(&mut self, pool_id: PoolId, epoch_ix: u32) -> Option<Tick> {
+        let db = Arc::clone(&self.db);
+        spawn_blocking(move || {
+            let schedule_key = prefixed_key(SCHEDULE_PREFIX, &pool_id);
+            let schedule = db
+                .get(&schedule_key)
+                .unwrap()
+                .and_then(|bs| bincode::deserialize::<PoolSchedule>(&bs).ok())?;
+            let schedule = rescheduled(schedule, epoch_ix)?;
+            let tick = Tick::try_from(schedule.clone()).ok()?;
+            let transaction = db.transaction();
+            // Drop all ticks of the pool, so that only the rescheduled one is peeked.
+            for pfx in [TICKS_PREFIX, DEFERRED_TICKS_PREFIX] {
+                let prefix = bincode::serialize(pfx).unwrap();
+                let mut readopts = ReadOptions::default();
+                readopts.set_iterate_range(rocksdb::PrefixRange(prefix.clone()));
+                for (bs, _) in db
+                    .iterator_opt(IteratorMode::From(&prefix, Direction::Forward), readopts)
+                    .filter_map(|res| res.ok())
+                {
+                    if extract_pool_id(&bs) == Some(pool_id) {
+                        transaction.delete(bs).unwrap();
+                    }
+                }
+            }
+            transaction
+                .put(schedule_key, bincode::serialize(&schedule).unwrap())
+                .unwrap();
+            transaction
+                .put(tick_key(TICKS_PREFIX, &pool_id, &tick.height), vec![])
+                .unwrap();
+            transaction.commit().unwrap();
+            Some(tick)
+        })
+        .await
+    }
+}
+
+/// In-memory implementation of `ScheduleRepo`.
+/// Ticks are ordered by the same keys as in `ScheduleRepoRocksDB`, so peeking order matches.
+#[derive(Default)]
+pub struct InMemoryScheduleRepo {
+    schedules: HashMap<PoolId, PoolSchedule>,
+    ticks: BTreeMap<Vec<u8>, PoolId>,
+    deferred_ticks: BTreeMap<Vec<u8>, (PoolId, i64)>,
+}
+
+impl InMemoryScheduleRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn try_tick(&self, pid: &PoolId) -> Option<Tick> {
+        self.schedules.get(pid).and_then(|sc| sc.clone().try_into().ok())
+    }
+}
+
+#[async_trait(?Send)]
+impl ScheduleRepo for InMemoryScheduleRepo {
+    async fn update_schedule(&mut self, schedule: PoolSchedule) -> Result<(), ProgramExhausted> {
+        let pid = schedule.pool_id;
+        if let Some(next_height) = schedule.next_compounding_at() {
+            let prev_height = next_height.saturating_sub(schedule.epoch_len);
+            let after_next_height = next_height + schedule.epoch_len;
+            let tried_tick = tick_key(DEFERRED_TICKS_PREFIX, &pid, &next_height);
+            let tick = tick_key(TICKS_PREFIX, &pid, &next_height);
+            // Write updated schedule only in case we haven't tried to compound it already on this height.
+            if !self.deferred_ticks.contains_key(&tried_tick) && !self.ticks.contains_key(&tick) {
+                self.schedules.insert(pid, schedule);
+                self.ticks.insert(tick, pid);
+                self.ticks.remove(&tick_key(TICKS_PREFIX, &pid, &prev_height));
+                self.ticks
+                    .remove(&tick_key(TICKS_PREFIX, &pid, &after_next_height));
+                self.deferred_ticks
+                    .remove(&tick_key(DEFERRED_TICKS_PREFIX, &pid, &prev_height));
+                self.deferred_ticks
+                    .remove(&tick_key(DEFERRED_TICKS_PREFIX, &pid, &after_next_height));
+            }
+            Ok(())
+        } else {
+            Err(ProgramExhausted)
+        }
+    }
+
+    async fn peek(&mut self) -> Option<Tick> {
+        // First we try to peek closest pending tick.
+        let pending_tick = self.ticks.values().find_map(|pid| self.try_tick(pid));
+        if pending_tick.is_some() {
+            trace!(target: "schedules", "pending tick chosen: {:?}", pending_tick);
+            return pending_tick;
+        }
+        // If there are no pending ticks we check deferred ticks.
+        let ts_now = Utc::now().timestamp();
+        for (pid, deferred_until) in self.deferred_ticks.values() {
+            if *deferred_until > ts_now {
+                break;
+            }
+            if let Some(tick) = self.try_tick(pid) {
+                trace!(target: "schedules", "deferred tick chosen: {:?}", tick);
+                return Some(tick);
+            }
+        }
+        None
+    }
+
+    async fn remove(&mut self, tick: Tick) {
+        self.deferred_ticks
+            .remove(&tick_key(DEFERRED_TICKS_PREFIX, &tick.pool_id, &tick.height));
+    }
+
+    async fn defer(&mut self, tick: Tick, until: i64) {
+        self.ticks
+            .remove(&tick_key(TICKS_PREFIX, &tick.pool_id, &tick.height));
+        self.deferred_ticks.insert(
+            tick_key(DEFERRED_TICKS_PREFIX, &tick.pool_id, &tick.height),
+            (tick.pool_id, until),
+        );
+    }
+
+    async fn clean(&mut self, pool_id: PoolId) {
+        self.schedules.remove(&pool_id);
+        self.ticks.retain(|_, pid| *pid != pool_id);
+        self.deferred_ticks.retain(|_, (pid, _)| *pid != pool_id);
+    }
+
+    async fn upcoming(&self) -> Vec<UpcomingTick> {
+        let pending = self.ticks.values().filter_map(|pid| {
+            self.try_tick(pid).map(|tick| UpcomingTick {
+                tick,
+                deferred_until: None,
+            })
+        });
+        let deferred = self.deferred_ticks.values().filter_map(|(pid, until)| {
+            self.try_tick(pid).map(|tick| UpcomingTick {
+                tick,
+                deferred_until: Some(*until),
+            })
+        });
+        pending.chain(deferred).collect()
+    }
+
+    async fn reschedule(&mut self, pool_id: PoolId, epoch_ix: u32) -> Option<Tick> {
+        let schedule = rescheduled(self.schedules.get(&pool_id)?.clone(), epoch_ix)?;
+        let tick = Tick::try_from(schedule.clone()).ok()?;
+        self.ticks.retain(|_, pid| *pid != pool_id);
+        self.deferred_ticks.retain(|_, (pid, _)| *pid != pool_id);
+        self.schedules.insert(pool_id, schedule);
+        self.ticks
+            .insert(tick_key(TICKS_PREFIX, &pool_id, &tick.height), pool_id);
+        Some(tick)
+    }
+}
+
+/// Rewind the given schedule so that the given epoch is the next one to compound.
+fn rescheduled(schedule: PoolSchedule, epoch_ix: u32) -> Option<PoolSchedule> {
+    if epoch_ix == 0 || epoch_ix > schedule.epoch_num {
+        return None;
+    }
+    Some(PoolSchedule {
+        last_completed_epoch_ix: epoch_ix - 1,
+        ..schedule
+    })
 }
 
 /// Schedules: (PREFIX:PoolId -> Schedule)
@@ -295,10 +578,10 @@ mod tests {
 
     use crate::data::pool::Pool;
     use crate::data::{AsBox, PoolId};
-    use crate::scheduler::data::PoolSchedule;
+    use crate::scheduler::data::{PoolSchedule, Tick, UpcomingTick};
     use crate::scheduler::{
-        destructure_deferred_tick_key, destructure_tick_key, extract_pool_id, tick_key, ScheduleRepo,
-        ScheduleRepoRocksDB, DEFERRED_TICKS_PREFIX, SCHEDULE_PREFIX, TICKS_PREFIX,
+        destructure_deferred_tick_key, destructure_tick_key, extract_pool_id, tick_key, InMemoryScheduleRepo,
+        ScheduleRepo, ScheduleRepoRocksDB, DEFERRED_TICKS_PREFIX, SCHEDULE_PREFIX, TICKS_PREFIX,
     };
 
     fn rocks_db_client() -> ScheduleRepoRocksDB {
@@ -328,7 +611,15 @@ mod tests {
 
     #[tokio::test]
     async fn put_real_schedule_peek_ticks() {
-        let mut client = rocks_db_client();
+        test_put_real_schedule_peek_ticks(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn put_real_schedule_peek_ticks_in_memory() {
+        test_put_real_schedule_peek_ticks(InMemoryScheduleRepo::new()).await;
+    }
+
+    async fn test_put_real_schedule_peek_ticks<C: ScheduleRepo>(mut client: C) {
         let pool_box: ErgoBox = serde_json::from_str(POOL_JSON).unwrap();
         let pool = <AsBox<Pool>>::try_from_box(pool_box).unwrap();
         let schedule = PoolSchedule::from(pool.1);
@@ -343,7 +634,15 @@ mod tests {
 
     #[tokio::test]
     async fn put_interfering_schedules_peek_ticks() {
-        let mut client = rocks_db_client();
+        test_put_interfering_schedules_peek_ticks(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn put_interfering_schedules_peek_ticks_in_memory() {
+        test_put_interfering_schedules_peek_ticks(InMemoryScheduleRepo::new()).await;
+    }
+
+    async fn test_put_interfering_schedules_peek_ticks<C: ScheduleRepo>(mut client: C) {
         let schedule_1 = PoolSchedule {
             pool_id: PoolId::from(TokenId::from(Digest32::from([0u8; 32]))),
             epoch_len: 10,
@@ -371,7 +670,15 @@ mod tests {
 
     #[tokio::test]
     async fn peek_defer_remove() {
-        let mut client = rocks_db_client();
+        test_peek_defer_remove(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn peek_defer_remove_in_memory() {
+        test_peek_defer_remove(InMemoryScheduleRepo::new()).await;
+    }
+
+    async fn test_peek_defer_remove<C: ScheduleRepo>(mut client: C) {
         let schedule_1 = PoolSchedule {
             pool_id: PoolId::from(TokenId::from(Digest32::from([0u8; 32]))),
             epoch_len: 10,
@@ -390,6 +697,81 @@ mod tests {
         assert!(client.peek().await.is_none());
     }
 
+    #[tokio::test]
+    async fn upcoming_ticks() {
+        test_upcoming_ticks(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn upcoming_ticks_in_memory() {
+        test_upcoming_ticks(InMemoryScheduleRepo::new()).await;
+    }
+
+    async fn test_upcoming_ticks<C: ScheduleRepo>(mut client: C) {
+        let schedule_1 = PoolSchedule {
+            pool_id: PoolId::from(TokenId::from(Digest32::from([0u8; 32]))),
+            epoch_len: 10,
+            epoch_num: 10,
+            program_start: 100,
+            last_completed_epoch_ix: 0,
+        };
+        let schedule_2 = PoolSchedule {
+            pool_id: PoolId::from(TokenId::from(Digest32::from([1u8; 32]))),
+            epoch_len: 15,
+            epoch_num: 15,
+            program_start: 110,
+            last_completed_epoch_ix: 0,
+        };
+        client.update_schedule(schedule_1.clone()).await.unwrap();
+        client.update_schedule(schedule_2.clone()).await.unwrap();
+        let tick_1 = client.peek().await.unwrap();
+        client.defer(tick_1, 1000).await;
+        let tick_2 = Tick::try_from(schedule_2).unwrap();
+        assert_eq!(
+            client.upcoming().await,
+            vec![
+                UpcomingTick {
+                    tick: tick_2,
+                    deferred_until: None,
+                },
+                UpcomingTick {
+                    tick: tick_1,
+                    deferred_until: Some(1000),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reschedule_tick() {
+        test_reschedule_tick(rocks_db_client()).await;
+    }
+
+    #[tokio::test]
+    async fn reschedule_tick_in_memory() {
+        test_reschedule_tick(InMemoryScheduleRepo::new()).await;
+    }
+
+    async fn test_reschedule_tick<C: ScheduleRepo>(mut client: C) {
+        let schedule = PoolSchedule {
+            pool_id: PoolId::from(TokenId::from(Digest32::from([0u8; 32]))),
+            epoch_len: 10,
+            epoch_num: 10,
+            program_start: 100,
+            last_completed_epoch_ix: 2,
+        };
+        client.update_schedule(schedule.clone()).await.unwrap();
+        let tick = client.peek().await.unwrap();
+        client.defer(tick, <i64>::MAX).await;
+        assert!(client.peek().await.is_none());
+        assert!(client.reschedule(schedule.pool_id, 11).await.is_none());
+
+        let rescheduled = client.reschedule(schedule.pool_id, 2).await.unwrap();
+        assert_eq!(rescheduled.epoch_ix, 2);
+        assert_eq!(rescheduled.height, 120);
+        assert_eq!(client.peek().await, Some(rescheduled));
+    }
+
     #[test]
     fn extract_pool_id_from_any_key() {
         let pool_id = PoolId::from(force_any_val::<TokenId>());
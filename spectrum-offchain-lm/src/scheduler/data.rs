@@ -13,6 +13,14 @@ pub struct Tick {
     pub height: u32,
 }
 
+/// A tick waiting to be processed. Ticks that were already tried carry the timestamp
+/// processing is deferred until.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct UpcomingTick {
+    pub tick: Tick,
+    pub deferred_until: Option<i64>,
+}
+
 /// A set of time points when a particular pool should distribute rewards.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoolSchedule {
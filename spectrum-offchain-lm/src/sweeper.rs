@@ -0,0 +1,417 @@
+use std::cell::Cell;
+use std::sync::Arc;
+
+use ergo_lib::chain::transaction::TxIoVec;
+use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use log::{info, trace, warn};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use spectrum_offchain::data::unique_entity::Predicted;
+use spectrum_offchain::event_sink::handlers::types::IntoBoxCandidate;
+use spectrum_offchain::network::{ErgoNetwork, NodeSubmitTxError};
+use spectrum_offchain::transaction::{TransactionCandidate, UnsignedTransactionOps};
+
+use crate::data::executor::ExecutorOutput;
+use crate::data::funding::{DistributionFunding, DistributionFundingProto, ExecutorWallet};
+use crate::data::miner::MinerOutput;
+use crate::data::{AsBox, FundingId};
+use crate::ergo::{NanoErg, MIN_SAFE_BOX_VALUE};
+use crate::funding::FundingRepo;
+use crate::prover::SigmaProver;
+
+pub mod process;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweeperConfig {
+    /// Address the surplus of rewards is forwarded to.
+    pub cold_addr: ExecutorWallet,
+    /// Funding boxes are topped up from rewards to hold at least this value in total.
+    pub funding_target: NanoErg,
+    /// Rewards left after topping up funding are forwarded once they reach this value.
+    pub forward_threshold: NanoErg,
+    /// Funding boxes are merged into one once there are more of them.
+    pub max_funding_boxes: usize,
+    pub miner_fee: NanoErg,
+    pub sweep_interval_secs: u64,
+    pub reward_repo_db_path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub tx: TransactionCandidate,
+    /// Funding box created by the sweep.
+    pub next_funding: Option<AsBox<DistributionFunding>>,
+}
+
+fn total_value(boxes: &[AsBox<DistributionFunding>]) -> NanoErg {
+    boxes.iter().map(|AsBox(_, f)| f.erg_value).sum()
+}
+
+/// Plan a tx moving `rewards` to funding and to the cold address and merging `funding` boxes.
+/// Returns `None` if there is nothing worth sweeping.
+pub fn plan_sweep(
+    funding: Vec<AsBox<DistributionFunding>>,
+    rewards: Vec<AsBox<DistributionFunding>>,
+    funding_prop: ErgoTree,
+    conf: &SweeperConfig,
+    height: u32,
+) -> Option<Sweep> {
+    // Boxes holding tokens are left untouched, so that tokens are never burned.
+    let funding = funding
+        .into_iter()
+        .filter(|AsBox(bx, _)| bx.tokens.is_none())
+        .collect::<Vec<_>>();
+    // Rewards paid to the funding address are already tracked as funding.
+    let rewards = rewards
+        .into_iter()
+        .filter(|AsBox(bx, r)| bx.tokens.is_none() && funding.iter().all(|AsBox(_, f)| f.id != r.id))
+        .collect::<Vec<_>>();
+    let zero = NanoErg::from(0);
+    let deficit = conf.funding_target.safe_sub(total_value(&funding));
+    let surplus = total_value(&rewards).safe_sub(deficit);
+    let top_up = deficit > zero && !rewards.is_empty();
+    let forward = surplus > zero && surplus >= conf.forward_threshold;
+    let consolidate = funding.len() > conf.max_funding_boxes;
+    if !top_up && !forward && !consolidate {
+        return None;
+    }
+    let mut inputs = Vec::new();
+    if top_up || forward {
+        inputs.extend(rewards);
+    }
+    if consolidate {
+        inputs.extend(funding);
+    }
+    let available = total_value(&inputs).safe_sub(conf.miner_fee);
+    let mut cold_value = if forward { surplus.min(available) } else { zero };
+    let mut funding_value = available.safe_sub(cold_value);
+    if funding_value > zero && funding_value < MIN_SAFE_BOX_VALUE {
+        if forward {
+            cold_value = available;
+            funding_value = zero;
+        } else {
+            return None;
+        }
+    }
+    if (cold_value > zero && cold_value < MIN_SAFE_BOX_VALUE) || cold_value + funding_value == zero {
+        return None;
+    }
+    let mut outputs = Vec::new();
+    if funding_value > zero {
+        let proto = DistributionFundingProto {
+            prop: funding_prop.clone(),
+            erg_value: funding_value,
+        };
+        outputs.push(proto.into_candidate(height));
+    }
+    if cold_value > zero {
+        let cold_out = ExecutorOutput {
+            executor_prop: conf.cold_addr.ergo_tree(),
+            erg_value: cold_value,
+        };
+        outputs.push(cold_out.into_candidate(height));
+    }
+    outputs.push(
+        MinerOutput {
+            erg_value: conf.miner_fee,
+        }
+        .into_candidate(height),
+    );
+    let tx = TransactionCandidate::new(
+        TxIoVec::from_vec(
+            inputs
+                .into_iter()
+                .map(|AsBox(bx, _)| (bx, ContextExtension::empty()))
+                .collect(),
+        )
+        .ok()?,
+        None,
+        TxIoVec::from_vec(outputs).ok()?,
+    );
+    let next_funding = if funding_value > zero {
+        let out = tx.clone().into_tx_without_proofs().outputs.first().clone();
+        let funding = DistributionFunding {
+            id: FundingId::from(out.box_id()),
+            prop: funding_prop,
+            erg_value: funding_value,
+        };
+        Some(AsBox(out, funding))
+    } else {
+        None
+    };
+    Some(Sweep { tx, next_funding })
+}
+
+/// Periodically sweeps operator rewards into funding and to the cold address.
+pub struct Sweeper<'a, TNetwork, TFunding, TRewards, TProver> {
+    network: &'a TNetwork,
+    funding_repo: Arc<Mutex<TFunding>>,
+    reward_repo: Arc<Mutex<TRewards>>,
+    prover: TProver,
+    funding_prop: ErgoTree,
+    conf: SweeperConfig,
+    /// Height at which inputs of the last sweep were found spent by a mempool tx.
+    double_spent_at: Cell<Option<u32>>,
+}
+
+impl<'a, TNetwork, TFunding, TRewards, TProver> Sweeper<'a, TNetwork, TFunding, TRewards, TProver>
+where
+    TNetwork: ErgoNetwork,
+    TFunding: FundingRepo,
+    TRewards: FundingRepo,
+    TProver: SigmaProver,
+{
+    pub fn new(
+        network: &'a TNetwork,
+        funding_repo: Arc<Mutex<TFunding>>,
+        reward_repo: Arc<Mutex<TRewards>>,
+        prover: TProver,
+        funding_prop: ErgoTree,
+        conf: SweeperConfig,
+    ) -> Self {
+        Self {
+            network,
+            funding_repo,
+            reward_repo,
+            prover,
+            funding_prop,
+            conf,
+            double_spent_at: Cell::new(None),
+        }
+    }
+
+    pub async fn sweep(&self) {
+        // Repos are locked until the sweep is submitted so that its inputs aren't collected elsewhere.
+        let mut funding_repo = self.funding_repo.lock().await;
+        let mut reward_repo = self.reward_repo.lock().await;
+        let height = self.network.get_height().await;
+        // Conflicting mempool tx isn't known, so we wait for the next block to settle it.
+        if matches!(self.double_spent_at.get(), Some(h) if h >= height) {
+            trace!(target: "sweeper", "Sweep is postponed until the next block");
+            return;
+        }
+        // Boxes selected for txs in flight are left to them.
+        let funding = funding_repo.get_available().await;
+        let rewards = reward_repo.get_available().await;
+        let Some(Sweep { tx, next_funding }) =
+            plan_sweep(funding, rewards, self.funding_prop.clone(), &self.conf, height)
+        else {
+            return;
+        };
+        let spent = tx
+            .inputs
+            .iter()
+            .map(|(bx, _)| FundingId::from(bx.box_id()))
+            .collect::<Vec<_>>();
+        let tx = match self.prover.sign(tx) {
+            Ok(tx) => tx,
+            Err(err) => {
+                warn!(target: "sweeper", "Failed to sign sweep due to {}", err);
+                return;
+            }
+        };
+        let tx_id = tx.id();
+        match self.network.submit_tx(tx).await {
+            Ok(()) => {
+                self.double_spent_at.set(None);
+                info!(target: "sweeper", "Sweep [{:?}] of {} boxes submitted", tx_id, spent.len());
+                for fid in spent {
+                    funding_repo.remove(fid).await;
+                    reward_repo.remove(fid).await;
+                }
+                if let Some(next_funding) = next_funding {
+                    funding_repo.put_predicted(Predicted(next_funding)).await;
+                }
+            }
+            Err(err) => {
+                warn!(target: "sweeper", "Sweep [{:?}] rejected due to {}", tx_id, err);
+                match err {
+                    NodeSubmitTxError::MissingInputs(missing_indices) => {
+                        for ix in missing_indices {
+                            if let Some(fid) = spent.get(ix as usize) {
+                                funding_repo.remove(*fid).await;
+                                reward_repo.remove(*fid).await;
+                            }
+                        }
+                    }
+                    NodeSubmitTxError::DoubleSpend => self.double_spent_at.set(Some(height)),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ergo_lib::chain::transaction::TxIoVec;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+    use ergo_lib::ergotree_ir::chain::address::Address;
+    use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisters};
+    use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+    use sigma_test_util::force_any_val;
+    use tokio::sync::Mutex;
+
+    use spectrum_offchain::data::unique_entity::Confirmed;
+    use spectrum_offchain::event_sink::handlers::types::IntoBoxCandidate;
+    use spectrum_offchain::network::simulator::NodeSimulator;
+    use spectrum_offchain::network::ErgoNetwork;
+    use spectrum_offchain::transaction::{TransactionCandidate, UnsignedTransactionOps};
+
+    use crate::data::funding::{DistributionFunding, DistributionFundingProto, ExecutorWallet};
+    use crate::data::{AsBox, FundingId};
+    use crate::ergo::NanoErg;
+    use crate::funding::{FundingRepo, InMemoryFundingRepo};
+    use crate::prover::NoopProver;
+    use crate::sweeper::{plan_sweep, Sweeper, SweeperConfig};
+
+    fn wallet() -> ExecutorWallet {
+        ExecutorWallet::from(Address::P2Pk(force_any_val::<ProveDlog>()))
+    }
+
+    fn boxes(wallet: &ExecutorWallet, values: Vec<u64>) -> Vec<AsBox<DistributionFunding>> {
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(ix, value)| {
+                let bx = ErgoBox::new(
+                    value.try_into().unwrap(),
+                    wallet.ergo_tree(),
+                    None,
+                    NonMandatoryRegisters::empty(),
+                    0,
+                    force_any_val(),
+                    ix as u16,
+                )
+                .unwrap();
+                let funding = DistributionFunding {
+                    id: FundingId::from(bx.box_id()),
+                    prop: bx.ergo_tree.clone(),
+                    erg_value: NanoErg::from(value),
+                };
+                AsBox(bx, funding)
+            })
+            .collect()
+    }
+
+    fn conf() -> SweeperConfig {
+        SweeperConfig {
+            cold_addr: wallet(),
+            funding_target: NanoErg::from(10_000_000),
+            forward_threshold: NanoErg::from(5_000_000),
+            max_funding_boxes: 3,
+            miner_fee: NanoErg::from(1_000_000),
+            sweep_interval_secs: 60,
+            reward_repo_db_path: String::new(),
+        }
+    }
+
+    async fn repo_of(node: &NodeSimulator, boxes: Vec<AsBox<DistributionFunding>>) -> InMemoryFundingRepo {
+        node.add_utxos(boxes.iter().map(|AsBox(bx, _)| bx.clone()).collect());
+        let mut repo = InMemoryFundingRepo::new();
+        for df in boxes {
+            repo.put_confirmed(Confirmed(df)).await;
+        }
+        repo
+    }
+
+    #[test]
+    fn nothing_to_sweep() {
+        let funding_wallet = wallet();
+        let funding = boxes(&funding_wallet, vec![20_000_000]);
+        let rewards = boxes(&wallet(), vec![1_000_000, 1_000_000]);
+        assert!(plan_sweep(funding, rewards, funding_wallet.ergo_tree(), &conf(), 1).is_none());
+    }
+
+    #[test]
+    fn rewards_top_up_funding_and_surplus_is_forwarded() {
+        let conf = conf();
+        let funding_wallet = wallet();
+        let funding = boxes(&funding_wallet, vec![4_000_000]);
+        let rewards = boxes(&wallet(), vec![5_000_000, 5_000_000, 4_000_000]);
+        let sweep = plan_sweep(funding, rewards, funding_wallet.ergo_tree(), &conf, 1).unwrap();
+        assert_eq!(sweep.tx.inputs.len(), 3);
+        let outputs = sweep.tx.output_candidates;
+        assert_eq!(outputs.len(), 3);
+        // Deficit of 6M nanoERG is covered minus the fee, surplus of 8M nanoERG goes to the cold address.
+        assert_eq!(outputs[0].ergo_tree, funding_wallet.ergo_tree());
+        assert_eq!(*outputs[0].value.as_u64(), 5_000_000);
+        assert_eq!(outputs[1].ergo_tree, conf.cold_addr.ergo_tree());
+        assert_eq!(*outputs[1].value.as_u64(), 8_000_000);
+        let next_funding = sweep.next_funding.unwrap();
+        assert_eq!(next_funding.1.erg_value, NanoErg::from(5_000_000));
+    }
+
+    #[test]
+    fn funding_boxes_are_consolidated() {
+        let funding_wallet = wallet();
+        let funding = boxes(&funding_wallet, vec![5_000_000, 5_000_000, 5_000_000, 5_000_000]);
+        let sweep = plan_sweep(funding, Vec::new(), funding_wallet.ergo_tree(), &conf(), 1).unwrap();
+        assert_eq!(sweep.tx.inputs.len(), 4);
+        assert_eq!(sweep.tx.output_candidates.len(), 2);
+        assert_eq!(*sweep.tx.output_candidates[0].value.as_u64(), 19_000_000);
+    }
+
+    #[tokio::test]
+    async fn funding_selected_for_txs_in_flight_is_not_swept() {
+        let node = NodeSimulator::new(0, 0);
+        let funding_wallet = wallet();
+        let funding = boxes(&funding_wallet, vec![5_000_000, 5_000_000, 5_000_000, 5_000_000]);
+        let mut funding_repo = repo_of(&node, funding).await;
+        funding_repo.collect(NanoErg::from(10_000_000)).await.unwrap();
+        let sweeper = Sweeper::new(
+            &node,
+            Arc::new(Mutex::new(funding_repo)),
+            Arc::new(Mutex::new(InMemoryFundingRepo::new())),
+            NoopProver,
+            funding_wallet.ergo_tree(),
+            conf(),
+        );
+        sweeper.sweep().await;
+        assert!(node.mempool().is_empty());
+    }
+
+    #[tokio::test]
+    async fn double_spent_sweep_is_postponed_until_next_block() {
+        let node = NodeSimulator::new(0, 0);
+        let funding_wallet = wallet();
+        let rewards = boxes(&wallet(), vec![5_000_000, 5_000_000]);
+        let AsBox(conflicting_input, _) = rewards[0].clone();
+        let reward_repo = repo_of(&node, rewards).await;
+        let sweeper = Sweeper::new(
+            &node,
+            Arc::new(Mutex::new(InMemoryFundingRepo::new())),
+            Arc::new(Mutex::new(reward_repo)),
+            NoopProver,
+            funding_wallet.ergo_tree(),
+            conf(),
+        );
+        let conflicting_output = DistributionFundingProto {
+            prop: wallet().ergo_tree(),
+            erg_value: NanoErg::from(5_000_000),
+        };
+        let conflicting_tx = TransactionCandidate::new(
+            TxIoVec::from_vec(vec![(conflicting_input, ContextExtension::empty())]).unwrap(),
+            None,
+            TxIoVec::from_vec(vec![conflicting_output.into_candidate(0)]).unwrap(),
+        )
+        .into_tx_without_proofs();
+        let conflicting_tx_id = conflicting_tx.id();
+        node.submit_tx(conflicting_tx).await.unwrap();
+
+        sweeper.sweep().await;
+        assert_eq!(sweeper.double_spent_at.get(), Some(0));
+        node.evict_tx(conflicting_tx_id);
+        sweeper.sweep().await;
+        assert!(node.mempool().is_empty());
+
+        node.mine_block();
+        sweeper.sweep().await;
+        assert_eq!(node.mempool().len(), 1);
+        assert_eq!(sweeper.double_spent_at.get(), None);
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Once;
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::trace;
+
+use spectrum_offchain::network::ErgoNetwork;
+
+use crate::funding::FundingRepo;
+use crate::prover::SigmaProver;
+use crate::sweeper::Sweeper;
+
+/// Construct stream that periodically sweeps operator rewards.
+pub fn sweeper_stream<'a, TNetwork, TFunding, TRewards, TProver>(
+    sweeper: Sweeper<'a, TNetwork, TFunding, TRewards, TProver>,
+    sweep_interval: Duration,
+    tip_reached_signal: &'a Once,
+) -> impl Stream<Item = ()> + 'a
+where
+    TNetwork: ErgoNetwork,
+    TFunding: FundingRepo + 'a,
+    TRewards: FundingRepo + 'a,
+    TProver: SigmaProver + 'a,
+{
+    stream::unfold(sweeper, move |sweeper| async move {
+        if tip_reached_signal.is_completed() {
+            trace!(target: "sweeper", "Sweeping rewards ..");
+            sweeper.sweep().await;
+        }
+        Delay::new(sweep_interval).await;
+        Some(((), sweeper))
+    })
+}
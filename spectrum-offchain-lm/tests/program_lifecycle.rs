@@ -0,0 +1,574 @@
+//! End-to-end lifecycle of a single LM program executed against `NodeSimulator`:
+//! the pool is deployed, users deposit before the program starts, the executor compounds
+//! every epoch and finally users redeem their bundles. Program invariants are asserted after
+//! every block.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use bounded_integer::BoundedU8;
+use chrono::Utc;
+use ergo_lib::chain::transaction::{TxId, TxIoVec};
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxTokens, ErgoBox, ErgoBoxCandidate, NonMandatoryRegisters};
+use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount, TokenId};
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+use futures::channel::mpsc;
+use futures::future::ready;
+use futures::stream::{select_all, SelectAll};
+use futures::{Stream, StreamExt};
+use sigma_test_util::force_any_val;
+use tokio::sync::Mutex;
+
+use ergo_chain_sync::client::node::ErgoNetwork as ChainSyncNetwork;
+use ergo_chain_sync::model::Block;
+use ergo_chain_sync::ChainUpgrade;
+use spectrum_offchain::backlog::persistence::InMemoryBacklogStore;
+use spectrum_offchain::backlog::process::backlog_stream;
+use spectrum_offchain::backlog::{BacklogConfig, BacklogService, PrioritizationPolicy};
+use spectrum_offchain::box_resolver::in_memory::InMemoryEntityRepo;
+use spectrum_offchain::box_resolver::persistence::EntityRepo;
+use spectrum_offchain::box_resolver::process::entity_tracking_stream;
+use spectrum_offchain::data::order::OrderUpdate;
+use spectrum_offchain::data::unique_entity::{Confirmed, StateUpdate};
+use spectrum_offchain::domain::TypedAssetAmount;
+use spectrum_offchain::event_sink::handlers::entity::ConfirmedUpdateHandler;
+use spectrum_offchain::event_sink::handlers::order::OrderUpdatesHandler;
+use spectrum_offchain::event_sink::handlers::types::{IntoBoxCandidate, TryFromBox};
+use spectrum_offchain::event_sink::process_events;
+use spectrum_offchain::event_sink::types::{EventHandler, NoopDefaultHandler};
+use spectrum_offchain::event_source::data::LedgerTxEvent;
+use spectrum_offchain::event_source::event_source_ledger;
+use spectrum_offchain::executor::Executor;
+use spectrum_offchain::network::simulator::NodeSimulator;
+use spectrum_offchain::network::ErgoNetwork;
+use spectrum_offchain::transaction::{TransactionCandidate, UnsignedTransactionOps};
+use spectrum_offchain_lm::backlog_stream::convert_order_proto;
+use spectrum_offchain_lm::bundle::in_memory::InMemoryBundleRepo;
+use spectrum_offchain_lm::bundle::process::bundle_update_stream;
+use spectrum_offchain_lm::data::bundle::{IndexedStakingBundle, StakingBundle};
+use spectrum_offchain_lm::data::funding::{DistributionFundingProto, FundingUpdate};
+use spectrum_offchain_lm::data::order::{Order, OrderProto};
+use spectrum_offchain_lm::data::pool::{Pool, ProgramConfig};
+use spectrum_offchain_lm::data::{AsBox, OrderId, PoolId};
+use spectrum_offchain_lm::ergo::{NanoErg, MAX_VALUE};
+use spectrum_offchain_lm::event_sink::handlers::bundle::ConfirmedBundleUpdateHadler;
+use spectrum_offchain_lm::event_sink::handlers::funding::ConfirmedFundingHadler;
+use spectrum_offchain_lm::event_sink::handlers::program::ConfirmedProgramUpdateHandler;
+use spectrum_offchain_lm::event_sink::handlers::schedule::ConfirmedScheduleUpdateHandler;
+use spectrum_offchain_lm::executor::OrderExecutor;
+use spectrum_offchain_lm::funding::process::funding_update_stream;
+use spectrum_offchain_lm::funding::InMemoryFundingRepo;
+use spectrum_offchain_lm::program::in_memory::InMemoryProgramRepo;
+use spectrum_offchain_lm::prover::{SeedPhrase, Wallet};
+use spectrum_offchain_lm::scheduler::process::distribution_stream;
+use spectrum_offchain_lm::scheduler::InMemoryScheduleRepo;
+use spectrum_offchain_lm::validators::REDEEM_VALIDATOR;
+
+const GENESIS_HEIGHT: u32 = 1000;
+const PROGRAM_START: u32 = 1010;
+const EPOCH_LEN: u32 = 4;
+const EPOCH_NUM: u32 = 3;
+const PROGRAM_BUDGET: u64 = 3_000_000_000;
+const MAX_ROUNDING_ERROR: u64 = 1000;
+/// Deposits are mined right before the first epoch, so they are entitled to all epochs.
+const DEPOSIT_AT: u32 = 1006;
+/// Redeems are created once the last epoch is over and compounded.
+const REDEEM_AT: u32 = 1025;
+const LAST_HEIGHT: u32 = 1030;
+/// Mnemonic of the operator, whose funding boxes are spent by the executor.
+const OPERATOR_SEED: &str =
+    "gather obvious bracket ticket uphold quantum quit pistol math direct rural turn west youth acid";
+/// LQ amounts deposited by each of the users.
+const DEPOSITS: [u64; 2] = [1000, 3000];
+
+const POOL_VALUE: u64 = 1_000_000_000;
+const FUNDING_VALUE: u64 = 10_000_000_000;
+const DEPOSIT_VALUE: u64 = 3_000_000;
+const REDEEM_VALUE: u64 = 2_000_000;
+
+const DEPOSIT_SAMPLE: &str = "198c041604000e20ad62f6dd92e7dc850bc406770dfac9a943dd221a7fb440b7b2bcc7d3149c179204020e240008cd020d22b6c7e1348da3c8d371d7b656b09e379d28e1ad6410697bc5820d95e01a6c0404040008cd02217daf90deb73bdf8b6709bb42093fdfaff6573fd47b630e2d3fdd4a8193a74d040005fcffffffffffffffff0104000e20057a413d4ae7baa1f7f3b5a66bc93e7f13f94b3108765939f9edd82fad80e93d040604000408041c0402050204040e691005040004000e36100204a00b08cd0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ea02d192a39a8cc7a701730073011001020402d19683030193a38cc7b2a57300000193c2b2a57301007473027303830108cdeeac93b1a573040500050005c0cf24d808d601b2a4730000d602db63087201d6037301d604b2a5730200d6057303d606c57201d607b2a5730400d6088cb2db6308a773050002eb027306d1ededed938cb27202730700017203ed93c27204720593860272067308b2db63087204730900ededededed93cbc27207730a93d0e4c672070608720593e4c67207070e72039386028cb27202730b00017208b2db63087207730c009386028cb27202730d00019c72087e730e05b2db63087207730f0093860272067310b2db6308720773110090b0ada5d90109639593c272097312c1720973137314d90109599a8c7209018c7209027315";
+
+type Pools = InMemoryEntityRepo<AsBox<Pool>>;
+type Backlog = BacklogService<Order, InMemoryBacklogStore<Order>>;
+
+struct ProgramTokens {
+    pool_nft: TokenId,
+    budget: TokenId,
+    lq: TokenId,
+    vlq: TokenId,
+    tmp: TokenId,
+}
+
+impl ProgramTokens {
+    fn random() -> Self {
+        Self {
+            pool_nft: random_token_id(),
+            budget: random_token_id(),
+            lq: random_token_id(),
+            vlq: random_token_id(),
+            tmp: random_token_id(),
+        }
+    }
+}
+
+struct User {
+    prop: ErgoTree,
+    lq: u64,
+}
+
+/// Epochs each bundle (identified by its key) was compounded in, in order of confirmation.
+type CompoundingLog = HashMap<TokenId, Vec<u32>>;
+
+#[tokio::test]
+async fn program_lifecycle() {
+    let node = NodeSimulator::new(GENESIS_HEIGHT, Utc::now().timestamp_millis() as u64);
+    let tip_reached = Once::new();
+    tip_reached.call_once(|| {});
+
+    let backlog_conf = BacklogConfig {
+        order_lifespan: chrono::Duration::days(1),
+        order_exec_time: chrono::Duration::hours(1),
+        retry_suspended_prob: BoundedU8::new(0).unwrap(),
+        retry_backoff_base: chrono::Duration::zero(),
+        retry_backoff_cap: chrono::Duration::zero(),
+        max_attempts: 10,
+        prioritization: PrioritizationPolicy::FeeBased,
+    };
+    let backlog = Arc::new(Mutex::new(
+        Backlog::new::<Order>(InMemoryBacklogStore::new(), backlog_conf.clone()).await,
+    ));
+    let pools = Arc::new(Mutex::new(Pools::new()));
+    let bundles = Arc::new(Mutex::new(InMemoryBundleRepo::new()));
+    let funding = Arc::new(Mutex::new(InMemoryFundingRepo::new()));
+    let programs = Arc::new(Mutex::new(InMemoryProgramRepo::new()));
+    let schedules = Arc::new(Mutex::new(InMemoryScheduleRepo::new()));
+
+    let (wallet, operator) = Wallet::try_from_seed(SeedPhrase::from(OPERATOR_SEED.to_string())).unwrap();
+    let wallet_ctx = wallet.state_context();
+    wallet_ctx.refresh(&node).await.unwrap();
+    let executor_prop = Address::P2Pk(force_any_val::<ProveDlog>()).script().unwrap();
+    let mut executor = OrderExecutor::new(
+        &node,
+        Arc::clone(&backlog),
+        Arc::clone(&pools),
+        Arc::clone(&bundles),
+        Arc::clone(&funding),
+        wallet,
+        executor_prop,
+        wallet_ctx.clone(),
+        false,
+    );
+
+    let (pool_snd, pool_recv) = mpsc::unbounded::<Confirmed<StateUpdate<AsBox<Pool>>>>();
+    let pool_han = ConfirmedUpdateHandler::<_, AsBox<Pool>, _>::new(pool_snd, Arc::clone(&pools));
+    let (bundle_snd, bundle_recv) = mpsc::unbounded::<Confirmed<StateUpdate<AsBox<IndexedStakingBundle>>>>();
+    let bundle_han = ConfirmedBundleUpdateHadler {
+        topic: bundle_snd,
+        bundles: Arc::clone(&bundles),
+        programs: Arc::clone(&programs),
+    };
+    let (order_snd, order_recv) = mpsc::unbounded::<OrderUpdate<OrderProto, OrderId>>();
+    let order_han = OrderUpdatesHandler::<_, Order, OrderProto, _>::new(
+        order_snd,
+        Arc::clone(&backlog),
+        backlog_conf.order_lifespan,
+    );
+    let (funding_snd, funding_recv) = mpsc::unbounded::<Confirmed<FundingUpdate>>();
+    let funding_han = ConfirmedFundingHadler {
+        topic: funding_snd,
+        repo: Arc::clone(&funding),
+        wallets: vec![operator.clone().into()],
+    };
+    let schedule_han = ConfirmedScheduleUpdateHandler {
+        schedules: Arc::clone(&schedules),
+        pools: Arc::clone(&pools),
+    };
+    let program_han = ConfirmedProgramUpdateHandler {
+        programs: Arc::clone(&programs),
+    };
+    let handlers: Vec<Box<dyn EventHandler<LedgerTxEvent>>> = vec![
+        Box::new(pool_han),
+        Box::new(order_han),
+        Box::new(bundle_han),
+        Box::new(funding_han),
+        Box::new(schedule_han),
+        Box::new(program_han),
+    ];
+
+    let (upgrades_snd, upgrades_recv) = mpsc::unbounded::<ChainUpgrade>();
+    let mut app = select_all(vec![
+        boxed(process_events(
+            event_source_ledger(upgrades_recv),
+            handlers,
+            NoopDefaultHandler,
+        )),
+        boxed(entity_tracking_stream(pool_recv, Arc::clone(&pools))),
+        boxed(bundle_update_stream(bundle_recv, Arc::clone(&bundles))),
+        boxed(funding_update_stream(funding_recv, Arc::clone(&funding))),
+        boxed(backlog_stream(
+            Arc::clone(&backlog),
+            convert_order_proto(Arc::clone(&bundles), order_recv).filter_map(ready),
+        )),
+    ]);
+    let mut scheduler = boxed(distribution_stream(
+        Arc::clone(&backlog),
+        Arc::clone(&schedules),
+        Arc::clone(&bundles),
+        &node,
+        10,
+        Duration::from_millis(1),
+        &tip_reached,
+    ));
+
+    let tokens = ProgramTokens::random();
+    let pool_id = PoolId::from(tokens.pool_nft);
+    deploy_program(&node, &tokens, &operator).await;
+    let users = DEPOSITS
+        .iter()
+        .map(|lq| User {
+            prop: Address::P2Pk(force_any_val::<ProveDlog>()).script().unwrap(),
+            lq: *lq,
+        })
+        .collect::<Vec<_>>();
+
+    let mut compounding_log = CompoundingLog::new();
+    while node.height() < LAST_HEIGHT {
+        let utxos_before = node
+            .utxos()
+            .into_iter()
+            .map(|bx| (bx.box_id(), bx))
+            .collect::<HashMap<_, _>>();
+        node.mine_block();
+        let height = node.height();
+        let block = ChainSyncNetwork::get_block_at(&node, height).await.unwrap();
+        let transactions = block.block_transactions.transactions.clone();
+        upgrades_snd
+            .unbounded_send(ChainUpgrade::RollForward(Block::from(block)))
+            .unwrap();
+        drain(&mut app).await;
+        wallet_ctx.refresh(&node).await.unwrap();
+
+        for tx in transactions.iter() {
+            let inputs = tx
+                .inputs
+                .iter()
+                .filter_map(|i| utxos_before.get(&i.box_id).cloned())
+                .collect::<Vec<_>>();
+            record_compounding(&mut compounding_log, inputs, tx.outputs.iter().cloned().collect());
+        }
+        check_invariants(&node, pool_id, &compounding_log);
+        let AsBox(pool_box, _) = find_pool(&node, pool_id).unwrap();
+        let Confirmed(AsBox(tracked_pool_box, _)) =
+            pools.lock().await.get_last_confirmed(pool_id).await.unwrap();
+        assert_eq!(tracked_pool_box.box_id(), pool_box.box_id());
+
+        if height == DEPOSIT_AT {
+            for user in users.iter() {
+                deposit(&node, &tokens, user).await;
+            }
+        }
+        if height == REDEEM_AT {
+            for user in users.iter() {
+                redeem(&node, &tokens, user).await;
+            }
+        }
+
+        scheduler.next().await;
+        drain(&mut app).await;
+        for _ in 0..10 {
+            if executor.try_execute_next().await.is_err() {
+                break;
+            }
+        }
+    }
+
+    let AsBox(_, pool) = find_pool(&node, pool_id).unwrap();
+    assert!(find_bundles(&node, pool_id).is_empty());
+    assert_eq!(pool.reserves_lq.amount, 1);
+    assert_eq!(pool.reserves_vlq.amount, MAX_VALUE);
+    assert_eq!(pool.reserves_tmp.amount, MAX_VALUE);
+    assert!(pool.budget_rem.amount <= MAX_ROUNDING_ERROR);
+    assert_eq!(compounding_log.len(), users.len());
+    for epochs in compounding_log.values() {
+        assert_eq!(*epochs, (1..=EPOCH_NUM).collect::<Vec<_>>());
+    }
+    for user in users.iter() {
+        let owned = node
+            .utxos()
+            .into_iter()
+            .filter(|bx| bx.ergo_tree == user.prop)
+            .collect::<Vec<_>>();
+        assert_eq!(token_balance(&owned, tokens.lq), user.lq);
+        assert!(token_balance(&owned, tokens.budget) > 0);
+    }
+}
+
+fn boxed<'a, S>(s: S) -> Pin<Box<dyn Stream<Item = ()> + 'a>>
+where
+    S: Stream<Item = ()> + 'a,
+{
+    Box::pin(s)
+}
+
+/// Process events until all background streams become idle.
+async fn drain(app: &mut SelectAll<Pin<Box<dyn Stream<Item = ()> + '_>>>) {
+    while let Ok(Some(_)) = tokio::time::timeout(Duration::from_millis(20), app.next()).await {}
+}
+
+/// Every bundle spent and recreated by a transaction which outputs a pool
+/// is considered compounded in the epoch the pool output points to.
+fn record_compounding(log: &mut CompoundingLog, inputs: Vec<ErgoBox>, outputs: Vec<ErgoBox>) {
+    let Some(pool) = outputs.iter().find_map(|bx| Pool::try_from_box(bx.clone())) else {
+        return;
+    };
+    let spent_bundles = inputs
+        .into_iter()
+        .filter_map(StakingBundle::try_from_box)
+        .map(|b| b.bundle_key_id.token_id)
+        .collect::<Vec<_>>();
+    for bundle in outputs.into_iter().filter_map(StakingBundle::try_from_box) {
+        let key = bundle.bundle_key_id.token_id;
+        if spent_bundles.contains(&key) {
+            let epoch_ix = pool.epoch_ix.expect("Compounded pool must point to an epoch");
+            let epochs = log.entry(key).or_default();
+            assert!(
+                !epochs.contains(&epoch_ix),
+                "Bundle {:?} compounded twice in epoch {}",
+                key,
+                epoch_ix
+            );
+            epochs.push(epoch_ix);
+        }
+    }
+}
+
+fn check_invariants(node: &NodeSimulator, pool_id: PoolId, log: &CompoundingLog) {
+    let AsBox(pool_box, pool) = find_pool(node, pool_id).unwrap();
+    let bundles = find_bundles(node, pool_id);
+    // Budget is conserved: whatever left the pool was paid out as rewards.
+    let non_pool_boxes = node
+        .utxos()
+        .into_iter()
+        .filter(|bx| bx.box_id() != pool_box.box_id())
+        .collect::<Vec<_>>();
+    let rewards_paid = token_balance(&non_pool_boxes, pool.budget_rem.token_id);
+    assert_eq!(
+        pool.budget_rem.amount + rewards_paid,
+        pool.conf.program_budget.amount
+    );
+    // vLQ and TMP are either in the pool or in bundles.
+    let bundles_vlq = bundles.iter().map(|b| b.vlq.amount).sum::<u64>();
+    let bundles_tmp = bundles
+        .iter()
+        .map(|b| b.tmp.map(|t| t.amount).unwrap_or(0))
+        .sum::<u64>();
+    assert_eq!(pool.reserves_vlq.amount + bundles_vlq, MAX_VALUE);
+    assert_eq!(pool.reserves_lq.amount - 1, bundles_vlq);
+    assert_eq!(pool.reserves_tmp.amount + bundles_tmp, MAX_VALUE);
+    // Each bundle is compounded in every completed epoch exactly once, in order.
+    let last_compounded = pool.epoch_ix.unwrap_or(0);
+    for bundle in bundles.iter() {
+        let epochs = log
+            .get(&bundle.bundle_key_id.token_id)
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(epochs, (1..=last_compounded).collect::<Vec<_>>());
+    }
+}
+
+async fn deploy_program(node: &NodeSimulator, tokens: &ProgramTokens, operator: &Address) {
+    let height = node.height();
+    let pool = Pool {
+        pool_id: PoolId::from(tokens.pool_nft),
+        budget_rem: TypedAssetAmount::new(tokens.budget, PROGRAM_BUDGET),
+        reserves_lq: TypedAssetAmount::new(tokens.lq, 1),
+        reserves_vlq: TypedAssetAmount::new(tokens.vlq, MAX_VALUE),
+        reserves_tmp: TypedAssetAmount::new(tokens.tmp, MAX_VALUE),
+        epoch_ix: None,
+        conf: ProgramConfig {
+            epoch_len: EPOCH_LEN,
+            epoch_num: EPOCH_NUM,
+            program_start: PROGRAM_START,
+            redeem_blocks_delta: 0,
+            max_rounding_error: MAX_ROUNDING_ERROR,
+            program_budget: TypedAssetAmount::new(tokens.budget, PROGRAM_BUDGET),
+        },
+        erg_value: NanoErg::from(POOL_VALUE),
+    };
+    let funding = DistributionFundingProto {
+        prop: operator.script().unwrap(),
+        erg_value: NanoErg::from(FUNDING_VALUE),
+    };
+    let faucet = faucet_box(
+        node,
+        POOL_VALUE + FUNDING_VALUE,
+        operator.script().unwrap(),
+        vec![
+            (tokens.pool_nft, 1),
+            (tokens.budget, PROGRAM_BUDGET),
+            (tokens.lq, 1),
+            (tokens.vlq, MAX_VALUE),
+            (tokens.tmp, MAX_VALUE),
+        ],
+    );
+    submit(
+        node,
+        vec![faucet],
+        vec![pool.into_candidate(height), funding.into_candidate(height)],
+    )
+    .await;
+}
+
+async fn deposit(node: &NodeSimulator, tokens: &ProgramTokens, user: &User) {
+    let faucet = faucet_box(node, DEPOSIT_VALUE, user.prop.clone(), vec![(tokens.lq, user.lq)]);
+    let deposit_tree = ErgoTree::sigma_parse_bytes(&base16::decode(DEPOSIT_SAMPLE).unwrap())
+        .unwrap()
+        .with_constant(1, Constant::from(<Vec<u8>>::from(tokens.pool_nft)))
+        .unwrap()
+        .with_constant(3, Constant::from(user.prop.sigma_serialize_bytes().unwrap()))
+        .unwrap()
+        .with_constant(14, Constant::from(EPOCH_NUM as i32))
+        .unwrap();
+    let deposit = candidate(
+        node.height(),
+        DEPOSIT_VALUE,
+        deposit_tree,
+        vec![(tokens.lq, user.lq)],
+    );
+    submit(node, vec![faucet], vec![deposit]).await;
+}
+
+async fn redeem(node: &NodeSimulator, tokens: &ProgramTokens, user: &User) {
+    let bundle_keys = find_bundles(node, PoolId::from(tokens.pool_nft))
+        .into_iter()
+        .map(|bundle| bundle.bundle_key_id.token_id)
+        .collect::<Vec<_>>();
+    let (key_box, key) = node
+        .utxos()
+        .into_iter()
+        .filter(|bx| bx.ergo_tree == user.prop)
+        .find_map(|bx| {
+            let key = bx
+                .tokens
+                .as_ref()?
+                .iter()
+                .find(|t| bundle_keys.contains(&t.token_id))?
+                .clone();
+            Some((bx, key))
+        })
+        .expect("User must own a bundle key");
+    let top_up = faucet_box(
+        node,
+        REDEEM_VALUE - *key_box.value.as_u64(),
+        user.prop.clone(),
+        Vec::new(),
+    );
+    let redeem_tree = REDEEM_VALIDATOR
+        .clone()
+        .with_constant(2, Constant::from(user.prop.sigma_serialize_bytes().unwrap()))
+        .unwrap()
+        .with_constant(3, Constant::from(<Vec<u8>>::from(tokens.lq)))
+        .unwrap()
+        .with_constant(4, Constant::from(user.lq as i64))
+        .unwrap();
+    let redeem = candidate(
+        node.height(),
+        REDEEM_VALUE,
+        redeem_tree,
+        vec![(key.token_id, *key.amount.as_u64())],
+    );
+    submit(node, vec![key_box, top_up], vec![redeem]).await;
+}
+
+async fn submit(node: &NodeSimulator, inputs: Vec<ErgoBox>, outputs: Vec<ErgoBoxCandidate>) {
+    let tx = TransactionCandidate::new(
+        TxIoVec::from_vec(
+            inputs
+                .into_iter()
+                .map(|bx| (bx, ContextExtension::empty()))
+                .collect(),
+        )
+        .unwrap(),
+        None,
+        TxIoVec::from_vec(outputs).unwrap(),
+    )
+    .into_tx_without_proofs();
+    node.submit_tx(tx).await.unwrap();
+}
+
+/// Put a box out of thin air into the UTXO set of the simulated chain.
+fn faucet_box(node: &NodeSimulator, value: u64, prop: ErgoTree, tokens: Vec<(TokenId, u64)>) -> ErgoBox {
+    let bx = ErgoBox::new(
+        BoxValue::try_from(value).unwrap(),
+        prop,
+        box_tokens(tokens),
+        NonMandatoryRegisters::empty(),
+        node.height(),
+        TxId(force_any_val::<Digest32>()),
+        0,
+    )
+    .unwrap();
+    node.add_utxos(vec![bx.clone()]);
+    bx
+}
+
+fn candidate(height: u32, value: u64, prop: ErgoTree, tokens: Vec<(TokenId, u64)>) -> ErgoBoxCandidate {
+    ErgoBoxCandidate {
+        value: BoxValue::try_from(value).unwrap(),
+        ergo_tree: prop,
+        tokens: box_tokens(tokens),
+        additional_registers: NonMandatoryRegisters::empty(),
+        creation_height: height,
+    }
+}
+
+fn box_tokens(tokens: Vec<(TokenId, u64)>) -> Option<BoxTokens> {
+    BoxTokens::opt_empty_vec(
+        tokens
+            .into_iter()
+            .map(|(token_id, amount)| Token {
+                token_id,
+                amount: TokenAmount::try_from(amount).unwrap(),
+            })
+            .collect(),
+    )
+    .unwrap()
+}
+
+fn find_pool(node: &NodeSimulator, pool_id: PoolId) -> Option<AsBox<Pool>> {
+    node.utxos().into_iter().find_map(|bx| {
+        Pool::try_from_box(bx.clone())
+            .filter(|pool| pool.pool_id == pool_id)
+            .map(|pool| AsBox(bx, pool))
+    })
+}
+
+fn find_bundles(node: &NodeSimulator, pool_id: PoolId) -> Vec<StakingBundle> {
+    node.utxos()
+        .into_iter()
+        .filter_map(StakingBundle::try_from_box)
+        .filter(|bundle| bundle.pool_id == pool_id)
+        .collect()
+}
+
+fn token_balance(boxes: &[ErgoBox], token_id: TokenId) -> u64 {
+    boxes
+        .iter()
+        .flat_map(|bx| bx.tokens.iter().flat_map(|ts| ts.iter()))
+        .filter(|t| t.token_id == token_id)
+        .map(|t| *t.amount.as_u64())
+        .sum()
+}
+
+fn random_token_id() -> TokenId {
+    TokenId::from(force_any_val::<Digest32>())
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::hash::Hash;
 
@@ -7,15 +7,17 @@ use bounded_integer::BoundedU8;
 use chrono::{Duration, Utc};
 use log::trace;
 use priority_queue::PriorityQueue;
+use prometheus::{IntCounterVec, IntGauge, Opts, Registry};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use type_equalities::IsEqual;
 
-use crate::backlog::data::{BacklogOrder, OrderWeight, Weighted};
+use crate::backlog::data::{BacklogEntry, BacklogOrder, OrderStage, OrderWeight, Suspension, Weighted};
 use crate::backlog::persistence::BacklogStore;
 use crate::data::order::{PendingOrder, ProgressingOrder, SuspendedOrder};
-use crate::data::OnChainOrder;
+use crate::data::{Has, OnChainOrder};
+use crate::executor::OrderType;
 
 pub mod data;
 pub mod persistence;
@@ -32,9 +34,10 @@ where
     async fn put<'a>(&mut self, ord: PendingOrder<TOrd>)
     where
         TOrd: 'a;
-    /// Suspend order that temporarily failed.
-    /// Potentially retry later.
-    async fn suspend<'a>(&mut self, ord: TOrd) -> bool
+    /// Suspend order that temporarily failed due to the given `err`.
+    /// Retry later with exponential backoff, unless the order failed too many times already.
+    /// Returns `false` if the order is unknown or dropped.
+    async fn suspend<'a>(&mut self, ord: TOrd, err: String) -> bool
     where
         TOrd: 'a;
     /// Register successfull order to check if it settled later.
@@ -59,6 +62,28 @@ where
     async fn find_orders<F: Fn(&TOrd) -> bool + Send + 'static>(&self, f: F) -> Vec<TOrd>
     where
         F: Fn(&TOrd) -> bool + Send + 'static;
+    /// Return all suspended orders along with their failure history.
+    async fn find_suspended(&self) -> Vec<SuspendedOrder<TOrd>>;
+    /// Return all orders along with their stage and effective weight.
+    async fn find_entries(&self) -> Vec<BacklogEntry<TOrd>>;
+    /// Move suspended order back to pending.
+    async fn unsuspend<'a>(&mut self, ord_id: TOrd::TOrderId) -> bool
+    where
+        TOrd::TOrderId: 'a;
+    /// Override weight of a queued order.
+    /// The override holds until the order leaves the queue.
+    async fn set_weight<'a>(&mut self, ord_id: TOrd::TOrderId, weight: OrderWeight) -> bool
+    where
+        TOrd::TOrderId: 'a;
+    /// Number of orders on each stage.
+    fn stats(&self) -> BacklogStats;
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct BacklogStats {
+    pub pending: usize,
+    pub suspended: usize,
+    pub revisit: usize,
 }
 
 pub struct BacklogTracing<B> {
@@ -87,13 +112,13 @@ where
         trace!(target: "backlog", "put({:?}) -> ()", ord);
     }
 
-    async fn suspend<'a>(&mut self, ord: TOrd) -> bool
+    async fn suspend<'a>(&mut self, ord: TOrd, err: String) -> bool
     where
         TOrd: 'a,
     {
-        trace!(target: "backlog", "suspend({:?})", ord);
-        let res = self.inner.suspend(ord.clone()).await;
-        trace!(target: "backlog", "suspend({:?}) -> {:?}", ord, res);
+        trace!(target: "backlog", "suspend({:?}, {})", ord, err);
+        let res = self.inner.suspend(ord.clone(), err.clone()).await;
+        trace!(target: "backlog", "suspend({:?}, {}) -> {:?}", ord, err, res);
         res
     }
 
@@ -148,6 +173,204 @@ where
         trace!(target: "backlog", "find_order() -> {:?}", res);
         res
     }
+
+    async fn find_suspended(&self) -> Vec<SuspendedOrder<TOrd>> {
+        self.inner.find_suspended().await
+    }
+
+    async fn find_entries(&self) -> Vec<BacklogEntry<TOrd>> {
+        self.inner.find_entries().await
+    }
+
+    async fn unsuspend<'a>(&mut self, ord_id: TOrd::TOrderId) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        trace!(target: "backlog", "unsuspend({:?})", ord_id);
+        let res = self.inner.unsuspend(ord_id.clone()).await;
+        trace!(target: "backlog", "unsuspend({:?}) -> {:?}", ord_id, res);
+        res
+    }
+
+    async fn set_weight<'a>(&mut self, ord_id: TOrd::TOrderId, weight: OrderWeight) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        trace!(target: "backlog", "set_weight({:?}, {:?})", ord_id, weight);
+        let res = self.inner.set_weight(ord_id.clone(), weight).await;
+        trace!(target: "backlog", "set_weight({:?}, {:?}) -> {:?}", ord_id, weight, res);
+        res
+    }
+
+    fn stats(&self) -> BacklogStats {
+        self.inner.stats()
+    }
+}
+
+/// Exposes backlog sizes and outcomes of order execution as Prometheus metrics.
+/// Outcome of an order is attributed when the executor returns an order it has popped
+/// back to the backlog: `check_later` means it was executed, `suspend` means it was suspended
+/// and `remove` means it was dropped.
+pub struct BacklogMetrics<B, TOrderId> {
+    inner: B,
+    in_flight: HashMap<TOrderId, OrderType>,
+    pending: IntGauge,
+    suspended: IntGauge,
+    revisit: IntGauge,
+    orders: IntCounterVec,
+}
+
+impl<B, TOrderId> BacklogMetrics<B, TOrderId> {
+    pub fn wrap(backlog: B, registry: &Registry) -> Self {
+        let pending = IntGauge::new("backlog_pending_orders", "Orders waiting for execution").unwrap();
+        let suspended = IntGauge::new("backlog_suspended_orders", "Orders waiting for retry").unwrap();
+        let revisit =
+            IntGauge::new("backlog_revisit_orders", "Executed orders waiting for settlement").unwrap();
+        let orders = IntCounterVec::new(
+            Opts::new("orders_total", "Orders processed by the executor"),
+            &["order_type", "outcome"],
+        )
+        .unwrap();
+        registry.register(Box::new(pending.clone())).unwrap();
+        registry.register(Box::new(suspended.clone())).unwrap();
+        registry.register(Box::new(revisit.clone())).unwrap();
+        registry.register(Box::new(orders.clone())).unwrap();
+        Self {
+            inner: backlog,
+            in_flight: HashMap::new(),
+            pending,
+            suspended,
+            revisit,
+            orders,
+        }
+    }
+}
+
+impl<B, TOrderId> BacklogMetrics<B, TOrderId>
+where
+    TOrderId: Eq + Hash,
+{
+    fn observe_stats(&self, stats: BacklogStats) {
+        self.pending.set(stats.pending as i64);
+        self.suspended.set(stats.suspended as i64);
+        self.revisit.set(stats.revisit as i64);
+    }
+
+    fn observe_outcome(&mut self, ord_id: &TOrderId, outcome: &str) {
+        if let Some(order_type) = self.in_flight.remove(ord_id) {
+            let order_type = match order_type {
+                OrderType::Deposit => "deposit",
+                OrderType::Compound => "compound",
+                OrderType::Redeem => "redeem",
+            };
+            self.orders.with_label_values(&[order_type, outcome]).inc();
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<TOrd, B> Backlog<TOrd> for BacklogMetrics<B, TOrd::TOrderId>
+where
+    TOrd: OnChainOrder + Has<OrderType>,
+    B: Backlog<TOrd>,
+{
+    async fn put<'a>(&mut self, ord: PendingOrder<TOrd>)
+    where
+        TOrd: 'a,
+    {
+        self.inner.put(ord).await;
+        self.observe_stats(self.inner.stats());
+    }
+
+    async fn suspend<'a>(&mut self, ord: TOrd, err: String) -> bool
+    where
+        TOrd: 'a,
+    {
+        let ord_id = ord.get_self_ref();
+        let res = self.inner.suspend(ord, err).await;
+        self.observe_outcome(&ord_id, if res { "suspended" } else { "dropped" });
+        self.observe_stats(self.inner.stats());
+        res
+    }
+
+    async fn check_later<'a>(&mut self, ord: ProgressingOrder<TOrd>) -> bool
+    where
+        TOrd: 'a,
+    {
+        self.observe_outcome(&ord.order.get_self_ref(), "executed");
+        let res = self.inner.check_later(ord).await;
+        self.observe_stats(self.inner.stats());
+        res
+    }
+
+    async fn try_pop(&mut self) -> Option<TOrd> {
+        let res = self.inner.try_pop().await;
+        if let Some(ord) = &res {
+            self.in_flight.insert(ord.get_self_ref(), ord.get::<OrderType>());
+        }
+        self.observe_stats(self.inner.stats());
+        res
+    }
+
+    async fn exists<'a>(&self, ord_id: TOrd::TOrderId) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        self.inner.exists(ord_id).await
+    }
+
+    async fn remove<'a>(&mut self, ord_id: TOrd::TOrderId)
+    where
+        TOrd::TOrderId: 'a + Clone,
+    {
+        self.observe_outcome(&ord_id, "dropped");
+        self.inner.remove(ord_id).await;
+        self.observe_stats(self.inner.stats());
+    }
+
+    async fn recharge<'a>(&mut self, ord: TOrd)
+    where
+        TOrd: 'a,
+    {
+        self.in_flight.remove(&ord.get_self_ref());
+        self.inner.recharge(ord).await;
+        self.observe_stats(self.inner.stats());
+    }
+
+    async fn find_orders<F>(&self, f: F) -> Vec<TOrd>
+    where
+        F: Fn(&TOrd) -> bool + Send + 'static,
+    {
+        self.inner.find_orders(f).await
+    }
+
+    async fn find_suspended(&self) -> Vec<SuspendedOrder<TOrd>> {
+        self.inner.find_suspended().await
+    }
+
+    async fn find_entries(&self) -> Vec<BacklogEntry<TOrd>> {
+        self.inner.find_entries().await
+    }
+
+    async fn unsuspend<'a>(&mut self, ord_id: TOrd::TOrderId) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        let res = self.inner.unsuspend(ord_id).await;
+        self.observe_stats(self.inner.stats());
+        res
+    }
+
+    async fn set_weight<'a>(&mut self, ord_id: TOrd::TOrderId, weight: OrderWeight) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        self.inner.set_weight(ord_id, weight).await
+    }
+
+    fn stats(&self) -> BacklogStats {
+        self.inner.stats()
+    }
 }
 
 #[serde_with::serde_as]
@@ -158,6 +381,126 @@ pub struct BacklogConfig {
     #[serde_as(as = "serde_with::DurationSeconds<i64>")]
     pub order_exec_time: Duration,
     pub retry_suspended_prob: BoundedU8<0, 100>,
+    /// Delay before the first retry of a suspended order. Doubles with every failed attempt.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub retry_backoff_base: Duration,
+    /// Upper bound of the retry delay.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub retry_backoff_cap: Duration,
+    /// Orders are dropped after this many failed attempts.
+    pub max_attempts: u32,
+    /// Order in which queued orders are popped.
+    #[serde(default)]
+    pub prioritization: PrioritizationPolicy,
+}
+
+impl BacklogConfig {
+    /// Delay before the next retry of an order that failed `attempts` times.
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        let cap_secs = self.retry_backoff_cap.num_seconds();
+        let delay_secs = 2i64
+            .checked_pow(attempts.saturating_sub(1))
+            .and_then(|factor| self.retry_backoff_base.num_seconds().checked_mul(factor))
+            .map_or(cap_secs, |secs| secs.min(cap_secs));
+        Duration::seconds(delay_secs)
+    }
+}
+
+/// Defines which of the queued orders is executed first.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PrioritizationPolicy {
+    /// Orders with greater weight (e.g. fee) go first.
+    #[default]
+    FeeBased,
+    /// Orders are executed in the order they arrived.
+    Fifo,
+    /// Like `FeeBased`, but every second spent in the backlog adds `boost_per_sec` to the weight,
+    /// so that light orders are not starved by a stream of heavier ones.
+    AgeBoosted { boost_per_sec: u64 },
+    /// Entities (e.g. pools) take turns, so that a single busy entity can't delay all others.
+    /// Orders of the same entity are executed in the order they arrived.
+    RoundRobin,
+}
+
+/// Position of an order in a queue. Orders with higher priority are popped first.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+struct Priority {
+    rank: i128,
+    tie_break: i128,
+}
+
+/// Assigns priorities to orders according to the configured [PrioritizationPolicy].
+struct Prioritizer<TEntityId> {
+    policy: PrioritizationPolicy,
+    /// Round of the last popped order (`RoundRobin` only).
+    current_round: u64,
+    /// Last round assigned to each entity (`RoundRobin` only).
+    last_rounds: HashMap<TEntityId, u64>,
+}
+
+impl<TEntityId> Prioritizer<TEntityId>
+where
+    TEntityId: Eq + Hash,
+{
+    fn new(policy: PrioritizationPolicy) -> Self {
+        Self {
+            policy,
+            current_round: 0,
+            last_rounds: HashMap::new(),
+        }
+    }
+
+    fn priority(&mut self, entity_id: TEntityId, weight: OrderWeight, timestamp: i64) -> Priority {
+        if let PrioritizationPolicy::RoundRobin = self.policy {
+            // Each next order of an entity waits for one more round,
+            // entities which were idle for a while join the current round.
+            let round = self
+                .last_rounds
+                .get(&entity_id)
+                .map_or(self.current_round, |last| (*last).max(self.current_round))
+                + 1;
+            self.last_rounds.insert(entity_id, round);
+            Priority {
+                rank: -(round as i128),
+                tie_break: u64::from(weight) as i128,
+            }
+        } else {
+            self.reweight(None, weight, timestamp)
+        }
+    }
+
+    /// Priority of an already queued order given its new weight.
+    fn reweight(&self, prev: Option<Priority>, weight: OrderWeight, timestamp: i64) -> Priority {
+        let weight = u64::from(weight) as i128;
+        let timestamp = timestamp as i128;
+        match self.policy {
+            PrioritizationPolicy::FeeBased => Priority {
+                rank: weight,
+                tie_break: -timestamp,
+            },
+            PrioritizationPolicy::Fifo => Priority {
+                rank: -timestamp,
+                tie_break: weight,
+            },
+            // Boost is relative to the arrival time, which is the same
+            // as boosting by age, as all queued orders age equally.
+            PrioritizationPolicy::AgeBoosted { boost_per_sec } => Priority {
+                rank: weight - boost_per_sec as i128 * timestamp,
+                tie_break: -timestamp,
+            },
+            PrioritizationPolicy::RoundRobin => Priority {
+                rank: prev.map_or(0, |p| p.rank),
+                tie_break: weight,
+            },
+        }
+    }
+
+    fn on_pop(&mut self, prio: Priority) {
+        if let PrioritizationPolicy::RoundRobin = self.policy {
+            self.current_round = self.current_round.max((-prio.rank) as u64);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
@@ -220,14 +563,17 @@ where
 {
     store: TStore,
     conf: BacklogConfig,
-    /// Pending orders ordered by weight.
-    pending_pq: PriorityQueue<WeightedOrder<TOrd::TOrderId>, OrderWeight>,
-    /// Failed orders waiting for retry (retries are performed with some constant probability, e.g. 5%).
-    /// Again, ordered by weight.
-    suspended_pq: PriorityQueue<WeightedOrder<TOrd::TOrderId>, OrderWeight>,
+    /// Pending orders ordered by priority.
+    pending_pq: PriorityQueue<WeightedOrder<TOrd::TOrderId>, Priority>,
+    /// Failed orders waiting for retry (retries are performed with some constant probability, e.g. 5%,
+    /// once the backoff of the order has elapsed). Again, ordered by priority.
+    suspended_pq: PriorityQueue<WeightedOrder<TOrd::TOrderId>, Priority>,
     /// Successully submitted orders. Left orders should be re-executed in some time.
     /// Normally successfull orders are eliminated from this queue before new execution attempt.
     revisit_queue: VecDeque<WeightedOrder<TOrd::TOrderId>>,
+    /// Weights set by `set_weight`, held while the order is queued.
+    weight_overrides: HashMap<TOrd::TOrderId, OrderWeight>,
+    prioritizer: Prioritizer<TOrd::TEntityId>,
 }
 
 impl<TOrd, TStore> BacklogService<TOrd, TStore>
@@ -238,18 +584,94 @@ where
 {
     pub async fn new<TOrd0: IsEqual<TOrd>>(store: TStore, conf: BacklogConfig) -> Self {
         let mut pending_pq = PriorityQueue::new();
+        let mut suspended_pq = PriorityQueue::new();
+        let mut progressing = Vec::new();
+        let mut prioritizer = Prioritizer::new(conf.prioritization);
         for ord in store.find_orders(|_| true).await {
-            let wt = ord.order.weight();
-            trace!(target: "backlog", "Restored order: {:?}", ord.order.get_self_ref());
-            pending_pq.push(ord.into(), wt);
+            trace!(
+                target: "backlog",
+                "Restored order: {:?} ({:?})",
+                ord.order.get_self_ref(),
+                ord.stage
+            );
+            match ord.stage {
+                OrderStage::Pending => {
+                    let prio =
+                        prioritizer.priority(ord.order.get_entity_ref(), ord.order.weight(), ord.timestamp);
+                    pending_pq.push(ord.into(), prio);
+                }
+                OrderStage::Suspended(_) => {
+                    let prio =
+                        prioritizer.priority(ord.order.get_entity_ref(), ord.order.weight(), ord.timestamp);
+                    suspended_pq.push(ord.into(), prio);
+                }
+                OrderStage::Progressing(ts) => progressing.push(WeightedOrder {
+                    order_id: ord.order.get_self_ref(),
+                    timestamp: ts,
+                }),
+            }
         }
+        // Revisit queue is expected to be ordered by the time of execution.
+        progressing.sort_by_key(|ord| ord.timestamp);
         Self {
             store,
             conf,
             pending_pq,
-            suspended_pq: PriorityQueue::new(),
-            revisit_queue: VecDeque::new(),
+            suspended_pq,
+            revisit_queue: progressing.into(),
+            weight_overrides: HashMap::new(),
+            prioritizer,
+        }
+    }
+
+    async fn set_stage(&mut self, ord_id: TOrd::TOrderId, stage: OrderStage) {
+        if let Some(ord) = self.store.get(ord_id).await {
+            self.store.put(BacklogOrder { stage, ..ord }).await;
+        }
+    }
+
+    /// Forget overrides of orders which are no longer queued.
+    fn prune_weight_overrides(&mut self) {
+        if !self.weight_overrides.is_empty() {
+            let (pending_pq, suspended_pq) = (&self.pending_pq, &self.suspended_pq);
+            self.weight_overrides.retain(|ord_id, _| {
+                pending_pq
+                    .iter()
+                    .chain(suspended_pq.iter())
+                    .any(|(ord, _)| ord.order_id == *ord_id)
+            });
+        }
+    }
+
+    /// Pop best suspended order which is eligible for retry already.
+    async fn try_pop_suspended(&mut self) -> Option<TOrd> {
+        let ts_now = Utc::now().timestamp();
+        let mut not_yet_eligible = Vec::new();
+        let mut res = None;
+        while let Some((WeightedOrder { order_id, timestamp }, prio)) = self.suspended_pq.pop() {
+            if ts_now - timestamp > self.conf.order_lifespan.num_seconds() {
+                self.store.remove(order_id).await;
+            } else if let Some(backlog_ord) = self.store.get(order_id).await {
+                match backlog_ord.stage {
+                    OrderStage::Suspended(Suspension { retry_at, .. }) if retry_at > ts_now => {
+                        let ord = WeightedOrder {
+                            order_id: backlog_ord.order.get_self_ref(),
+                            timestamp,
+                        };
+                        not_yet_eligible.push((ord, prio));
+                    }
+                    _ => {
+                        self.prioritizer.on_pop(prio);
+                        res = Some(backlog_ord.order);
+                        break;
+                    }
+                }
+            }
+        }
+        for (ord, prio) in not_yet_eligible {
+            self.suspended_pq.push(ord, prio);
         }
+        res
     }
 
     async fn revisit_progressing_orders(&mut self) {
@@ -259,8 +681,24 @@ where
             if elapsed_secs > self.conf.order_exec_time.num_seconds() {
                 if elapsed_secs <= self.conf.order_lifespan.num_seconds() {
                     if let Some(ord) = self.store.get(ord.order_id).await {
-                        let wt = ord.order.weight();
-                        self.pending_pq.push(ord.into(), wt);
+                        let prio = self.prioritizer.priority(
+                            ord.order.get_entity_ref(),
+                            ord.order.weight(),
+                            ord.timestamp,
+                        );
+                        self.pending_pq.push(
+                            WeightedOrder {
+                                order_id: ord.order.get_self_ref(),
+                                timestamp: ord.timestamp,
+                            },
+                            prio,
+                        );
+                        self.store
+                            .put(BacklogOrder {
+                                stage: OrderStage::Pending,
+                                ..ord
+                            })
+                            .await;
                     }
                 } else {
                     self.store.remove(ord.order_id).await;
@@ -275,13 +713,14 @@ where
 async fn try_pop_max_order<TOrd, TStore>(
     conf: &BacklogConfig,
     store: &mut TStore,
-    pq: &mut PriorityQueue<WeightedOrder<TOrd::TOrderId>, OrderWeight>,
+    pq: &mut PriorityQueue<WeightedOrder<TOrd::TOrderId>, Priority>,
+    prioritizer: &mut Prioritizer<TOrd::TEntityId>,
 ) -> Option<TOrd>
 where
     TOrd: OnChainOrder + Weighted + Hash + Eq,
     TStore: BacklogStore<TOrd>,
 {
-    while let Some((ord, _)) = pq.pop() {
+    while let Some((ord, prio)) = pq.pop() {
         let ts_now = Utc::now().timestamp();
         let elapsed_secs = ts_now - ord.timestamp;
         if elapsed_secs > conf.order_lifespan.num_seconds() {
@@ -289,6 +728,7 @@ where
         } else {
             let res = store.get(ord.order_id).await.map(|bo| bo.order);
             if res.is_some() {
+                prioritizer.on_pop(prio);
                 return res;
             }
         }
@@ -300,7 +740,7 @@ where
 impl<TOrd, TStore> Backlog<TOrd> for BacklogService<TOrd, TStore>
 where
     TStore: BacklogStore<TOrd>,
-    TOrd::TOrderId: Debug,
+    TOrd::TOrderId: Debug + Clone,
     TOrd: OnChainOrder + Weighted + Hash + Eq + Clone,
 {
     async fn put<'a>(&mut self, ord: PendingOrder<TOrd>)
@@ -311,28 +751,57 @@ where
             .put(BacklogOrder {
                 order: ord.order.clone(),
                 timestamp: ord.timestamp,
+                stage: OrderStage::Pending,
             })
             .await;
-        let wt = ord.order.weight();
-        self.pending_pq.push(ord.into(), wt);
+        let prio = self
+            .prioritizer
+            .priority(ord.order.get_entity_ref(), ord.order.weight(), ord.timestamp);
+        self.pending_pq.push(ord.into(), prio);
     }
 
-    async fn suspend<'a>(&mut self, ord: TOrd) -> bool
+    async fn suspend<'a>(&mut self, ord: TOrd, err: String) -> bool
     where
         TOrd: 'a,
     {
-        if self.store.exists(ord.get_self_ref()).await {
-            let wt = ord.weight();
-            if let Some(backlog_ord) = self.store.get(ord.get_self_ref()).await {
-                self.suspended_pq.push(
-                    WeightedOrder {
-                        order_id: ord.get_self_ref(),
-                        timestamp: backlog_ord.timestamp,
-                    },
-                    wt,
+        if let Some(backlog_ord) = self.store.get(ord.get_self_ref()).await {
+            let attempts = match &backlog_ord.stage {
+                OrderStage::Suspended(suspension) => suspension.attempts + 1,
+                _ => 1,
+            };
+            if attempts >= self.conf.max_attempts {
+                trace!(
+                    target: "backlog",
+                    "Dropping order {:?} after {} failed attempts, last error: {}",
+                    ord.get_self_ref(),
+                    attempts,
+                    err
                 );
-                return true;
+                self.store.remove(ord.get_self_ref()).await;
+                return false;
             }
+            let retry_at = Utc::now().timestamp() + self.conf.retry_delay(attempts).num_seconds();
+            let prio = self
+                .prioritizer
+                .priority(ord.get_entity_ref(), ord.weight(), backlog_ord.timestamp);
+            self.suspended_pq.push(
+                WeightedOrder {
+                    order_id: ord.get_self_ref(),
+                    timestamp: backlog_ord.timestamp,
+                },
+                prio,
+            );
+            self.store
+                .put(BacklogOrder {
+                    stage: OrderStage::Suspended(Suspension {
+                        attempts,
+                        last_error: err,
+                        retry_at,
+                    }),
+                    ..backlog_ord
+                })
+                .await;
+            return true;
         }
         false
     }
@@ -342,6 +811,8 @@ where
         TOrd: 'a,
     {
         if self.store.exists(ord.order.get_self_ref()).await {
+            self.set_stage(ord.order.get_self_ref(), OrderStage::Progressing(ord.timestamp))
+                .await;
             self.revisit_queue.push_back(ord.into());
             return true;
         }
@@ -351,11 +822,34 @@ where
     async fn try_pop(&mut self) -> Option<TOrd> {
         self.revisit_progressing_orders().await;
         let rng = rand::thread_rng().gen_range(0..=99);
-        if rng >= self.conf.retry_suspended_prob.get() {
-            try_pop_max_order(&self.conf, &mut self.store, &mut self.pending_pq).await
+        let res = if rng >= self.conf.retry_suspended_prob.get() {
+            match try_pop_max_order(
+                &self.conf,
+                &mut self.store,
+                &mut self.pending_pq,
+                &mut self.prioritizer,
+            )
+            .await
+            {
+                None => self.try_pop_suspended().await,
+                res => res,
+            }
         } else {
-            try_pop_max_order(&self.conf, &mut self.store, &mut self.suspended_pq).await
-        }
+            match self.try_pop_suspended().await {
+                None => {
+                    try_pop_max_order(
+                        &self.conf,
+                        &mut self.store,
+                        &mut self.pending_pq,
+                        &mut self.prioritizer,
+                    )
+                    .await
+                }
+                res => res,
+            }
+        };
+        self.prune_weight_overrides();
+        res
     }
 
     async fn exists<'a>(&self, ord_id: TOrd::TOrderId) -> bool
@@ -369,6 +863,7 @@ where
     where
         TOrd::TOrderId: Clone + 'a,
     {
+        self.weight_overrides.remove(&ord_id);
         self.store.remove(ord_id).await;
     }
 
@@ -376,15 +871,23 @@ where
     where
         TOrd: 'a,
     {
-        let wt = ord.weight();
         if let Some(backlog_ord) = self.store.get(ord.get_self_ref()).await {
+            let prio = self
+                .prioritizer
+                .priority(ord.get_entity_ref(), ord.weight(), backlog_ord.timestamp);
             self.pending_pq.push(
                 WeightedOrder {
                     order_id: ord.get_self_ref(),
                     timestamp: backlog_ord.timestamp,
                 },
-                wt,
+                prio,
             );
+            self.store
+                .put(BacklogOrder {
+                    stage: OrderStage::Pending,
+                    ..backlog_ord
+                })
+                .await;
         }
     }
 
@@ -399,22 +902,108 @@ where
             .map(|b| b.order)
             .collect()
     }
+
+    async fn find_suspended(&self) -> Vec<SuspendedOrder<TOrd>> {
+        self.store
+            .find_orders(|_| true)
+            .await
+            .into_iter()
+            .filter_map(|ord| match ord.stage {
+                OrderStage::Suspended(Suspension {
+                    attempts,
+                    last_error,
+                    retry_at,
+                }) => Some(SuspendedOrder {
+                    order: ord.order,
+                    timestamp: ord.timestamp,
+                    attempts,
+                    last_error,
+                    retry_at,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn find_entries(&self) -> Vec<BacklogEntry<TOrd>> {
+        self.store
+            .find_orders(|_| true)
+            .await
+            .into_iter()
+            .map(|ord| BacklogEntry {
+                weight: self
+                    .weight_overrides
+                    .get(&ord.order.get_self_ref())
+                    .copied()
+                    .unwrap_or_else(|| ord.order.weight()),
+                order: ord.order,
+                timestamp: ord.timestamp,
+                stage: ord.stage,
+            })
+            .collect()
+    }
+
+    async fn unsuspend<'a>(&mut self, ord_id: TOrd::TOrderId) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        let suspended = self
+            .suspended_pq
+            .iter()
+            .find(|(ord, _)| ord.order_id == ord_id)
+            .map(|(ord, _)| ord.clone());
+        if let Some((ord, prio)) = suspended.and_then(|ord| self.suspended_pq.remove(&ord)) {
+            self.set_stage(ord_id, OrderStage::Pending).await;
+            self.pending_pq.push(ord, prio);
+            return true;
+        }
+        false
+    }
+
+    async fn set_weight<'a>(&mut self, ord_id: TOrd::TOrderId, weight: OrderWeight) -> bool
+    where
+        TOrd::TOrderId: 'a,
+    {
+        let mut updated = false;
+        for pq in [&mut self.pending_pq, &mut self.suspended_pq] {
+            let queued = pq
+                .iter()
+                .find(|(ord, _)| ord.order_id == ord_id)
+                .map(|(ord, prio)| (ord.clone(), *prio));
+            if let Some((ord, prio)) = queued {
+                let new_prio = self.prioritizer.reweight(Some(prio), weight, ord.timestamp);
+                updated |= pq.change_priority(&ord, new_prio).is_some();
+            }
+        }
+        if updated {
+            self.weight_overrides.insert(ord_id, weight);
+        }
+        updated
+    }
+
+    fn stats(&self) -> BacklogStats {
+        BacklogStats {
+            pending: self.pending_pq.len(),
+            suspended: self.suspended_pq.len(),
+            revisit: self.revisit_queue.len(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
     use std::sync::Arc;
 
-    use async_trait::async_trait;
     use bounded_integer::BoundedU8;
     use chrono::{Duration, Utc};
     use rand::RngCore;
     use serde::{Deserialize, Serialize};
 
-    use crate::backlog::data::{BacklogOrder, OrderWeight, Weighted};
-    use crate::backlog::persistence::{BacklogStore, BacklogStoreRocksDB};
-    use crate::backlog::{Backlog, BacklogConfig, BacklogService};
+    use crate::backlog::data::{BacklogOrder, OrderStage, OrderWeight, Suspension, Weighted};
+    use crate::backlog::persistence::{BacklogStore, BacklogStoreRocksDB, InMemoryBacklogStore};
+    use crate::backlog::{
+        Backlog, BacklogConfig, BacklogService, BacklogStats, PrioritizationPolicy, Prioritizer,
+    };
     use crate::data::order::{PendingOrder, ProgressingOrder, SuspendedOrder};
     use crate::data::OnChainOrder;
 
@@ -450,6 +1039,9 @@ mod tests {
             Self {
                 order: x.order,
                 timestamp: x.timestamp,
+                attempts: 0,
+                last_error: String::new(),
+                retry_at: x.timestamp,
             }
         }
     }
@@ -460,18 +1052,6 @@ mod tests {
         }
     }
 
-    struct MockBacklogStore {
-        inner: HashMap<MockOrderId, BacklogOrder<MockOrder>>,
-    }
-
-    impl MockBacklogStore {
-        fn new() -> Self {
-            Self {
-                inner: HashMap::new(),
-            }
-        }
-    }
-
     impl OnChainOrder for MockOrder {
         type TOrderId = MockOrderId;
 
@@ -484,46 +1064,39 @@ mod tests {
         fn get_entity_ref(&self) -> Self::TEntityId {}
     }
 
-    #[async_trait(?Send)]
-    impl BacklogStore<MockOrder> for MockBacklogStore {
-        async fn put(&mut self, ord: BacklogOrder<MockOrder>) {
-            self.inner.insert(ord.order.order_id, ord);
-        }
-
-        async fn exists(&self, ord_id: MockOrderId) -> bool {
-            self.inner.contains_key(&ord_id)
-        }
-
-        async fn remove(&mut self, ord_id: MockOrderId) {
-            self.inner.remove(&ord_id);
-        }
-
-        async fn get(&self, ord_id: MockOrderId) -> Option<BacklogOrder<MockOrder>> {
-            self.inner.get(&ord_id).cloned()
-        }
-
-        async fn find_orders<F>(&self, f: F) -> Vec<BacklogOrder<MockOrder>>
-        where
-            F: Fn(&MockOrder) -> bool + Send + 'static,
-        {
-            self.inner.values().cloned().filter(|b| f(&b.order)).collect()
-        }
-    }
-
     async fn setup_backlog(
         order_lifespan_secs: i64,
         order_exec_time_secs: i64,
         retry_suspended_prob: u8,
-    ) -> BacklogService<MockOrder, MockBacklogStore> {
-        let store = MockBacklogStore::new();
+    ) -> BacklogService<MockOrder, InMemoryBacklogStore<MockOrder>> {
+        let store = InMemoryBacklogStore::new();
         let conf = BacklogConfig {
             order_lifespan: Duration::seconds(order_lifespan_secs),
             order_exec_time: Duration::seconds(order_exec_time_secs),
             retry_suspended_prob: <BoundedU8<0, 100>>::new(retry_suspended_prob).unwrap(),
+            retry_backoff_base: Duration::zero(),
+            retry_backoff_cap: Duration::zero(),
+            max_attempts: 3,
+            prioritization: PrioritizationPolicy::FeeBased,
         };
         BacklogService::new::<MockOrder>(store, conf).await
     }
 
+    async fn setup_backlog_with_policy(
+        prioritization: PrioritizationPolicy,
+    ) -> BacklogService<MockOrder, InMemoryBacklogStore<MockOrder>> {
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(100),
+            order_exec_time: Duration::seconds(50),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(0).unwrap(),
+            retry_backoff_base: Duration::zero(),
+            retry_backoff_cap: Duration::zero(),
+            max_attempts: 3,
+            prioritization,
+        };
+        BacklogService::new::<MockOrder>(InMemoryBacklogStore::new(), conf).await
+    }
+
     fn make_order(id: i64, weight: u64) -> BacklogOrder<MockOrder> {
         BacklogOrder {
             order: MockOrder {
@@ -531,6 +1104,7 @@ mod tests {
                 weight: OrderWeight::from(weight),
             },
             timestamp: Utc::now().timestamp(),
+            stage: OrderStage::Pending,
         }
     }
 
@@ -539,7 +1113,7 @@ mod tests {
         let mut backlog = setup_backlog(10, 5, 50).await;
         let ord = make_order(1, 1);
         backlog.put(ord.clone().into()).await;
-        let suspended = backlog.suspend(ord.order).await;
+        let suspended = backlog.suspend(ord.order, String::from("error")).await;
         assert!(suspended)
     }
 
@@ -556,7 +1130,7 @@ mod tests {
     async fn should_not_suspend_non_existent_order() {
         let mut backlog = setup_backlog(10, 5, 50).await;
         let ord = make_order(1, 1);
-        let suspended = backlog.suspend(ord.order).await;
+        let suspended = backlog.suspend(ord.order, String::from("error")).await;
         assert!(!suspended)
     }
 
@@ -597,7 +1171,7 @@ mod tests {
         backlog.put(ord2.into()).await;
         backlog.put(ord3.clone().into()).await;
         let _ = backlog.try_pop().await;
-        backlog.suspend(ord3.clone().order).await;
+        backlog.suspend(ord3.clone().order, String::from("error")).await;
 
         let res = backlog.try_pop().await;
         assert_eq!(res, Some(ord3.order))
@@ -613,18 +1187,299 @@ mod tests {
         backlog.put(ord2.clone().into()).await;
         backlog.put(ord3.clone().into()).await;
         let _ = backlog.try_pop().await;
-        backlog.suspend(ord3.clone().order).await;
+        backlog.suspend(ord3.clone().order, String::from("error")).await;
+
+        let res = backlog.try_pop().await;
+        assert_eq!(res, Some(ord2.order))
+    }
+
+    #[tokio::test]
+    async fn should_pop_unsuspended_order() {
+        let mut backlog = setup_backlog(10, 5, 0).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        backlog.put(ord1.into()).await;
+        backlog.put(ord2.clone().into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.suspend(ord2.clone().order, String::from("error")).await;
 
+        assert!(backlog.unsuspend(ord2.order.order_id).await);
         let res = backlog.try_pop().await;
         assert_eq!(res, Some(ord2.order))
     }
 
+    #[tokio::test]
+    async fn should_pop_reweighted_order_first() {
+        let mut backlog = setup_backlog(10, 5, 0).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        backlog.put(ord1.clone().into()).await;
+        backlog.put(ord2.into()).await;
+
+        assert!(
+            backlog
+                .set_weight(ord1.order.order_id, OrderWeight::from(3))
+                .await
+        );
+        let res = backlog.try_pop().await;
+        assert_eq!(res, Some(ord1.order))
+    }
+
+    #[tokio::test]
+    async fn should_report_entries_with_overridden_weight() {
+        let mut backlog = setup_backlog(10, 5, 0).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        backlog.put(ord1.clone().into()).await;
+        backlog.put(ord2.clone().into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.suspend(ord2.order.clone(), String::from("error")).await;
+        backlog
+            .set_weight(ord1.order.order_id, OrderWeight::from(3))
+            .await;
+
+        let mut entries = backlog.find_entries().await;
+        entries.sort_by_key(|e| e.order.order_id);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].stage, OrderStage::Pending);
+        assert_eq!(entries[0].weight, OrderWeight::from(3));
+        assert_eq!(entries[1].weight, OrderWeight::from(2));
+        assert!(matches!(
+            &entries[1].stage,
+            OrderStage::Suspended(Suspension { attempts: 1, last_error, .. }) if last_error == "error"
+        ));
+
+        // Override is gone once the order leaves the queue.
+        let _ = backlog.try_pop().await;
+        backlog.recharge(ord1.order.clone()).await;
+        let entries = backlog.find_entries().await;
+        let entry = entries.iter().find(|e| e.order == ord1.order).unwrap();
+        assert_eq!(entry.weight, OrderWeight::from(1));
+    }
+
+    #[tokio::test]
+    async fn should_restore_order_stages() {
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(10),
+            order_exec_time: Duration::seconds(5),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(0).unwrap(),
+            retry_backoff_base: Duration::zero(),
+            retry_backoff_cap: Duration::zero(),
+            max_attempts: 3,
+            prioritization: PrioritizationPolicy::FeeBased,
+        };
+        let mut backlog = BacklogService::new::<MockOrder>(InMemoryBacklogStore::new(), conf.clone()).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        let ord3 = make_order(3, 3);
+        backlog.put(ord1.clone().into()).await;
+        backlog.put(ord2.clone().into()).await;
+        backlog.put(ord3.clone().into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.check_later(ord3.into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.suspend(ord2.order, String::from("error")).await;
+
+        let restored = BacklogService::new::<MockOrder>(backlog.store, conf).await;
+        assert_eq!(
+            restored.stats(),
+            BacklogStats {
+                pending: 1,
+                suspended: 1,
+                revisit: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_restore_orders_stored_without_stage() {
+        let rnd = rand::thread_rng().next_u32();
+        let store = BacklogStoreRocksDB {
+            db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(format!("./tmp/{}", rnd)).unwrap()),
+        };
+        let legacy_ord = make_order(1, 1);
+        store
+            .db
+            .put(
+                bincode::serialize(&legacy_ord.order.order_id).unwrap(),
+                bincode::serialize(&(legacy_ord.order.clone(), legacy_ord.timestamp)).unwrap(),
+            )
+            .unwrap();
+        store
+            .db
+            .put(bincode::serialize(&MockOrderId(2)).unwrap(), vec![0xff])
+            .unwrap();
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(10),
+            order_exec_time: Duration::seconds(5),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(0).unwrap(),
+            retry_backoff_base: Duration::zero(),
+            retry_backoff_cap: Duration::zero(),
+            max_attempts: 3,
+            prioritization: PrioritizationPolicy::FeeBased,
+        };
+        let mut restored = BacklogService::new::<MockOrder>(store, conf).await;
+        assert_eq!(
+            restored.stats(),
+            BacklogStats {
+                pending: 1,
+                suspended: 0,
+                revisit: 0,
+            }
+        );
+        assert!(!restored.exists(MockOrderId(2)).await);
+        assert_eq!(restored.try_pop().await, Some(legacy_ord.order));
+    }
+
+    #[tokio::test]
+    async fn should_not_pop_suspended_order_before_backoff_elapsed() {
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(10),
+            order_exec_time: Duration::seconds(5),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(100).unwrap(),
+            retry_backoff_base: Duration::seconds(60),
+            retry_backoff_cap: Duration::seconds(600),
+            max_attempts: 3,
+            prioritization: PrioritizationPolicy::FeeBased,
+        };
+        let mut backlog = BacklogService::new::<MockOrder>(InMemoryBacklogStore::new(), conf).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        backlog.put(ord1.clone().into()).await;
+        backlog.put(ord2.clone().into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.suspend(ord2.order, String::from("error")).await;
+
+        let res = backlog.try_pop().await;
+        assert_eq!(res, Some(ord1.order));
+        let suspended = backlog.find_suspended().await;
+        assert_eq!(suspended.len(), 1);
+        assert_eq!(suspended[0].attempts, 1);
+        assert_eq!(suspended[0].last_error, "error");
+        assert!(suspended[0].retry_at > Utc::now().timestamp());
+    }
+
+    #[tokio::test]
+    async fn should_drop_order_after_max_attempts() {
+        let mut backlog = setup_backlog(10, 5, 100).await;
+        let ord = make_order(1, 1);
+        backlog.put(ord.clone().into()).await;
+        for _ in 0..2 {
+            let _ = backlog.try_pop().await;
+            assert!(backlog.suspend(ord.clone().order, String::from("error")).await);
+        }
+        let _ = backlog.try_pop().await;
+        assert!(!backlog.suspend(ord.clone().order, String::from("error")).await);
+        assert!(!backlog.exists(ord.order.order_id).await);
+    }
+
+    #[test]
+    fn retry_delay_grows_exponentially_up_to_cap() {
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(10),
+            order_exec_time: Duration::seconds(5),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(0).unwrap(),
+            retry_backoff_base: Duration::seconds(10),
+            retry_backoff_cap: Duration::seconds(60),
+            max_attempts: 100,
+            prioritization: PrioritizationPolicy::FeeBased,
+        };
+        assert_eq!(conf.retry_delay(1), Duration::seconds(10));
+        assert_eq!(conf.retry_delay(3), Duration::seconds(40));
+        assert_eq!(conf.retry_delay(4), Duration::seconds(60));
+        assert_eq!(conf.retry_delay(64), Duration::seconds(60));
+    }
+
+    #[tokio::test]
+    async fn should_pop_oldest_order_when_fifo() {
+        let mut backlog = setup_backlog_with_policy(PrioritizationPolicy::Fifo).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = BacklogOrder {
+            timestamp: ord1.timestamp - 10,
+            ..make_order(2, 1)
+        };
+        let ord3 = make_order(3, 3);
+        backlog.put(ord1.into()).await;
+        backlog.put(ord2.clone().into()).await;
+        backlog.put(ord3.into()).await;
+
+        let res = backlog.try_pop().await;
+        assert_eq!(res, Some(ord2.order))
+    }
+
+    #[tokio::test]
+    async fn should_pop_aged_order_when_age_boosted() {
+        let mut backlog =
+            setup_backlog_with_policy(PrioritizationPolicy::AgeBoosted { boost_per_sec: 10 }).await;
+        let ord1 = BacklogOrder {
+            timestamp: Utc::now().timestamp() - 25,
+            ..make_order(1, 1)
+        };
+        let ord2 = make_order(2, 200);
+        let ord3 = make_order(3, 300);
+        backlog.put(ord1.clone().into()).await;
+        backlog.put(ord2.into()).await;
+        backlog.put(ord3.clone().into()).await;
+
+        let res1 = backlog.try_pop().await;
+        let res2 = backlog.try_pop().await;
+        assert_eq!(res1, Some(ord3.order));
+        assert_eq!(res2, Some(ord1.order));
+    }
+
+    #[test]
+    fn round_robin_priorities() {
+        let mut prioritizer = Prioritizer::new(PrioritizationPolicy::RoundRobin);
+        let a1 = prioritizer.priority("a", OrderWeight::from(5), 0);
+        let a2 = prioritizer.priority("a", OrderWeight::from(5), 1);
+        let b1 = prioritizer.priority("b", OrderWeight::from(1), 2);
+        assert!(a1 > b1);
+        assert!(b1 > a2);
+        prioritizer.on_pop(a1);
+        prioritizer.on_pop(b1);
+        prioritizer.on_pop(a2);
+        // Pool which was idle starts from the current round.
+        let c1 = prioritizer.priority("c", OrderWeight::from(1), 3);
+        let a3 = prioritizer.priority("a", OrderWeight::from(1), 4);
+        assert_eq!(c1.rank, a3.rank);
+        assert!(a2 > c1);
+    }
+
+    #[tokio::test]
+    async fn should_report_stats() {
+        let mut backlog = setup_backlog(10, 5, 0).await;
+        let ord1 = make_order(1, 1);
+        let ord2 = make_order(2, 2);
+        backlog.put(ord1.into()).await;
+        backlog.put(ord2.clone().into()).await;
+        let _ = backlog.try_pop().await;
+        backlog.suspend(ord2.order, String::from("error")).await;
+
+        assert_eq!(
+            backlog.stats(),
+            BacklogStats {
+                pending: 1,
+                suspended: 1,
+                revisit: 0,
+            }
+        )
+    }
+
     #[tokio::test]
     async fn test_rocksdb_backlog() {
         let rnd = rand::thread_rng().next_u32();
-        let mut store = BacklogStoreRocksDB {
+        let store = BacklogStoreRocksDB {
             db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(format!("./tmp/{}", rnd)).unwrap()),
         };
+        test_backlog_store(store).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_backlog() {
+        test_backlog_store(InMemoryBacklogStore::<MockOrder>::new()).await;
+    }
+
+    async fn test_backlog_store<S: BacklogStore<MockOrder>>(mut store: S) {
         for i in 0..30 {
             store.put(make_order(i, i as u64)).await;
         }
@@ -637,23 +1492,14 @@ mod tests {
         };
 
         for i in 0..30 {
-            assert!(<BacklogStoreRocksDB as BacklogStore<MockOrder>>::exists(&store, MockOrderId(i)).await);
-            check_eq(
-                make_order(i, i as u64),
-                <BacklogStoreRocksDB as BacklogStore<MockOrder>>::get(&store, MockOrderId(i))
-                    .await
-                    .unwrap(),
-            );
+            assert!(store.exists(MockOrderId(i)).await);
+            check_eq(make_order(i, i as u64), store.get(MockOrderId(i)).await.unwrap());
         }
 
         for i in 0..30 {
-            <BacklogStoreRocksDB as BacklogStore<MockOrder>>::remove(&mut store, MockOrderId(i)).await;
-            assert!(!<BacklogStoreRocksDB as BacklogStore<MockOrder>>::exists(&store, MockOrderId(i)).await);
-            assert!(
-                <BacklogStoreRocksDB as BacklogStore<MockOrder>>::get(&store, MockOrderId(i))
-                    .await
-                    .is_none()
-            )
+            store.remove(MockOrderId(i)).await;
+            assert!(!store.exists(MockOrderId(i)).await);
+            assert!(store.get(MockOrderId(i)).await.is_none())
         }
     }
 }
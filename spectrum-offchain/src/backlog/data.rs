@@ -4,6 +4,38 @@ use serde::{Deserialize, Serialize};
 pub struct BacklogOrder<TOrd> {
     pub order: TOrd,
     pub timestamp: i64,
+    pub stage: OrderStage,
+}
+
+/// An order along with its current state in the backlog.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BacklogEntry<TOrd> {
+    pub order: TOrd,
+    pub timestamp: i64,
+    pub stage: OrderStage,
+    /// Weight the order is prioritized by, accounting for overrides.
+    pub weight: OrderWeight,
+}
+
+/// Stage of the order lifecycle, so that backlog can be restored exactly after restart.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub enum OrderStage {
+    /// Waiting for execution.
+    Pending,
+    /// Temporarily failed, waiting for retry.
+    Suspended(Suspension),
+    /// Executed at the given timestamp, waiting for settlement.
+    Progressing(i64),
+}
+
+/// Failure history of a suspended order.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+pub struct Suspension {
+    /// Number of failed execution attempts.
+    pub attempts: u32,
+    pub last_error: String,
+    /// The order is not retried before this timestamp.
+    pub retry_at: i64,
 }
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone, Serialize, Deserialize)]
@@ -15,6 +47,12 @@ impl From<u64> for OrderWeight {
     }
 }
 
+impl From<OrderWeight> for u64 {
+    fn from(x: OrderWeight) -> Self {
+        x.0
+    }
+}
+
 pub trait Weighted {
     fn weight(&self) -> OrderWeight;
 }
@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_std::task::spawn_blocking;
 use async_trait::async_trait;
+use log::warn;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::backlog::data::BacklogOrder;
+use crate::backlog::data::{BacklogOrder, OrderStage};
 use crate::data::OnChainOrder;
 use ergo_chain_sync::rocksdb::RocksConfig;
 
@@ -35,6 +37,27 @@ impl BacklogStoreRocksDB {
     }
 }
 
+/// Order stored before lifecycle stages were persisted.
+#[derive(Deserialize)]
+struct LegacyBacklogOrder<TOrd> {
+    order: TOrd,
+    timestamp: i64,
+}
+
+/// Decode a stored order. Orders stored without a stage are restored as pending,
+/// just like all orders were restored before.
+fn decode_order<TOrd: DeserializeOwned>(bytes: &[u8]) -> Option<BacklogOrder<TOrd>> {
+    bincode::deserialize(bytes).ok().or_else(|| {
+        bincode::deserialize::<LegacyBacklogOrder<TOrd>>(bytes)
+            .ok()
+            .map(|legacy| BacklogOrder {
+                order: legacy.order,
+                timestamp: legacy.timestamp,
+                stage: OrderStage::Pending,
+            })
+    })
+}
+
 #[async_trait(?Send)]
 impl<TOrd> BacklogStore<TOrd> for BacklogStoreRocksDB
 where
@@ -65,9 +88,15 @@ where
     async fn get(&self, ord_id: TOrd::TOrderId) -> Option<BacklogOrder<TOrd>> {
         let db = self.db.clone();
         spawn_blocking(move || {
-            db.get(bincode::serialize(&ord_id).unwrap())
-                .unwrap()
-                .map(|b| bincode::deserialize(&b).unwrap())
+            let key = bincode::serialize(&ord_id).unwrap();
+            let bytes = db.get(&key).unwrap()?;
+            let res = decode_order(&bytes);
+            if res.is_none() {
+                // Otherwise the order is never executed, while `exists()` keeps it from being re-added.
+                warn!(target: "backlog", "Removing undecodable order stored under {:?}", key);
+                db.delete(key).unwrap();
+            }
+            res
         })
         .await
     }
@@ -78,18 +107,75 @@ where
     {
         let db = self.db.clone();
         spawn_blocking(move || {
-            db.iterator(rocksdb::IteratorMode::Start)
+            let mut undecodable = Vec::new();
+            let orders = db
+                .iterator(rocksdb::IteratorMode::Start)
                 .filter_map(|i| {
-                    let (_, v) = i.unwrap();
-                    if let Ok(b) = bincode::deserialize::<BacklogOrder<TOrd>>(&v) {
-                        if f(&b.order) {
-                            return Some(b);
+                    let (k, v) = i.unwrap();
+                    match decode_order::<TOrd>(&v) {
+                        Some(b) if f(&b.order) => Some(b),
+                        Some(_) => None,
+                        None => {
+                            undecodable.push(k);
+                            None
                         }
                     }
-                    None
                 })
-                .collect()
+                .collect();
+            for key in undecodable {
+                warn!(target: "backlog", "Removing undecodable order stored under {:?}", key);
+                db.delete(key).unwrap();
+            }
+            orders
         })
         .await
     }
 }
+
+/// In-memory implementation of `BacklogStore`.
+pub struct InMemoryBacklogStore<TOrd: OnChainOrder> {
+    orders: HashMap<TOrd::TOrderId, BacklogOrder<TOrd>>,
+}
+
+impl<TOrd: OnChainOrder> InMemoryBacklogStore<TOrd> {
+    pub fn new() -> Self {
+        Self {
+            orders: HashMap::new(),
+        }
+    }
+}
+
+impl<TOrd: OnChainOrder> Default for InMemoryBacklogStore<TOrd> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<TOrd> BacklogStore<TOrd> for InMemoryBacklogStore<TOrd>
+where
+    TOrd: OnChainOrder + Clone,
+{
+    async fn put(&mut self, ord: BacklogOrder<TOrd>) {
+        self.orders.insert(ord.order.get_self_ref(), ord);
+    }
+
+    async fn exists(&self, ord_id: TOrd::TOrderId) -> bool {
+        self.orders.contains_key(&ord_id)
+    }
+
+    async fn remove(&mut self, ord_id: TOrd::TOrderId) {
+        self.orders.remove(&ord_id);
+    }
+
+    async fn get(&self, ord_id: TOrd::TOrderId) -> Option<BacklogOrder<TOrd>> {
+        self.orders.get(&ord_id).cloned()
+    }
+
+    async fn find_orders<F>(&self, f: F) -> Vec<BacklogOrder<TOrd>>
+    where
+        F: Fn(&TOrd) -> bool + Send + 'static,
+    {
+        self.orders.values().filter(|b| f(&b.order)).cloned().collect()
+    }
+}
@@ -6,6 +6,7 @@ use crate::box_resolver::persistence::EntityRepo;
 use crate::data::unique_entity::{Confirmed, Predicted, Traced, Unconfirmed};
 use crate::data::OnChainEntity;
 
+pub mod in_memory;
 pub mod persistence;
 pub mod process;
 pub mod rocksdb;
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::box_resolver::persistence::EntityRepo;
+use crate::box_resolver::{Predicted, Traced};
+use crate::data::unique_entity::{Confirmed, Unconfirmed};
+use crate::data::OnChainEntity;
+
+/// In-memory implementation of `EntityRepo` mirroring semantics of `EntityRepoRocksDB`.
+pub struct InMemoryEntityRepo<TEntity: OnChainEntity> {
+    states: HashMap<TEntity::TStateId, TEntity>,
+    prediction_links: HashMap<TEntity::TStateId, TEntity::TStateId>,
+    last_predicted: HashMap<TEntity::TEntityId, TEntity::TStateId>,
+    last_confirmed: HashMap<TEntity::TEntityId, TEntity::TStateId>,
+    last_unconfirmed: HashMap<TEntity::TEntityId, TEntity::TStateId>,
+}
+
+impl<TEntity: OnChainEntity> InMemoryEntityRepo<TEntity> {
+    pub fn new() -> Self {
+        Self {
+            states: HashMap::new(),
+            prediction_links: HashMap::new(),
+            last_predicted: HashMap::new(),
+            last_confirmed: HashMap::new(),
+            last_unconfirmed: HashMap::new(),
+        }
+    }
+}
+
+impl<TEntity: OnChainEntity> Default for InMemoryEntityRepo<TEntity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<TEntity> EntityRepo<TEntity> for InMemoryEntityRepo<TEntity>
+where
+    TEntity: OnChainEntity + Clone,
+    <TEntity as OnChainEntity>::TStateId: Clone,
+    <TEntity as OnChainEntity>::TEntityId: Clone,
+{
+    async fn get_prediction_predecessor<'a>(
+        &self,
+        sid: <TEntity as OnChainEntity>::TStateId,
+    ) -> Option<TEntity::TStateId>
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        self.prediction_links.get(&sid).cloned()
+    }
+
+    async fn get_last_predicted<'a>(
+        &self,
+        id: <TEntity as OnChainEntity>::TEntityId,
+    ) -> Option<Predicted<TEntity>>
+    where
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        self.last_predicted
+            .get(&id)
+            .filter(|sid| self.prediction_links.contains_key(sid))
+            .and_then(|sid| self.states.get(sid))
+            .cloned()
+            .map(Predicted)
+    }
+
+    async fn get_last_confirmed<'a>(
+        &self,
+        id: <TEntity as OnChainEntity>::TEntityId,
+    ) -> Option<Confirmed<TEntity>>
+    where
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        self.last_confirmed
+            .get(&id)
+            .and_then(|sid| self.states.get(sid))
+            .cloned()
+            .map(Confirmed)
+    }
+
+    async fn get_last_unconfirmed<'a>(
+        &self,
+        id: <TEntity as OnChainEntity>::TEntityId,
+    ) -> Option<Unconfirmed<TEntity>>
+    where
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        self.last_unconfirmed
+            .get(&id)
+            .and_then(|sid| self.states.get(sid))
+            .cloned()
+            .map(Unconfirmed)
+    }
+
+    async fn put_predicted<'a>(
+        &mut self,
+        Traced {
+            state: Predicted(entity),
+            prev_state_id,
+        }: Traced<Predicted<TEntity>>,
+    ) where
+        Traced<Predicted<TEntity>>: 'a,
+    {
+        let sid = entity.get_self_state_ref();
+        self.last_predicted.insert(entity.get_self_ref(), sid.clone());
+        if let Some(prev_sid) = prev_state_id {
+            self.prediction_links.insert(sid.clone(), prev_sid);
+        }
+        self.states.insert(sid, entity);
+    }
+
+    async fn put_confirmed<'a>(&mut self, Confirmed(entity): Confirmed<TEntity>)
+    where
+        Traced<Predicted<TEntity>>: 'a,
+    {
+        let sid = entity.get_self_state_ref();
+        let eid = entity.get_self_ref();
+        self.last_unconfirmed.remove(&eid);
+        self.last_confirmed.insert(eid, sid.clone());
+        self.states.insert(sid, entity);
+    }
+
+    async fn put_unconfirmed<'a>(&mut self, Unconfirmed(entity): Unconfirmed<TEntity>)
+    where
+        Traced<Predicted<TEntity>>: 'a,
+    {
+        let sid = entity.get_self_state_ref();
+        self.last_unconfirmed.insert(entity.get_self_ref(), sid.clone());
+        self.states.insert(sid, entity);
+    }
+
+    async fn invalidate<'a>(
+        &mut self,
+        sid: <TEntity as OnChainEntity>::TStateId,
+        eid: <TEntity as OnChainEntity>::TEntityId,
+    ) where
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        self.prediction_links.remove(&sid);
+        self.last_confirmed.remove(&eid);
+        self.last_unconfirmed.remove(&eid);
+    }
+
+    async fn discard_predicted<'a>(&mut self, sid: <TEntity as OnChainEntity>::TStateId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        self.prediction_links.remove(&sid);
+    }
+
+    async fn discard_unconfirmed<'a>(
+        &mut self,
+        sid: <TEntity as OnChainEntity>::TStateId,
+        eid: <TEntity as OnChainEntity>::TEntityId,
+    ) where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        if self.last_unconfirmed.get(&eid) == Some(&sid) {
+            self.last_unconfirmed.remove(&eid);
+        }
+    }
+
+    async fn eliminate<'a>(&mut self, entity: TEntity)
+    where
+        TEntity: 'a,
+    {
+        let eid = entity.get_self_ref();
+        self.prediction_links.remove(&entity.get_self_state_ref());
+        self.last_predicted.remove(&eid);
+        self.last_confirmed.remove(&eid);
+        self.last_unconfirmed.remove(&eid);
+    }
+
+    async fn may_exist<'a>(&self, sid: <TEntity as OnChainEntity>::TStateId) -> bool
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        self.states.contains_key(&sid)
+    }
+
+    async fn get_state<'a>(&self, sid: <TEntity as OnChainEntity>::TStateId) -> Option<TEntity>
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        self.states.get(&sid).cloned()
+    }
+}
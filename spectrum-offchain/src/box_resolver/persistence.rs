@@ -32,6 +32,8 @@ pub trait EntityRepo<TEntity: OnChainEntity> {
     where
         Traced<Predicted<TEntity>>: 'a;
     /// Persist confirmed state of the entity.
+    /// Last unconfirmed state is discarded, as it is either confirmed this way or conflicts with it.
+    /// Own unconfirmed txs chained on top of the confirmed state are still resolved via predictions.
     async fn put_confirmed<'a>(&mut self, entity: Confirmed<TEntity>)
     where
         Traced<Predicted<TEntity>>: 'a;
@@ -41,6 +43,17 @@ pub trait EntityRepo<TEntity: OnChainEntity> {
         Traced<Predicted<TEntity>>: 'a;
     /// Invalidate particular state of the entity.
     async fn invalidate<'a>(&mut self, sid: TEntity::TStateId, eid: TEntity::TEntityId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+        <TEntity as OnChainEntity>::TEntityId: 'a;
+    /// Discard particular predicted state of the entity along with all predictions made upon it.
+    /// Confirmed and unconfirmed states are kept intact.
+    async fn discard_predicted<'a>(&mut self, sid: TEntity::TStateId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a;
+    /// Stop tracking particular state of the entity as the last unconfirmed one.
+    /// Nothing happens if another state is the last unconfirmed one. Confirmed state is kept intact.
+    async fn discard_unconfirmed<'a>(&mut self, sid: TEntity::TStateId, eid: TEntity::TEntityId)
     where
         <TEntity as OnChainEntity>::TStateId: 'a,
         <TEntity as OnChainEntity>::TEntityId: 'a;
@@ -167,6 +180,25 @@ where
         trace!(target: "box_resolver", "invalidate({:?}) -> ()", sid);
     }
 
+    async fn discard_predicted<'a>(&mut self, sid: TEntity::TStateId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        trace!(target: "box_resolver", "discard_predicted({:?})", sid);
+        self.inner.discard_predicted(sid).await;
+        trace!(target: "box_resolver", "discard_predicted({:?}) -> ()", sid);
+    }
+
+    async fn discard_unconfirmed<'a>(&mut self, sid: TEntity::TStateId, eid: TEntity::TEntityId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        trace!(target: "box_resolver", "discard_unconfirmed({:?}, {:?})", sid, eid);
+        self.inner.discard_unconfirmed(sid, eid).await;
+        trace!(target: "box_resolver", "discard_unconfirmed({:?}, {:?}) -> ()", sid, eid);
+    }
+
     async fn eliminate<'a>(&mut self, entity: TEntity)
     where
         TEntity: 'a,
@@ -214,6 +246,7 @@ pub(crate) mod tests {
     use serde::{Deserialize, Serialize};
     use sigma_test_util::force_any_val;
 
+    use crate::box_resolver::in_memory::InMemoryEntityRepo;
     use crate::box_resolver::rocksdb::EntityRepoRocksDB;
     use crate::{
         box_resolver::persistence::EntityRepo,
@@ -266,18 +299,81 @@ pub(crate) mod tests {
         test_entity_repo_unconfirmed(client).await;
     }
 
+    #[tokio::test]
+    async fn test_rocksdb_confirmed_supersedes_unconfirmed() {
+        let client = rocks_db_client();
+        test_entity_repo_confirmed_supersedes_unconfirmed(client).await;
+    }
+
     #[tokio::test]
     async fn test_rocksdb_invalidate() {
         let client = rocks_db_client();
         test_entity_repo_invalidate(client).await;
     }
 
+    #[tokio::test]
+    async fn test_rocksdb_discard_predicted() {
+        let client = rocks_db_client();
+        test_entity_repo_discard_predicted(client).await;
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_discard_unconfirmed() {
+        let client = rocks_db_client();
+        test_entity_repo_discard_unconfirmed(client).await;
+    }
+
     #[tokio::test]
     async fn test_rocksdb_eliminate() {
         let client = rocks_db_client();
         test_entity_repo_eliminate(client).await;
     }
 
+    #[tokio::test]
+    async fn test_in_memory_may_exist() {
+        test_entity_repo_may_exist(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_predicted() {
+        test_entity_repo_predicted(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_confirmed() {
+        test_entity_repo_confirmed(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_unconfirmed() {
+        test_entity_repo_unconfirmed(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_confirmed_supersedes_unconfirmed() {
+        test_entity_repo_confirmed_supersedes_unconfirmed(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_invalidate() {
+        test_entity_repo_invalidate(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_discard_predicted() {
+        test_entity_repo_discard_predicted(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_discard_unconfirmed() {
+        test_entity_repo_discard_unconfirmed(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_eliminate() {
+        test_entity_repo_eliminate(InMemoryEntityRepo::<ErgoEntity>::new()).await;
+    }
+
     pub fn rocks_db_client() -> EntityRepoRocksDB {
         let rnd = rand::thread_rng().next_u32();
         EntityRepoRocksDB {
@@ -383,6 +479,81 @@ pub(crate) mod tests {
         }
     }
 
+    async fn test_entity_repo_discard_predicted<C: EntityRepo<ErgoEntity>>(mut client: C) {
+        let (box_ids, token_ids, n) = gen_box_and_token_ids();
+        for i in 1..n {
+            let ee = ErgoEntity {
+                token_id: token_ids[i],
+                box_id: box_ids[i],
+            };
+            let entity = Traced {
+                state: Predicted(ee.clone()),
+                prev_state_id: box_ids.get(i - 1).cloned(),
+            };
+            client.put_predicted(entity.clone()).await;
+            client.put_confirmed(Confirmed(ee)).await;
+
+            <C as EntityRepo<ErgoEntity>>::discard_predicted(&mut client, box_ids[i]).await;
+            let link: Option<BoxId> = client.get_prediction_predecessor(box_ids[i]).await;
+            let predicted: Option<Predicted<ErgoEntity>> = client.get_last_predicted(token_ids[i]).await;
+            let confirmed: Option<Confirmed<ErgoEntity>> = client.get_last_confirmed(token_ids[i]).await;
+            assert!(link.is_none());
+            assert!(predicted.is_none());
+            assert!(confirmed.is_some());
+        }
+    }
+
+    async fn test_entity_repo_discard_unconfirmed<C: EntityRepo<ErgoEntity>>(mut client: C) {
+        let (box_ids, token_ids, n) = gen_box_and_token_ids();
+        for i in 1..n {
+            let confirmed = ErgoEntity {
+                token_id: token_ids[i],
+                box_id: box_ids[i - 1],
+            };
+            let unconfirmed = ErgoEntity {
+                token_id: token_ids[i],
+                box_id: box_ids[i],
+            };
+            client.put_confirmed(Confirmed(confirmed.clone())).await;
+            client.put_unconfirmed(Unconfirmed(unconfirmed)).await;
+
+            // Another state is the last unconfirmed one
+            <C as EntityRepo<ErgoEntity>>::discard_unconfirmed(&mut client, box_ids[i - 1], token_ids[i])
+                .await;
+            let last_unconfirmed: Option<Unconfirmed<ErgoEntity>> =
+                client.get_last_unconfirmed(token_ids[i]).await;
+            assert!(last_unconfirmed.is_some());
+
+            <C as EntityRepo<ErgoEntity>>::discard_unconfirmed(&mut client, box_ids[i], token_ids[i]).await;
+            let last_unconfirmed: Option<Unconfirmed<ErgoEntity>> =
+                client.get_last_unconfirmed(token_ids[i]).await;
+            let last_confirmed: Option<Confirmed<ErgoEntity>> = client.get_last_confirmed(token_ids[i]).await;
+            assert!(last_unconfirmed.is_none());
+            assert_eq!(last_confirmed.map(|Confirmed(e)| e), Some(confirmed));
+        }
+    }
+
+    async fn test_entity_repo_confirmed_supersedes_unconfirmed<C: EntityRepo<ErgoEntity>>(mut client: C) {
+        let (box_ids, token_ids, n) = gen_box_and_token_ids();
+        for i in 1..n {
+            client
+                .put_unconfirmed(Unconfirmed(ErgoEntity {
+                    token_id: token_ids[i],
+                    box_id: box_ids[i - 1],
+                }))
+                .await;
+            client
+                .put_confirmed(Confirmed(ErgoEntity {
+                    token_id: token_ids[i],
+                    box_id: box_ids[i],
+                }))
+                .await;
+            let last_unconfirmed: Option<Unconfirmed<ErgoEntity>> =
+                client.get_last_unconfirmed(token_ids[i]).await;
+            assert!(last_unconfirmed.is_none());
+        }
+    }
+
     async fn test_entity_repo_eliminate<C: EntityRepo<ErgoEntity>>(mut client: C) {
         let (box_ids, token_ids, n) = gen_box_and_token_ids();
         for i in 1..n {
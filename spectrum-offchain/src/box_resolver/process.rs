@@ -5,7 +5,7 @@ use tokio::sync::Mutex;
 
 use crate::box_resolver::persistence::EntityRepo;
 use crate::combinators::EitherOrBoth;
-use crate::data::unique_entity::{Confirmed, StateUpdate};
+use crate::data::unique_entity::{Confirmed, StateUpdate, Unconfirmed};
 use crate::data::OnChainEntity;
 
 pub fn entity_tracking_stream<'a, S, TRepo, TEntity>(
@@ -36,3 +36,169 @@ where
         }
     })
 }
+
+pub fn unconfirmed_entity_tracking_stream<'a, S, TRepo, TEntity>(
+    upstream: S,
+    entities: Arc<Mutex<TRepo>>,
+) -> impl Stream<Item = ()> + 'a
+where
+    S: Stream<Item = Unconfirmed<StateUpdate<TEntity>>> + 'a,
+    TEntity: OnChainEntity + 'a,
+    TRepo: EntityRepo<TEntity> + 'a,
+{
+    upstream.then(move |Unconfirmed(upd)| {
+        let entities = Arc::clone(&entities);
+        async move {
+            let mut repo = entities.lock().await;
+            match upd {
+                StateUpdate::Transition(EitherOrBoth::Right(new_state))
+                | StateUpdate::Transition(EitherOrBoth::Both(_, new_state))
+                | StateUpdate::TransitionRollback(EitherOrBoth::Right(new_state)) => {
+                    repo.put_unconfirmed(Unconfirmed(new_state)).await
+                }
+                // Entity is eliminated only once the consuming tx is confirmed,
+                // until then the consumed state is just not resolved as unconfirmed.
+                StateUpdate::Transition(EitherOrBoth::Left(st)) => {
+                    repo.discard_unconfirmed(st.get_self_state_ref(), st.get_self_ref())
+                        .await
+                }
+                // State created by the withdrawn tx must not be resolved anymore,
+                // the confirmed state of the entity is kept intact.
+                StateUpdate::TransitionRollback(EitherOrBoth::Left(withdrawn_state))
+                | StateUpdate::TransitionRollback(EitherOrBoth::Both(withdrawn_state, _)) => {
+                    repo.discard_predicted(withdrawn_state.get_self_state_ref()).await;
+                    repo.discard_unconfirmed(
+                        withdrawn_state.get_self_state_ref(),
+                        withdrawn_state.get_self_ref(),
+                    )
+                    .await
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::{stream, StreamExt};
+    use sigma_test_util::force_any_val;
+    use tokio::sync::Mutex;
+
+    use crate::box_resolver::in_memory::InMemoryEntityRepo;
+    use crate::box_resolver::persistence::tests::ErgoEntity;
+    use crate::box_resolver::persistence::EntityRepo;
+    use crate::box_resolver::process::{entity_tracking_stream, unconfirmed_entity_tracking_stream};
+    use crate::box_resolver::resolve_entity_state;
+    use crate::combinators::EitherOrBoth;
+    use crate::data::unique_entity::{Confirmed, Predicted, StateUpdate, Traced, Unconfirmed};
+    use crate::data::OnChainEntity;
+
+    #[tokio::test]
+    async fn state_of_evicted_tx_is_not_resolved() {
+        let token_id = force_any_val();
+        let old_state = ErgoEntity {
+            token_id,
+            box_id: force_any_val(),
+        };
+        let new_state = ErgoEntity {
+            token_id,
+            box_id: force_any_val(),
+        };
+        let mut repo = InMemoryEntityRepo::new();
+        repo.put_confirmed(Confirmed(old_state.clone())).await;
+        repo.put_predicted(Traced {
+            state: Predicted(new_state.clone()),
+            prev_state_id: Some(old_state.get_self_state_ref()),
+        })
+        .await;
+        let repo = Arc::new(Mutex::new(repo));
+        let updates = vec![
+            Unconfirmed(StateUpdate::Transition(EitherOrBoth::Both(
+                old_state.clone(),
+                new_state.clone(),
+            ))),
+            // Tx is evicted from mempool.
+            Unconfirmed(StateUpdate::TransitionRollback(EitherOrBoth::Both(
+                new_state.clone(),
+                old_state.clone(),
+            ))),
+        ];
+        unconfirmed_entity_tracking_stream(stream::iter(updates), Arc::clone(&repo))
+            .collect::<Vec<_>>()
+            .await;
+        let resolved = resolve_entity_state::<ErgoEntity, _>(token_id, repo).await;
+        assert_eq!(resolved, Some(old_state));
+    }
+
+    #[tokio::test]
+    async fn confirmed_state_resolves_after_eviction() {
+        let token_id = force_any_val();
+        let state = |box_id| ErgoEntity { token_id, box_id };
+        let confirmed_state = state(force_any_val());
+        let evicted_state = state(force_any_val());
+        let next_state = state(force_any_val());
+        let mut repo = InMemoryEntityRepo::new();
+        repo.put_confirmed(Confirmed(confirmed_state.clone())).await;
+        let repo = Arc::new(Mutex::new(repo));
+        let updates = vec![
+            Unconfirmed(StateUpdate::Transition(EitherOrBoth::Both(
+                confirmed_state.clone(),
+                evicted_state.clone(),
+            ))),
+            Unconfirmed(StateUpdate::TransitionRollback(EitherOrBoth::Both(
+                evicted_state,
+                confirmed_state.clone(),
+            ))),
+        ];
+        unconfirmed_entity_tracking_stream(stream::iter(updates), Arc::clone(&repo))
+            .collect::<Vec<_>>()
+            .await;
+        let resolved = resolve_entity_state::<ErgoEntity, _>(token_id, Arc::clone(&repo)).await;
+        assert_eq!(resolved, Some(confirmed_state.clone()));
+
+        repo.lock()
+            .await
+            .put_predicted(Traced {
+                state: Predicted(next_state.clone()),
+                prev_state_id: Some(confirmed_state.get_self_state_ref()),
+            })
+            .await;
+        let resolved = resolve_entity_state::<ErgoEntity, _>(token_id, repo).await;
+        assert_eq!(resolved, Some(next_state));
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_state_is_superseded_by_confirmed_one() {
+        let token_id = force_any_val();
+        let state = |box_id| ErgoEntity { token_id, box_id };
+        let initial_state = state(force_any_val());
+        let unconfirmed_state = state(force_any_val());
+        let confirmed_state = state(force_any_val());
+        let mut repo = InMemoryEntityRepo::new();
+        repo.put_confirmed(Confirmed(initial_state.clone())).await;
+        let repo = Arc::new(Mutex::new(repo));
+        unconfirmed_entity_tracking_stream(
+            stream::iter(vec![Unconfirmed(StateUpdate::Transition(EitherOrBoth::Both(
+                initial_state.clone(),
+                unconfirmed_state,
+            )))]),
+            Arc::clone(&repo),
+        )
+        .collect::<Vec<_>>()
+        .await;
+        // Conflicting tx is confirmed instead.
+        entity_tracking_stream(
+            stream::iter(vec![Confirmed(StateUpdate::Transition(EitherOrBoth::Both(
+                initial_state,
+                confirmed_state.clone(),
+            )))]),
+            Arc::clone(&repo),
+        )
+        .collect::<Vec<_>>()
+        .await;
+        let resolved = resolve_entity_state::<ErgoEntity, _>(token_id, repo).await;
+        assert_eq!(resolved, Some(confirmed_state));
+    }
+}
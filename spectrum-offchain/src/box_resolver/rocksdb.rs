@@ -183,8 +183,10 @@ where
         let state_key = prefixed_key(STATE_PREFIX, &entity.get_self_state_ref());
         let state_bytes = bincode::serialize(&entity).unwrap();
         let index_key = prefixed_key(LAST_CONFIRMED_PREFIX, &entity.get_self_ref());
+        let unconfirmed_index_key = prefixed_key(LAST_UNCONFIRMED_PREFIX, &entity.get_self_ref());
         spawn_blocking(move || {
             let tx = db.transaction();
+            tx.delete(unconfirmed_index_key).unwrap();
             tx.put(state_key, state_bytes).unwrap();
             tx.put(index_key, state_id_bytes).unwrap();
             tx.commit().unwrap();
@@ -232,6 +234,36 @@ where
         .await
     }
 
+    async fn discard_predicted<'a>(&mut self, sid: <TEntity as OnChainEntity>::TStateId)
+    where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+    {
+        let db = self.db.clone();
+        let link_key = prefixed_key(PREDICTION_LINK_PREFIX, &sid);
+        spawn_blocking(move || db.delete(link_key).unwrap()).await
+    }
+
+    async fn discard_unconfirmed<'a>(
+        &mut self,
+        sid: <TEntity as OnChainEntity>::TStateId,
+        eid: <TEntity as OnChainEntity>::TEntityId,
+    ) where
+        <TEntity as OnChainEntity>::TStateId: 'a,
+        <TEntity as OnChainEntity>::TEntityId: 'a,
+    {
+        let db = self.db.clone();
+        let state_id_bytes = bincode::serialize(&sid).unwrap();
+        let index_key = prefixed_key(LAST_UNCONFIRMED_PREFIX, &eid);
+        spawn_blocking(move || {
+            let tx = db.transaction();
+            if tx.get(&index_key).unwrap() == Some(state_id_bytes) {
+                tx.delete(index_key).unwrap();
+            }
+            tx.commit().unwrap();
+        })
+        .await
+    }
+
     async fn eliminate<'a>(&mut self, entity: TEntity)
     where
         TEntity: 'a,
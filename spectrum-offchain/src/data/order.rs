@@ -25,6 +25,11 @@ impl<TOrd> From<ProgressingOrder<TOrd>> for PendingOrder<TOrd> {
 pub struct SuspendedOrder<TOrd> {
     pub order: TOrd,
     pub timestamp: i64,
+    /// Number of failed execution attempts.
+    pub attempts: u32,
+    pub last_error: String,
+    /// The order is not retried before this timestamp.
+    pub retry_at: i64,
 }
 
 #[derive(Debug, Hash, Clone, Eq, PartialEq)]
@@ -1,3 +1,4 @@
 pub mod entity;
 pub mod order;
+pub mod tx;
 pub mod types;
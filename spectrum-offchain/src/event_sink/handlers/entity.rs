@@ -128,6 +128,16 @@ pub struct UnconfirmedUpgradeHandler<TSink, TEntity, TRepo> {
     pub pd: PhantomData<TEntity>,
 }
 
+impl<TSink, TEntity, TRepo> UnconfirmedUpgradeHandler<TSink, TEntity, TRepo> {
+    pub fn new(topic: TSink, entities: Arc<Mutex<TRepo>>) -> Self {
+        Self {
+            topic,
+            entities,
+            pd: Default::default(),
+        }
+    }
+}
+
 #[async_trait(?Send)]
 impl<TSink, TEntity, TRepo> EventHandler<MempoolUpdate> for UnconfirmedUpgradeHandler<TSink, TEntity, TRepo>
 where
@@ -141,19 +151,22 @@ where
         let res = match ev {
             MempoolUpdate::TxAccepted(tx) => {
                 let transitions = extract_transitions(Arc::clone(&self.entities), tx.clone()).await;
-                let is_success = !transitions.is_empty();
+                let num_transitions = transitions.len();
+                let is_success = num_transitions > 0;
                 for tr in transitions {
                     let _ = self.topic.feed(Unconfirmed(StateUpdate::Transition(tr))).await;
                 }
                 if is_success {
-                    Some(MempoolUpdate::TxAccepted(tx))
-                } else {
+                    trace!(target: "offchain_lm", "[{}] entities parsed from accepted tx", num_transitions);
                     None
+                } else {
+                    Some(MempoolUpdate::TxAccepted(tx))
                 }
             }
             MempoolUpdate::TxWithdrawn(tx) => {
                 let transitions = extract_transitions(Arc::clone(&self.entities), tx.clone()).await;
-                let is_success = !transitions.is_empty();
+                let num_transitions = transitions.len();
+                let is_success = num_transitions > 0;
                 for tr in transitions {
                     let _ = self
                         .topic
@@ -161,12 +174,33 @@ where
                         .await;
                 }
                 if is_success {
-                    Some(MempoolUpdate::TxWithdrawn(tx))
+                    trace!(target: "offchain_lm", "[{}] entities parsed from withdrawn tx", num_transitions);
+                    None
                 } else {
+                    Some(MempoolUpdate::TxWithdrawn(tx))
+                }
+            }
+            MempoolUpdate::TxConfirmed(tx) => {
+                let transitions = extract_transitions(Arc::clone(&self.entities), tx.clone()).await;
+                let num_transitions = transitions.len();
+                let is_success = num_transitions > 0;
+                // States created by the tx are tracked as confirmed ones by now,
+                // while the consumed ones are spent for good.
+                for tr in transitions {
+                    if let EitherOrBoth::Left(st) | EitherOrBoth::Both(st, _) = tr {
+                        let _ = self
+                            .topic
+                            .feed(Unconfirmed(StateUpdate::Transition(EitherOrBoth::Left(st))))
+                            .await;
+                    }
+                }
+                if is_success {
+                    trace!(target: "offchain_lm", "[{}] entities parsed from confirmed tx", num_transitions);
                     None
+                } else {
+                    Some(MempoolUpdate::TxConfirmed(tx))
                 }
             }
-            ev => Some(ev),
         };
         let _ = self.topic.flush().await;
         res
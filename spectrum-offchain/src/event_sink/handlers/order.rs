@@ -110,8 +110,9 @@ where
 impl<TSink, TOrd, TOrdProto, TBacklog> EventHandler<MempoolUpdate>
     for OrderUpdatesHandler<TSink, TOrd, TOrdProto, TBacklog>
 where
-    TSink: Sink<OrderUpdate<TOrd, TOrd::TOrderId>> + Unpin,
-    TOrd: OnChainOrder + TryFromBox,
+    TSink: Sink<OrderUpdate<TOrdProto, TOrd::TOrderId>> + Unpin,
+    TOrd: OnChainOrder,
+    TOrdProto: Has<TOrd::TOrderId> + TryFromBox,
     TOrd::TOrderId: From<BoxId> + Copy,
     TBacklog: Backlog<TOrd>,
 {
@@ -127,7 +128,7 @@ where
                     }
                 }
                 for bx in &tx.outputs {
-                    if let Some(order) = TOrd::try_from_box(bx.clone()) {
+                    if let Some(order) = TOrdProto::try_from_box(bx.clone()) {
                         is_success = true;
                         let _ = self
                             .topic
@@ -147,11 +148,13 @@ where
             MempoolUpdate::TxWithdrawn(tx) => {
                 let mut is_success = false;
                 for bx in &tx.outputs {
-                    if let Some(order) = TOrd::try_from_box(bx.clone()) {
+                    if let Some(order) = TOrdProto::try_from_box(bx.clone()) {
                         is_success = true;
                         let _ = self
                             .topic
-                            .feed(OrderUpdate::OrderEliminated(order.get_self_ref()))
+                            .feed(OrderUpdate::OrderEliminated(
+                                <TOrdProto as Has<TOrd::TOrderId>>::get::<TOrd::TOrderId>(&order),
+                            ))
                             .await;
                     }
                 }
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use ergo_mempool_sync::MempoolUpdate;
+
+use crate::event_sink::types::EventHandler;
+use crate::event_source::data::LedgerTxEvent;
+use crate::tx_tracker::{TxStatus, TxTracker};
+
+/// Feeds observed statuses of transactions to `TxTracker`.
+pub struct TxTrackerHandler<TTracker> {
+    pub tracker: Arc<Mutex<TTracker>>,
+}
+
+impl<TTracker> TxTrackerHandler<TTracker> {
+    pub fn new(tracker: Arc<Mutex<TTracker>>) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait(?Send)]
+impl<TTracker> EventHandler<LedgerTxEvent> for TxTrackerHandler<TTracker>
+where
+    TTracker: TxTracker,
+{
+    async fn try_handle(&mut self, ev: LedgerTxEvent) -> Option<LedgerTxEvent> {
+        let mut tracker = self.tracker.lock().await;
+        let is_success = match &ev {
+            LedgerTxEvent::AppliedTx { tx, .. } => tracker.observe(tx.id(), TxStatus::Confirmed),
+            LedgerTxEvent::UnappliedTx(tx) => {
+                // Tx was rolled back along with its block and has to be mined again.
+                let is_known = tracker.status(tx.id()) == Some(TxStatus::Confirmed);
+                if is_known {
+                    tracker.track(tx.clone());
+                }
+                is_known
+            }
+        };
+        if is_success {
+            return None;
+        }
+        Some(ev)
+    }
+}
+
+#[async_trait(?Send)]
+impl<TTracker> EventHandler<MempoolUpdate> for TxTrackerHandler<TTracker>
+where
+    TTracker: TxTracker,
+{
+    async fn try_handle(&mut self, ev: MempoolUpdate) -> Option<MempoolUpdate> {
+        let mut tracker = self.tracker.lock().await;
+        let is_success = match &ev {
+            MempoolUpdate::TxAccepted(tx) => tracker.observe(tx.id(), TxStatus::InMempool),
+            MempoolUpdate::TxWithdrawn(tx) => tracker.observe(tx.id(), TxStatus::Withdrawn),
+            // Confirmation is observed in the ledger.
+            MempoolUpdate::TxConfirmed(_) => false,
+        };
+        if is_success {
+            return None;
+        }
+        Some(ev)
+    }
+}
@@ -1,10 +1,11 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
 use std::sync::{Arc, Once};
 use std::time::Duration;
 
 use async_trait::async_trait;
-use futures::{stream, Stream};
+use futures::{future, stream, Stream};
 use futures_timer::Delay;
 use log::{trace, warn};
 use tokio::sync::Mutex;
@@ -57,6 +58,13 @@ pub trait Executor {
     async fn try_execute_next(&mut self) -> Result<(), ()>;
 }
 
+#[async_trait(?Send)]
+pub trait ExecuteOrder<TOrd> {
+    /// Execute the given order.
+    /// Drives execution to completion (submit tx or handle error).
+    async fn execute(&self, ord: TOrd) -> Result<(), ()>;
+}
+
 /// A generic executor suitable for cases when single order is applied to a signle entity (pool).
 pub struct OrderExecutor<TNetwork, TBacklog, TEntities, TCtx, TOrd, TEntity> {
     network: TNetwork,
@@ -92,10 +100,16 @@ where
                         let mut entity_repo = self.entity_repo.lock().await;
                         if let Err(err) = self.network.submit_tx(tx.into_tx_without_proofs()).await {
                             warn!("Execution failed while submitting tx due to {}", err);
-                            entity_repo
-                                .invalidate(entity.get_self_state_ref(), entity.get_self_ref())
-                                .await;
-                            self.backlog.recharge(ord).await; // Return order to backlog
+                            if err.is_transient() {
+                                self.backlog.recharge(ord).await; // Return order to backlog
+                            } else if err.is_stale_inputs() {
+                                entity_repo
+                                    .invalidate(entity.get_self_state_ref(), entity.get_self_ref())
+                                    .await;
+                                self.backlog.recharge(ord).await; // Return order to backlog
+                            } else {
+                                self.backlog.suspend(ord, err.to_string()).await;
+                            }
                         } else {
                             entity_repo
                                 .put_predicted(Traced {
@@ -107,7 +121,7 @@ where
                     }
                     Err(RunOrderError::NonFatal(err, ord)) => {
                         warn!("Order suspended due to non-fatal error {}", err);
-                        self.backlog.suspend(ord).await;
+                        self.backlog.suspend(ord, err).await;
                     }
                     Err(RunOrderError::Fatal(err, ord)) => {
                         warn!("Order dropped due to fatal error {}", err);
@@ -147,6 +161,81 @@ pub fn executor_stream<'a, TExecutor: Executor + 'a>(
     })
 }
 
+/// Construct Executor stream that executes orders of distinct entities (e.g. pools) concurrently.
+/// Orders of the same entity are still executed one by one, so that each of them
+/// is run against the state predicted by the previous one.
+pub fn parallel_executor_stream<'a, TOrd, TBacklog, TExecutor>(
+    executor: TExecutor,
+    backlog: Arc<Mutex<TBacklog>>,
+    max_concurrency: usize,
+    tip_reached_signal: &'a Once,
+) -> impl Stream<Item = ()> + 'a
+where
+    TOrd: OnChainOrder + 'a,
+    TBacklog: Backlog<TOrd> + 'a,
+    TExecutor: ExecuteOrder<TOrd> + 'a,
+{
+    let executor = Arc::new(executor);
+    stream::unfold(VecDeque::new(), move |deferred| {
+        let executor = Arc::clone(&executor);
+        let backlog = Arc::clone(&backlog);
+        async move {
+            let deferred = if tip_reached_signal.is_completed() {
+                let (batch, deferred) = next_batch(&backlog, deferred, max_concurrency).await;
+                trace!(target: "offchain_lm", "Executing {} orders concurrently ..", batch.len());
+                let results = future::join_all(batch.into_iter().map(|ord| executor.execute(ord))).await;
+                if results.iter().all(|res| res.is_err()) {
+                    trace!(target: "offchain_lm", "Execution attempts failed, throttling ..");
+                    Delay::new(Duration::from_secs(THROTTLE_SECS)).await;
+                }
+                deferred
+            } else {
+                Delay::new(Duration::from_secs(THROTTLE_SECS)).await;
+                deferred
+            };
+            Some(((), deferred))
+        }
+    })
+}
+
+/// Select at most one order per entity to execute next.
+/// Orders of entities which are already busy are deferred to the next round in the order they were popped.
+async fn next_batch<TOrd, TBacklog>(
+    backlog: &Mutex<TBacklog>,
+    deferred: VecDeque<TOrd>,
+    max_concurrency: usize,
+) -> (Vec<TOrd>, VecDeque<TOrd>)
+where
+    TOrd: OnChainOrder,
+    TBacklog: Backlog<TOrd>,
+{
+    let mut busy_entities = HashSet::new();
+    let mut batch = Vec::new();
+    let mut still_deferred = VecDeque::new();
+    for ord in deferred {
+        if batch.len() < max_concurrency && busy_entities.insert(ord.get_entity_ref()) {
+            batch.push(ord);
+        } else {
+            still_deferred.push_back(ord);
+        }
+    }
+    let mut backlog = backlog.lock().await;
+    // Number of orders held outside of the backlog is bounded.
+    while batch.len() < max_concurrency && still_deferred.len() < max_concurrency {
+        match backlog.try_pop().await {
+            Some(ord) => {
+                if busy_entities.insert(ord.get_entity_ref()) {
+                    batch.push(ord);
+                } else {
+                    still_deferred.push_back(ord);
+                }
+            }
+            None => break,
+        }
+    }
+    (batch, still_deferred)
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum OrderType {
     Deposit,
@@ -154,15 +243,26 @@ pub enum OrderType {
     Redeem,
 }
 
+/// An entity consumed by an order which turned out to be stale.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Invalidation {
     Pool,
+    /// Positions of stale bundles among the bundles consumed by the order.
     StakingBundles(Vec<usize>),
     Order,
-    Funding,
+    /// Positions of stale boxes among the funding boxes consumed by the order.
+    Funding(Vec<usize>),
 }
 
+/// Map inputs the node reported missing to the entities they belong to.
+/// Input layouts are:
+///   Deposit:  [pool, order]
+///   Redeem:   [pool, bundle, order]
+///   Compound: [pool, funding_0, .., funding_{k-1}, bundle_0, ..]
+/// where `k` is `num_funding_inputs`.
 pub fn generate_invalidations(
     order_type: OrderType,
+    num_funding_inputs: usize,
     missing_indices: Vec<MissingIndex>,
 ) -> Vec<Invalidation> {
     let mut res = vec![];
@@ -178,24 +278,26 @@ pub fn generate_invalidations(
             }
         }
         OrderType::Compound => {
-            if missing_indices.contains(&1) {
-                res.push(Invalidation::Funding);
-            }
-
-            for ix in missing_indices {
-                // Staking bundles start at index 2 of inputs.
-                let mut bundles_to_invalidate = vec![];
-                if ix > 1 {
-                    bundles_to_invalidate.push(ix as usize - 2);
-                }
-                if !bundles_to_invalidate.is_empty() {
-                    res.push(Invalidation::StakingBundles(bundles_to_invalidate));
+            let mut funding_to_invalidate = vec![];
+            let mut bundles_to_invalidate = vec![];
+            for ix in missing_indices.into_iter().filter(|ix| *ix > 0) {
+                let ix = ix as usize - 1;
+                if ix < num_funding_inputs {
+                    funding_to_invalidate.push(ix);
+                } else {
+                    bundles_to_invalidate.push(ix - num_funding_inputs);
                 }
             }
+            if !funding_to_invalidate.is_empty() {
+                res.push(Invalidation::Funding(funding_to_invalidate));
+            }
+            if !bundles_to_invalidate.is_empty() {
+                res.push(Invalidation::StakingBundles(bundles_to_invalidate));
+            }
         }
         OrderType::Redeem => {
             if missing_indices.contains(&1) {
-                res.push(Invalidation::StakingBundles(vec![1]));
+                res.push(Invalidation::StakingBundles(vec![0]));
             }
 
             if missing_indices.contains(&2) {
@@ -208,44 +310,138 @@ pub fn generate_invalidations(
 
 pub type MissingIndex = i32;
 
-pub fn parse_err(err: &str) -> NodeSubmitTxError {
-    // Such an error can appear for example as:
-    // [Malformed transaction: Every input of the transaction should be in UTXO... Missing inputs: 0, 1, 2]
-    let error_description = "Missing inputs: ";
-    if err.contains(error_description) {
-        let s = err.split(error_description).last().unwrap();
-        trace!(target: "offchain_lm", "parse_err: Missing inputs {}", s);
-        return NodeSubmitTxError::MissingInputs(
-            s[..s.len() - 1] // Don't consider the trailing ']' character
-                .split(',')
-                .map(|s| s.trim().parse::<i32>().unwrap())
-                .collect(),
-        );
-    } else if err.contains("Double spending attempt") {
-        return NodeSubmitTxError::DoubleSpend;
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use bounded_integer::BoundedU8;
+    use chrono::{Duration, Utc};
+    use tokio::sync::Mutex;
+
+    use crate::backlog::data::{OrderWeight, Weighted};
+    use crate::backlog::persistence::InMemoryBacklogStore;
+    use crate::backlog::{Backlog, BacklogConfig, BacklogService, PrioritizationPolicy};
+    use crate::data::order::PendingOrder;
+    use crate::data::OnChainOrder;
+
+    use super::{generate_invalidations, next_batch, Invalidation, OrderType};
+
+    #[derive(Debug, Eq, PartialEq, Hash, Clone)]
+    struct MockOrder {
+        order_id: i64,
+        pool_id: i64,
+        weight: u64,
     }
 
-    NodeSubmitTxError::Unhandled
-}
+    impl OnChainOrder for MockOrder {
+        type TOrderId = i64;
+        type TEntityId = i64;
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum NodeSubmitTxError {
-    MissingInputs(Vec<MissingIndex>),
-    DoubleSpend,
-    Unhandled,
-}
+        fn get_self_ref(&self) -> Self::TOrderId {
+            self.order_id
+        }
 
-#[cfg(test)]
-mod tests {
-    use crate::executor::NodeSubmitTxError;
+        fn get_entity_ref(&self) -> Self::TEntityId {
+            self.pool_id
+        }
+    }
+
+    impl Weighted for MockOrder {
+        fn weight(&self) -> OrderWeight {
+            OrderWeight::from(self.weight)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_select_one_order_per_pool() {
+        let conf = BacklogConfig {
+            order_lifespan: Duration::seconds(100),
+            order_exec_time: Duration::seconds(50),
+            retry_suspended_prob: <BoundedU8<0, 100>>::new(0).unwrap(),
+            retry_backoff_base: Duration::zero(),
+            retry_backoff_cap: Duration::zero(),
+            max_attempts: 3,
+            prioritization: PrioritizationPolicy::FeeBased,
+        };
+        let mut backlog = BacklogService::new::<MockOrder>(InMemoryBacklogStore::new(), conf).await;
+        let orders = vec![
+            MockOrder {
+                order_id: 1,
+                pool_id: 1,
+                weight: 4,
+            },
+            MockOrder {
+                order_id: 2,
+                pool_id: 1,
+                weight: 3,
+            },
+            MockOrder {
+                order_id: 3,
+                pool_id: 2,
+                weight: 2,
+            },
+            MockOrder {
+                order_id: 4,
+                pool_id: 3,
+                weight: 1,
+            },
+        ];
+        for order in orders.clone() {
+            backlog
+                .put(PendingOrder {
+                    order,
+                    timestamp: Utc::now().timestamp(),
+                })
+                .await;
+        }
+        let backlog = Mutex::new(backlog);
 
-    use super::parse_err;
+        let (batch, deferred) = next_batch(&backlog, VecDeque::new(), 2).await;
+        assert_eq!(batch, vec![orders[0].clone(), orders[2].clone()]);
+        assert_eq!(deferred, VecDeque::from(vec![orders[1].clone()]));
+
+        let (batch, deferred) = next_batch(&backlog, deferred, 2).await;
+        assert_eq!(batch, vec![orders[1].clone(), orders[3].clone()]);
+        assert!(deferred.is_empty());
+    }
+
+    #[test]
+    fn deposit_invalidations() {
+        assert_eq!(
+            generate_invalidations(OrderType::Deposit, 0, vec![0]),
+            vec![Invalidation::Pool]
+        );
+        assert_eq!(
+            generate_invalidations(OrderType::Deposit, 0, vec![1]),
+            vec![Invalidation::Order]
+        );
+    }
 
     #[test]
-    fn test_missing_indices() {
+    fn redeem_invalidations() {
+        assert_eq!(
+            generate_invalidations(OrderType::Redeem, 0, vec![1]),
+            vec![Invalidation::StakingBundles(vec![0])]
+        );
+        assert_eq!(
+            generate_invalidations(OrderType::Redeem, 0, vec![0, 2]),
+            vec![Invalidation::Pool, Invalidation::Order]
+        );
+    }
+
+    #[test]
+    fn compound_invalidations() {
+        // [pool, funding_0, funding_1, bundle_0, bundle_1, bundle_2]
+        assert_eq!(
+            generate_invalidations(OrderType::Compound, 2, vec![2, 3, 5]),
+            vec![
+                Invalidation::Funding(vec![1]),
+                Invalidation::StakingBundles(vec![0, 2])
+            ]
+        );
         assert_eq!(
-            parse_err("Missing inputs: 3, 4, 6]"),
-            NodeSubmitTxError::MissingInputs(vec![3_i32, 4, 6])
+            generate_invalidations(OrderType::Compound, 2, vec![0, 1]),
+            vec![Invalidation::Pool, Invalidation::Funding(vec![0])]
         );
     }
 }
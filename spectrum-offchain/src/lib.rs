@@ -8,5 +8,7 @@ pub mod event_sink;
 pub mod event_source;
 pub mod executor;
 pub mod network;
+pub mod state_context;
 pub mod streaming;
 pub mod transaction;
+pub mod tx_tracker;
@@ -1,17 +1,120 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
 use async_trait::async_trait;
+use chrono::Utc;
 use derive_more::Display;
-use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergo_chain_types::{Digest32, Header, PreHeader};
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
 use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_lib::wallet::signing::TransactionContext;
 use isahc::AsyncReadResponseExt;
 use isahc::Request;
+use log::{info, warn};
+use parking_lot::Mutex;
+use prometheus::{IntCounterVec, Opts, Registry};
 use serde::{Deserialize, Serialize};
 
 use ergo_chain_sync::client::node::ErgoNodeHttpClient;
 use ergo_chain_sync::client::types::with_path;
 
+use crate::executor::MissingIndex;
+
+pub mod explorer;
+pub mod failover;
+pub mod simulator;
+pub mod source;
+
 #[derive(Debug, Display)]
 pub struct ClientError(pub String);
 
+/// Reason for the node to reject a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum NodeSubmitTxError {
+    /// Some inputs are not in the UTXO set.
+    #[display(fmt = "Missing inputs: {:?}", _0)]
+    MissingInputs(Vec<MissingIndex>),
+    /// Some inputs are already spent by a mempool tx.
+    #[display(fmt = "Double spending attempt")]
+    DoubleSpend,
+    /// Script of some input failed or reduced to `false`.
+    #[display(fmt = "Script failure: {}", _0)]
+    ScriptFailure(String),
+    /// Validation cost of the tx exceeds the limit.
+    #[display(fmt = "Cost exceeded")]
+    CostExceeded,
+    /// Fee is below the minimal one accepted by the node.
+    #[display(fmt = "Low fee")]
+    LowFee,
+    /// Some output holds less than the minimal box value.
+    #[display(fmt = "Dust output")]
+    DustOutput,
+    /// Mempool is full and the tx can't replace any of the txs in it.
+    #[display(fmt = "Mempool full")]
+    MempoolFull,
+    /// Node can't be reached or failed to process the request.
+    #[display(fmt = "Node unavailable: {}", _0)]
+    NodeUnavailable(String),
+    /// Rejected for any other reason.
+    #[display(fmt = "Unhandled: {}", _0)]
+    Unhandled(String),
+}
+
+impl NodeSubmitTxError {
+    /// The same tx may be accepted later, e.g. once the node is back.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            NodeSubmitTxError::NodeUnavailable(_) | NodeSubmitTxError::MempoolFull
+        )
+    }
+
+    /// Some inputs of the tx are stale, so the entities they represent should be invalidated.
+    pub fn is_stale_inputs(&self) -> bool {
+        matches!(
+            self,
+            NodeSubmitTxError::MissingInputs(_) | NodeSubmitTxError::DoubleSpend
+        )
+    }
+
+    /// Classify the error reported by the node.
+    pub fn from_node_error(err: &str) -> Self {
+        // Such an error can appear for example as:
+        // [Malformed transaction: Every input of the transaction should be in UTXO... Missing inputs: 0, 1, 2]
+        let missing_inputs = "Missing inputs: ";
+        let err_lc = err.to_lowercase();
+        if let Some((_, indices)) = err.split_once(missing_inputs) {
+            let indices = indices
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == ',' || c.is_whitespace())
+                .collect::<String>();
+            NodeSubmitTxError::MissingInputs(
+                indices
+                    .split(',')
+                    .filter_map(|ix| ix.trim().parse::<MissingIndex>().ok())
+                    .collect(),
+            )
+        } else if err_lc.contains("double spending") {
+            NodeSubmitTxError::DoubleSpend
+        } else if err_lc.contains("cost") && (err_lc.contains("exceed") || err_lc.contains("limit")) {
+            NodeSubmitTxError::CostExceeded
+        } else if err_lc.contains("script") || err_lc.contains("reduced to false") {
+            NodeSubmitTxError::ScriptFailure(err.to_string())
+        } else if err_lc.contains("min fee") || err_lc.contains("fee not met") {
+            NodeSubmitTxError::LowFee
+        } else if err_lc.contains("dust") || err_lc.contains("minimal erg amount") {
+            NodeSubmitTxError::DustOutput
+        } else if err_lc.contains("pool is full") || err_lc.contains("pool can not accept") {
+            NodeSubmitTxError::MempoolFull
+        } else {
+            NodeSubmitTxError::Unhandled(err.to_string())
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NodeError {
     error: u16,
@@ -22,12 +125,44 @@ pub struct NodeError {
 #[async_trait]
 pub trait ErgoNetwork {
     /// Submit the given `Transaction` to Ergo network.
-    async fn submit_tx(&self, tx: Transaction) -> Result<(), ClientError>;
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError>;
     async fn get_height(&self) -> u32;
     async fn get_token_minting_info(
         &self,
         token_id: TokenId,
     ) -> Result<Option<TokenMintingInfo>, ClientError>;
+    /// State context of the next block to evaluate scripts against.
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError>;
+    /// Unspent box with the given id, outputs of mempool txs included where the source tracks them.
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError>;
+    /// Check if the tx with the given id is pending in mempool.
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError>;
+}
+
+/// Number of last headers available to scripts.
+pub const NUM_LAST_HEADERS: usize = 10;
+
+/// Make the state context of the block following the most recent of the given `headers`.
+/// Missing headers are filled with the oldest available one.
+pub fn make_state_context(mut headers: Vec<Header>, timestamp: u64) -> Option<ErgoStateContext> {
+    headers.sort_by(|a, b| b.height.cmp(&a.height));
+    headers.truncate(NUM_LAST_HEADERS);
+    let last_header = headers.first()?.clone();
+    let oldest_header = headers.last()?.clone();
+    headers.resize(NUM_LAST_HEADERS, oldest_header);
+    let pre_header = PreHeader {
+        version: last_header.version,
+        parent_id: last_header.id,
+        timestamp,
+        n_bits: last_header.n_bits,
+        height: last_header.height + 1,
+        miner_pk: last_header.autolykos_solution.miner_pk.clone(),
+        votes: last_header.votes.clone(),
+    };
+    Some(ErgoStateContext {
+        pre_header,
+        headers: headers.try_into().ok()?,
+    })
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -46,25 +181,30 @@ const GENESIS_HEIGHT: u32 = 0;
 
 #[async_trait]
 impl ErgoNetwork for ErgoNodeHttpClient {
-    async fn submit_tx(&self, tx: Transaction) -> Result<(), ClientError> {
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
         println!("{}", serde_json::to_string(&tx).unwrap());
         let req = Request::post(with_path(&self.base_url, "/transactions"))
             .header("Content-Type", "application/json")
             .body(serde_json::to_vec(&tx).unwrap())
             .unwrap();
-        let mut res = self
-            .client
-            .send_async(req)
-            .await
-            .map_err(|e| ClientError(format!("ErgoNetwork::submit_tx: {:?}", e)))?;
-        if res.status().is_client_error() {
+        let mut res =
+            self.client.send_async(req).await.map_err(|e| {
+                NodeSubmitTxError::NodeUnavailable(format!("ErgoNetwork::submit_tx: {:?}", e))
+            })?;
+        let status_code = res.status();
+        if status_code.is_client_error() {
             let details = res
                 .json::<NodeError>()
                 .await
                 .ok()
                 .map(|ne| format!("[{}] [{}] [{}]", ne.error, ne.reason, ne.detail))
                 .unwrap_or("<unknown>".to_string());
-            Err(ClientError(format!("Malformed tx. {}", details)))
+            Err(NodeSubmitTxError::from_node_error(&details))
+        } else if status_code.is_server_error() {
+            Err(NodeSubmitTxError::NodeUnavailable(format!(
+                "expected 200 from /transactions, got {:?}",
+                status_code
+            )))
         } else {
             Ok(())
         }
@@ -116,6 +256,299 @@ impl ErgoNetwork for ErgoNodeHttpClient {
             Err(ClientError("No response from ergo node".into()))
         }
     }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        let mut resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!("/blocks/lastHeaders/{}", NUM_LAST_HEADERS),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from ergo node".into()))?;
+        let status_code = resp.status();
+        if !status_code.is_success() {
+            return Err(ClientError(format!(
+                "expected 200 from /blocks/lastHeaders/_, got {:?}",
+                status_code
+            )));
+        }
+        let headers = resp
+            .json::<Vec<Header>>()
+            .await
+            .map_err(|e| ClientError(format!("Failed to parse headers: {}", e)))?;
+        make_state_context(headers, Utc::now().timestamp_millis() as u64)
+            .ok_or(ClientError("No headers returned by ergo node".into()))
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        let mut resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!("/utxo/withPool/byId/{}", Digest32::from(box_id)),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from ergo node".into()))?;
+        let status_code = resp.status();
+        if status_code.is_success() {
+            resp.json::<ErgoBox>()
+                .await
+                .map(Some)
+                .map_err(|e| ClientError(format!("Failed to parse box: {}", e)))
+        } else if status_code.as_u16() == 404 {
+            Ok(None)
+        } else {
+            Err(ClientError(format!(
+                "expected 200 from /utxo/withPool/byId/_, got {:?}",
+                status_code
+            )))
+        }
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        let resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!(
+                    "/transactions/unconfirmed/byTransactionId/{}",
+                    String::from(tx_id)
+                ),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from ergo node".into()))?;
+        let status_code = resp.status();
+        if status_code.is_success() {
+            Ok(true)
+        } else if status_code.as_u16() == 404 {
+            Ok(false)
+        } else {
+            Err(ClientError(format!(
+                "expected 200 from /transactions/unconfirmed/byTransactionId/_, got {:?}",
+                status_code
+            )))
+        }
+    }
+}
+
+/// Counts rejected transactions by the kind of `NodeSubmitTxError`.
+pub struct ErgoNetworkMetrics<R> {
+    inner: R,
+    submit_errors: IntCounterVec,
+}
+
+impl<R> ErgoNetworkMetrics<R> {
+    pub fn wrap(network: R, registry: &Registry) -> Self {
+        let submit_errors = IntCounterVec::new(
+            Opts::new("node_submit_tx_errors_total", "Transactions rejected by the node"),
+            &["kind"],
+        )
+        .unwrap();
+        registry.register(Box::new(submit_errors.clone())).unwrap();
+        Self {
+            inner: network,
+            submit_errors,
+        }
+    }
+}
+
+#[async_trait]
+impl<R> ErgoNetwork for ErgoNetworkMetrics<R>
+where
+    R: ErgoNetwork + Send + Sync,
+{
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        let res = self.inner.submit_tx(tx).await;
+        if let Err(err) = &res {
+            let kind = match err {
+                NodeSubmitTxError::MissingInputs(_) => "missing_inputs",
+                NodeSubmitTxError::DoubleSpend => "double_spend",
+                NodeSubmitTxError::ScriptFailure(_) => "script_failure",
+                NodeSubmitTxError::CostExceeded => "cost_exceeded",
+                NodeSubmitTxError::LowFee => "low_fee",
+                NodeSubmitTxError::DustOutput => "dust_output",
+                NodeSubmitTxError::MempoolFull => "mempool_full",
+                NodeSubmitTxError::NodeUnavailable(_) => "node_unavailable",
+                NodeSubmitTxError::Unhandled(_) => "unhandled",
+            };
+            self.submit_errors.with_label_values(&[kind]).inc();
+        }
+        res
+    }
+
+    async fn get_height(&self) -> u32 {
+        self.inner.get_height().await
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        self.inner.get_token_minting_info(token_id).await
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        self.inner.get_state_context().await
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        self.inner.get_utxo(box_id).await
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        self.inner.is_in_mempool(tx_id).await
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DryRunConfig {
+    /// Directory to export JSON of txs to, txs are only logged otherwise.
+    pub export_dir: Option<PathBuf>,
+}
+
+/// Outcome of local validation of a dry run tx, exported next to the tx itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DryRunValidation {
+    pub valid: bool,
+    pub error: Option<String>,
+}
+
+/// Never broadcasts txs when dry run is enabled, validates them locally and logs or exports them instead.
+/// Such txs are reported as submitted even if validation fails, so predictions made upon them
+/// are recorded as usual and rolled back by the tx tracker once it gives up on seeing the txs in mempool.
+pub struct ErgoNetworkDryRun<R> {
+    inner: R,
+    conf: Option<DryRunConfig>,
+    /// Outputs of dry run txs, so that txs chained upon them can be validated too.
+    outputs: Mutex<HashMap<BoxId, ErgoBox>>,
+}
+
+impl<R> ErgoNetworkDryRun<R> {
+    pub fn wrap(network: R, conf: Option<DryRunConfig>) -> Self {
+        Self {
+            inner: network,
+            conf,
+            outputs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<R> ErgoNetworkDryRun<R>
+where
+    R: ErgoNetwork + Send + Sync,
+{
+    async fn resolve_box(&self, box_id: BoxId) -> Result<ErgoBox, String> {
+        if let Some(bx) = self.outputs.lock().get(&box_id) {
+            return Ok(bx.clone());
+        }
+        match self.inner.get_utxo(box_id).await {
+            Ok(Some(bx)) => Ok(bx),
+            Ok(None) => Err(format!("Box [{:?}] is not in UTXO set", box_id)),
+            Err(err) => Err(format!("Box [{:?}] can't be resolved due to {}", box_id, err)),
+        }
+    }
+
+    /// Evaluate scripts of all inputs of the given `tx` against the current state context.
+    async fn validate(&self, tx: &Transaction) -> Result<(), String> {
+        let state_ctx = self
+            .inner
+            .get_state_context()
+            .await
+            .map_err(|err| format!("State context unavailable: {}", err))?;
+        let mut inputs = Vec::new();
+        for input in tx.inputs.iter() {
+            inputs.push(self.resolve_box(input.box_id).await?);
+        }
+        let mut data_inputs = Vec::new();
+        for input in tx.data_inputs.iter().flat_map(|d| d.iter()) {
+            data_inputs.push(self.resolve_box(input.box_id).await?);
+        }
+        let tx_ctx = TransactionContext::new(tx.clone(), inputs, data_inputs)
+            .map_err(|err| format!("Invalid tx context: {:?}", err))?;
+        tx_ctx
+            .validate(&state_ctx)
+            .map_err(|err| format!("Tx validation failed: {:?}", err))
+    }
+}
+
+#[async_trait]
+impl<R> ErgoNetwork for ErgoNetworkDryRun<R>
+where
+    R: ErgoNetwork + Send + Sync,
+{
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        match &self.conf {
+            Some(DryRunConfig { export_dir }) => {
+                let tx_id = tx.id();
+                let validation = match self.validate(&tx).await {
+                    Ok(()) => DryRunValidation {
+                        valid: true,
+                        error: None,
+                    },
+                    Err(err) => {
+                        warn!("Dry run, tx [{:?}] is invalid: {}", tx_id, err);
+                        DryRunValidation {
+                            valid: false,
+                            error: Some(err),
+                        }
+                    }
+                };
+                let tx_json = serde_json::to_string_pretty(&tx).unwrap();
+                if let Some(dir) = export_dir {
+                    let tx_id = String::from(tx_id);
+                    let path = dir.join(format!("{}.json", tx_id));
+                    if let Err(err) = fs::write(&path, &tx_json) {
+                        warn!("Failed to export tx [{}] to {:?} due to {}", tx_id, path, err);
+                    }
+                    let path = dir.join(format!("{}.validation.json", tx_id));
+                    let validation_json = serde_json::to_string_pretty(&validation).unwrap();
+                    if let Err(err) = fs::write(&path, validation_json) {
+                        warn!(
+                            "Failed to export validation of tx [{}] to {:?} due to {}",
+                            tx_id, path, err
+                        );
+                    }
+                }
+                info!(
+                    "Dry run, tx [{:?}] is not broadcasted (valid: {}): {}",
+                    tx_id, validation.valid, tx_json
+                );
+                let mut outputs = self.outputs.lock();
+                for input in tx.inputs.iter() {
+                    outputs.remove(&input.box_id);
+                }
+                for bx in tx.outputs.iter() {
+                    outputs.insert(bx.box_id(), bx.clone());
+                }
+                Ok(())
+            }
+            None => self.inner.submit_tx(tx).await,
+        }
+    }
+
+    async fn get_height(&self) -> u32 {
+        self.inner.get_height().await
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        self.inner.get_token_minting_info(token_id).await
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        self.inner.get_state_context().await
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        self.inner.get_utxo(box_id).await
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        self.inner.is_in_mempool(tx_id).await
+    }
 }
 
 #[cfg(test)]
@@ -124,7 +557,61 @@ mod tests {
     use ergo_lib::{ergo_chain_types::Digest32, ergotree_ir::chain::token::TokenId};
     use isahc::{prelude::Configurable, HttpClient};
 
-    use crate::network::ErgoNetwork;
+    use crate::network::{ErgoNetwork, NodeSubmitTxError};
+
+    #[test]
+    fn test_missing_indices() {
+        assert_eq!(
+            NodeSubmitTxError::from_node_error("Missing inputs: 3, 4, 6]"),
+            NodeSubmitTxError::MissingInputs(vec![3_i32, 4, 6])
+        );
+        assert_eq!(
+            NodeSubmitTxError::from_node_error("[400] [bad.request] [Missing inputs: 0, x]"),
+            NodeSubmitTxError::MissingInputs(vec![0])
+        );
+        assert_eq!(
+            NodeSubmitTxError::from_node_error("Missing inputs: "),
+            NodeSubmitTxError::MissingInputs(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_node_errors_classified() {
+        let cases = vec![
+            (
+                "[400] [bad.request] [Malformed transaction: Double spending attempt]",
+                NodeSubmitTxError::DoubleSpend,
+            ),
+            (
+                "[400] [bad.request] [Estimated execution cost 1200000 exceeds the limit 1000000]",
+                NodeSubmitTxError::CostExceeded,
+            ),
+            (
+                "[400] [bad.request] [Min fee not met: 0.001 ergs required, 0.0001 ergs given]",
+                NodeSubmitTxError::LowFee,
+            ),
+            (
+                "[400] [bad.request] [Transaction is trying to create dust]",
+                NodeSubmitTxError::DustOutput,
+            ),
+            (
+                "[400] [bad.request] [Pool can not accept transaction, it is invalidated earlier or the pool is full]",
+                NodeSubmitTxError::MempoolFull,
+            ),
+            (
+                "[400] [bad.request] [Something went wrong]",
+                NodeSubmitTxError::Unhandled("[400] [bad.request] [Something went wrong]".into()),
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(NodeSubmitTxError::from_node_error(err), expected);
+        }
+        let script_err = "[400] [bad.request] [Scripts of all transaction inputs should pass verification]";
+        assert_eq!(
+            NodeSubmitTxError::from_node_error(script_err),
+            NodeSubmitTxError::ScriptFailure(script_err.into())
+        );
+    }
 
     #[tokio::test]
     async fn test_token_minting_info() {
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use isahc::{AsyncReadResponseExt, Request};
+use serde::Deserialize;
+
+use ergo_chain_sync::client::explorer::ErgoExplorerHttpClient;
+use ergo_chain_sync::client::types::with_path;
+
+use crate::network::{
+    make_state_context, ClientError, ErgoNetwork, NodeSubmitTxError, TokenMintingInfo, NUM_LAST_HEADERS,
+};
+
+#[derive(Debug, Deserialize)]
+struct ExplorerError {
+    status: u16,
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplorerTokenInfo {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+const GENESIS_HEIGHT: u32 = 0;
+
+#[async_trait]
+impl ErgoNetwork for ErgoExplorerHttpClient {
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        let req = Request::post(with_path(&self.base_url, "/api/v1/mempool/transactions/submit"))
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&tx).unwrap())
+            .unwrap();
+        let mut res =
+            self.client.send_async(req).await.map_err(|e| {
+                NodeSubmitTxError::NodeUnavailable(format!("ErgoNetwork::submit_tx: {:?}", e))
+            })?;
+        let status_code = res.status();
+        if status_code.is_client_error() {
+            // Explorer relays the reason reported by its node.
+            let details = res
+                .json::<ExplorerError>()
+                .await
+                .ok()
+                .map(|ee| format!("[{}] [{}]", ee.status, ee.reason))
+                .unwrap_or("<unknown>".to_string());
+            Err(NodeSubmitTxError::from_node_error(&details))
+        } else if status_code.is_server_error() {
+            Err(NodeSubmitTxError::NodeUnavailable(format!(
+                "expected 200 from /api/v1/mempool/transactions/submit, got {:?}",
+                status_code
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn get_height(&self) -> u32 {
+        self.fetch_height().await.unwrap_or(GENESIS_HEIGHT)
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        let mut resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!("/api/v1/tokens/{}", String::from(token_id)),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from explorer".into()))?;
+        let status_code = resp.status();
+        if status_code.is_success() {
+            Ok(resp
+                .json::<ExplorerTokenInfo>()
+                .await
+                .ok()
+                .map(|info| TokenMintingInfo {
+                    name: info.name.unwrap_or_default(),
+                    description: info.description.unwrap_or_default(),
+                }))
+        } else if status_code.as_u16() == 404 {
+            Ok(None)
+        } else {
+            Err(ClientError(format!(
+                "expected 200 from /api/v1/tokens/_, got {:?}",
+                status_code
+            )))
+        }
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        let headers = self
+            .fetch_last_headers(NUM_LAST_HEADERS)
+            .await
+            .map_err(|e| ClientError(format!("Failed to get headers from explorer: {}", e)))?;
+        make_state_context(headers, Utc::now().timestamp_millis() as u64)
+            .ok_or(ClientError("No headers returned by explorer".into()))
+    }
+
+    /// Explorer only indexes confirmed boxes, so outputs of mempool txs are not found.
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        let mut resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!("/api/v1/boxes/{}", Digest32::from(box_id)),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from explorer".into()))?;
+        let status_code = resp.status();
+        if status_code.is_success() {
+            let bx = resp
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|e| ClientError(format!("Failed to parse box: {}", e)))?;
+            if !bx["spentTransactionId"].is_null() {
+                return Ok(None);
+            }
+            serde_json::from_value::<ErgoBox>(bx)
+                .map(Some)
+                .map_err(|e| ClientError(format!("Failed to parse box: {}", e)))
+        } else if status_code.as_u16() == 404 {
+            Ok(None)
+        } else {
+            Err(ClientError(format!(
+                "expected 200 from /api/v1/boxes/_, got {:?}",
+                status_code
+            )))
+        }
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        let resp = self
+            .client
+            .get_async(with_path(
+                &self.base_url,
+                &format!("/api/v0/transactions/unconfirmed/{}", String::from(tx_id)),
+            ))
+            .await
+            .map_err(|_| ClientError("No response from explorer".into()))?;
+        let status_code = resp.status();
+        if status_code.is_success() {
+            Ok(true)
+        } else if status_code.as_u16() == 404 {
+            Ok(false)
+        } else {
+            Err(ClientError(format!(
+                "expected 200 from /api/v0/transactions/unconfirmed/_, got {:?}",
+                status_code
+            )))
+        }
+    }
+}
@@ -0,0 +1,553 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergo_chain_types::BlockId;
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use futures::future::join_all;
+use log::{trace, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use ergo_chain_sync::client::model::FullBlock;
+use ergo_chain_sync::client::node::{self, ErgoNetwork as ChainSyncNetwork};
+
+use crate::network::{ClientError, ErgoNetwork, NodeSubmitTxError, TokenMintingInfo};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FailoverConfig {
+    /// Tips of the nodes are re-checked at most once per this period.
+    pub health_check_interval_secs: i64,
+    /// Number of nodes that must have the same block at a height for it to be the agreed tip.
+    pub tip_quorum: usize,
+    /// Nodes lagging behind the agreed tip by more than this many blocks are considered unhealthy.
+    pub max_tip_lag: u32,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            health_check_interval_secs: 10,
+            tip_quorum: 1,
+            max_tip_lag: 2,
+        }
+    }
+}
+
+/// Block held by at least `quorum` of the nodes, given ids of their blocks at the same height.
+pub fn agreed_block(block_ids: &[Option<BlockId>], quorum: usize) -> Option<BlockId> {
+    block_ids
+        .iter()
+        .flatten()
+        .find(|id| block_ids.iter().flatten().filter(|other| other == id).count() >= quorum.max(1))
+        .cloned()
+}
+
+struct Health {
+    /// Last known tip of each node, `None` if the node failed since the last check.
+    tips: Vec<Option<u32>>,
+    agreed_tip: Option<u32>,
+    checked_at: i64,
+}
+
+/// Spreads requests over several nodes. Reads go to the preferred healthy node and fail over
+/// to the others on errors, txs are broadcasted to all of them.
+#[derive(Clone)]
+pub struct FailoverNetwork<N> {
+    nodes: Vec<N>,
+    conf: FailoverConfig,
+    health: Arc<Mutex<Health>>,
+}
+
+impl<N> FailoverNetwork<N> {
+    /// Fails if no nodes are given.
+    pub fn new(nodes: Vec<N>, conf: FailoverConfig) -> Result<Self, String> {
+        if nodes.is_empty() {
+            return Err("At least one node is required".to_string());
+        }
+        let num_nodes = nodes.len();
+        Ok(Self {
+            nodes,
+            conf,
+            health: Arc::new(Mutex::new(Health {
+                tips: vec![None; num_nodes],
+                agreed_tip: None,
+                checked_at: 0,
+            })),
+        })
+    }
+
+    /// Take the health check upon the caller if it is due. Concurrent requests rely on
+    /// the last known health meanwhile, so that nodes aren't checked once per request.
+    fn claim_health_check(&self) -> bool {
+        let now = Utc::now().timestamp();
+        let mut health = self.health.lock();
+        if now - health.checked_at >= self.conf.health_check_interval_secs {
+            health.checked_at = now;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn observe_tips(&self, tips: Vec<Option<u32>>, agreed_tip: Option<u32>) {
+        match agreed_tip {
+            Some(tip) => {
+                for (ix, node_tip) in tips.iter().enumerate() {
+                    match node_tip {
+                        Some(node_tip) if node_tip + self.conf.max_tip_lag < tip => warn!(
+                            target: "failover",
+                            "Node #{} lags behind at {}, agreed tip is {}",
+                            ix,
+                            node_tip,
+                            tip
+                        ),
+                        None => warn!(target: "failover", "Node #{} is unavailable", ix),
+                        _ => {}
+                    }
+                }
+            }
+            None => warn!(target: "failover", "Nodes don't agree on tip, known tips: {:?}", tips),
+        }
+        let mut health = self.health.lock();
+        health.tips = tips;
+        health.agreed_tip = agreed_tip;
+    }
+
+    fn mark_failed(&self, ix: usize) {
+        trace!(target: "failover", "Node #{} failed", ix);
+        self.health.lock().tips[ix] = None;
+    }
+
+    fn agreed_tip(&self) -> Option<u32> {
+        self.health.lock().agreed_tip
+    }
+
+    /// Indices of nodes in order of preference: nodes at the agreed tip first,
+    /// then healthy lagging ones and the failed ones as the last resort. Config order is kept otherwise.
+    fn candidates(&self) -> Vec<usize> {
+        let health = self.health.lock();
+        let rank = |ix: &usize| match (health.tips[*ix], health.agreed_tip) {
+            (Some(tip), Some(agreed_tip)) if tip >= agreed_tip => 0,
+            (Some(tip), Some(agreed_tip)) if tip + self.conf.max_tip_lag >= agreed_tip => 1,
+            (Some(_), None) => 1,
+            _ => 2,
+        };
+        let mut candidates = (0..self.nodes.len()).collect::<Vec<_>>();
+        candidates.sort_by_key(rank);
+        candidates
+    }
+
+    /// Nodes txs are broadcasted to. All nodes are used if none of them is known to be healthy.
+    fn healthy(&self) -> Vec<usize> {
+        let health = self.health.lock();
+        let healthy = (0..self.nodes.len())
+            .filter(|ix| match (health.tips[*ix], health.agreed_tip) {
+                (Some(tip), Some(agreed_tip)) => tip + self.conf.max_tip_lag >= agreed_tip,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+            .collect::<Vec<_>>();
+        if healthy.is_empty() {
+            (0..self.nodes.len()).collect()
+        } else {
+            healthy
+        }
+    }
+}
+
+impl<N> FailoverNetwork<N>
+where
+    N: ChainSyncNetwork,
+{
+    async fn check_health(&self) {
+        if self.claim_health_check() {
+            let mut tips = join_all(self.nodes.iter().map(|node| node.get_best_height()))
+                .await
+                .into_iter()
+                .map(Result::ok)
+                .collect::<Vec<_>>();
+            let agreed_tip = self.agree_on_tip(&mut tips).await;
+            self.observe_tips(tips, agreed_tip);
+        }
+    }
+
+    /// Find the best height at which at least `tip_quorum` of the nodes have the same block.
+    /// Nodes having another block at the agreed height are on a fork, so they are considered failed.
+    async fn agree_on_tip(&self, tips: &mut [Option<u32>]) -> Option<u32> {
+        let mut heights = tips.iter().flatten().copied().collect::<Vec<_>>();
+        heights.sort_by(|a, b| b.cmp(a));
+        heights.dedup();
+        for height in heights {
+            let reached = (0..tips.len())
+                .filter(|ix| matches!(tips[*ix], Some(tip) if tip >= height))
+                .collect::<Vec<_>>();
+            if reached.len() < self.conf.tip_quorum {
+                continue;
+            }
+            let block_ids = join_all(reached.iter().map(|ix| self.nodes[*ix].get_block_id_at(height)))
+                .await
+                .into_iter()
+                .map(Result::ok)
+                .collect::<Vec<_>>();
+            if let Some(agreed_id) = agreed_block(&block_ids, self.conf.tip_quorum) {
+                for (ix, block_id) in reached.into_iter().zip(block_ids) {
+                    if block_id.as_ref() != Some(&agreed_id) {
+                        warn!(target: "failover", "Node #{} is off the agreed chain at {}", ix, height);
+                        tips[ix] = None;
+                    }
+                }
+                return Some(height);
+            }
+        }
+        None
+    }
+}
+
+#[async_trait(?Send)]
+impl<N> ChainSyncNetwork for FailoverNetwork<N>
+where
+    N: ChainSyncNetwork,
+{
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, node::Error> {
+        self.check_health().await;
+        // Blocks above the agreed tip aren't followed until enough nodes have them.
+        if matches!(self.agreed_tip(), Some(tip) if height > tip) {
+            return Err(node::Error::NoBlock);
+        }
+        let mut last_err = node::Error::NoBlock;
+        for ix in self.candidates() {
+            match self.nodes[ix].get_block_at(height).await {
+                Ok(blk) => return Ok(blk),
+                // The node lags behind, someone else may have the block.
+                Err(node::Error::NoBlock) => last_err = node::Error::NoBlock,
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, node::Error> {
+        self.check_health().await;
+        if matches!(self.agreed_tip(), Some(tip) if height > tip) {
+            return Err(node::Error::NoBlock);
+        }
+        let mut last_err = node::Error::NoBlock;
+        for ix in self.candidates() {
+            match self.nodes[ix].get_block_id_at(height).await {
+                Ok(id) => return Ok(id),
+                Err(node::Error::NoBlock) => last_err = node::Error::NoBlock,
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, node::Error> {
+        self.check_health().await;
+        let mut last_err = None;
+        for ix in self.candidates() {
+            match self.nodes[ix].fetch_mempool(offset, limit).await {
+                Ok(txs) => return Ok(txs),
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    async fn get_best_height(&self) -> Result<u32, node::Error> {
+        self.check_health().await;
+        self.agreed_tip().ok_or(node::Error::UnsuccessfulRequest(
+            "Nodes don't agree on tip".into(),
+        ))
+    }
+}
+
+#[async_trait]
+impl<N> ErgoNetwork for FailoverNetwork<N>
+where
+    N: ErgoNetwork + Send + Sync,
+{
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        let healthy = self.healthy();
+        let results = join_all(healthy.iter().map(|ix| self.nodes[*ix].submit_tx(tx.clone()))).await;
+        let mut errors = Vec::new();
+        for (ix, res) in healthy.into_iter().zip(results) {
+            match res {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    if let NodeSubmitTxError::NodeUnavailable(_) = err {
+                        self.mark_failed(ix);
+                    }
+                    errors.push(err);
+                }
+            }
+        }
+        // Rejection reported by a node is more informative than unavailability of another one.
+        let (transient, definitive): (Vec<_>, Vec<_>) =
+            errors.into_iter().partition(|err| err.is_transient());
+        Err(definitive.into_iter().chain(transient).next().unwrap())
+    }
+
+    async fn get_height(&self) -> u32 {
+        // Tip is agreed on by health checks of chain sync, which keeps polling nodes for new blocks.
+        if let Some(tip) = self.agreed_tip() {
+            return tip;
+        }
+        join_all(self.nodes.iter().map(|node| node.get_height()))
+            .await
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        let mut last_err = None;
+        for ix in self.candidates() {
+            match self.nodes[ix].get_token_minting_info(token_id).await {
+                Ok(info) => return Ok(info),
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        let mut last_err = None;
+        for ix in self.candidates() {
+            match self.nodes[ix].get_state_context().await {
+                Ok(ctx) => return Ok(ctx),
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        let mut last_err = None;
+        for ix in self.candidates() {
+            match self.nodes[ix].get_utxo(box_id).await {
+                Ok(bx) => return Ok(bx),
+                Err(err) => {
+                    self.mark_failed(ix);
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        // Mempools of nodes may differ, so the tx is pending as long as any of them holds it.
+        let candidates = self.candidates();
+        let results = join_all(candidates.iter().map(|ix| self.nodes[*ix].is_in_mempool(tx_id))).await;
+        let mut res = None;
+        for (ix, r) in candidates.into_iter().zip(results) {
+            match r {
+                Ok(true) => return Ok(true),
+                Ok(false) => res = Some(Ok(false)),
+                Err(err) => {
+                    self.mark_failed(ix);
+                    res = res.or(Some(Err(err)));
+                }
+            }
+        }
+        res.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use async_trait::async_trait;
+    use ergo_lib::chain::transaction::Transaction;
+    use ergo_lib::ergo_chain_types::{BlockId, Digest32};
+    use futures::future::join_all;
+
+    use ergo_chain_sync::client::model::FullBlock;
+    use ergo_chain_sync::client::node::{self, ErgoNetwork as ChainSyncNetwork};
+
+    use crate::network::failover::{agreed_block, FailoverConfig, FailoverNetwork};
+    use crate::network::simulator::NodeSimulator;
+    use crate::network::ErgoNetwork;
+
+    fn block_id(b: u8) -> BlockId {
+        BlockId(Digest32::from([b; 32]))
+    }
+
+    #[test]
+    fn block_agreed_by_quorum() {
+        let ids = vec![Some(block_id(1)), Some(block_id(2)), None, Some(block_id(2))];
+        assert!(agreed_block(&ids, 1).is_some());
+        assert_eq!(agreed_block(&ids, 2), Some(block_id(2)));
+        assert_eq!(agreed_block(&ids, 3), None);
+    }
+
+    #[tokio::test]
+    async fn nodes_on_different_forks_dont_agree() {
+        let node = NodeSimulator::new(0, 0);
+        let same_chain_node = NodeSimulator::new(0, 0);
+        let forked_node = NodeSimulator::new(0, 0);
+        for _ in 0..3 {
+            node.mine_block();
+            forked_node.mine_block();
+        }
+        // Blocks of independent simulators coincide unless they are forked.
+        for _ in 0..2 {
+            same_chain_node.mine_block();
+        }
+        forked_node.fork(1);
+        forked_node.mine_block();
+        let network = FailoverNetwork::new(
+            vec![forked_node.clone(), node.clone()],
+            FailoverConfig {
+                health_check_interval_secs: 0,
+                tip_quorum: 2,
+                max_tip_lag: 1,
+            },
+        )
+        .unwrap();
+        assert!(ChainSyncNetwork::get_best_height(&network).await.is_err());
+        let network = FailoverNetwork::new(
+            vec![forked_node, node, same_chain_node.clone()],
+            FailoverConfig {
+                health_check_interval_secs: 0,
+                tip_quorum: 2,
+                max_tip_lag: 1,
+            },
+        )
+        .unwrap();
+        // Nodes which are ahead confirm the block of the lagging one.
+        assert_eq!(ChainSyncNetwork::get_best_height(&network).await.unwrap(), 2);
+        same_chain_node.mine_block();
+        assert_eq!(ChainSyncNetwork::get_best_height(&network).await.unwrap(), 3);
+        assert_eq!(network.healthy(), vec![1, 2]);
+    }
+
+    /// Node which counts requests of its tip and answers them asynchronously.
+    struct CountingNode {
+        inner: NodeSimulator,
+        tip_requests: Rc<Cell<usize>>,
+    }
+
+    #[async_trait(?Send)]
+    impl ChainSyncNetwork for CountingNode {
+        async fn get_block_at(&self, height: u32) -> Result<FullBlock, node::Error> {
+            self.inner.get_block_at(height).await
+        }
+
+        async fn get_block_id_at(&self, height: u32) -> Result<BlockId, node::Error> {
+            self.inner.get_block_id_at(height).await
+        }
+
+        async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, node::Error> {
+            self.inner.fetch_mempool(offset, limit).await
+        }
+
+        async fn get_best_height(&self) -> Result<u32, node::Error> {
+            self.tip_requests.set(self.tip_requests.get() + 1);
+            tokio::task::yield_now().await;
+            self.inner.get_best_height().await
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_health_check() {
+        let node = NodeSimulator::new(0, 0);
+        for _ in 0..5 {
+            node.mine_block();
+        }
+        let tip_requests = Rc::new(Cell::new(0));
+        let network = FailoverNetwork::new(
+            vec![CountingNode {
+                inner: node,
+                tip_requests: Rc::clone(&tip_requests),
+            }],
+            FailoverConfig {
+                health_check_interval_secs: 60,
+                tip_quorum: 1,
+                max_tip_lag: 1,
+            },
+        )
+        .unwrap();
+        let blocks = join_all((1..=5).map(|height| network.get_block_at(height))).await;
+        assert!(blocks.iter().all(Result::is_ok));
+        assert_eq!(tip_requests.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn lagging_node_is_avoided() {
+        let lagging = NodeSimulator::new(0, 0);
+        let synced = NodeSimulator::new(0, 0);
+        let synced_too = synced.clone();
+        for _ in 0..5 {
+            synced.mine_block();
+        }
+        let network = FailoverNetwork::new(
+            vec![lagging, synced, synced_too],
+            FailoverConfig {
+                health_check_interval_secs: 0,
+                tip_quorum: 2,
+                max_tip_lag: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(ChainSyncNetwork::get_best_height(&network).await.unwrap(), 5);
+        assert_eq!(ErgoNetwork::get_height(&network).await, 5);
+        assert_eq!(network.candidates(), vec![1, 2, 0]);
+        assert_eq!(network.healthy(), vec![1, 2]);
+        let blk = network.get_block_at(3).await.unwrap();
+        assert_eq!(blk.header.height, 3);
+        assert!(matches!(network.get_block_at(6).await, Err(node::Error::NoBlock)));
+    }
+
+    #[tokio::test]
+    async fn blocks_above_agreed_tip_are_not_followed() {
+        let ahead = NodeSimulator::new(0, 0);
+        let behind = NodeSimulator::new(0, 0);
+        for _ in 0..3 {
+            ahead.mine_block();
+        }
+        behind.mine_block();
+        let network = FailoverNetwork::new(
+            vec![ahead, behind],
+            FailoverConfig {
+                health_check_interval_secs: 0,
+                tip_quorum: 2,
+                max_tip_lag: 5,
+            },
+        )
+        .unwrap();
+        assert_eq!(ChainSyncNetwork::get_best_height(&network).await.unwrap(), 1);
+        assert!(matches!(network.get_block_at(2).await, Err(node::Error::NoBlock)));
+        assert!(network.get_block_at(1).await.is_ok());
+    }
+
+    #[test]
+    fn no_nodes_is_an_error() {
+        assert!(FailoverNetwork::<NodeSimulator>::new(Vec::new(), FailoverConfig::default()).is_err());
+    }
+}
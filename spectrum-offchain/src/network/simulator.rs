@@ -0,0 +1,643 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergo_chain_types::autolykos_pow_scheme::AutolykosSolution;
+use ergo_lib::ergo_chain_types::{ec_point, ADDigest, BlockId, Digest32, Header, Votes};
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use log::trace;
+use parking_lot::Mutex;
+
+use ergo_chain_sync::client::model::{BlockTransactions, FullBlock};
+use ergo_chain_sync::client::node::{self, ErgoNetwork as ChainSyncNetwork};
+
+use crate::executor::MissingIndex;
+use crate::network::{
+    make_state_context, ClientError, ErgoNetwork, NodeSubmitTxError, TokenMintingInfo, NUM_LAST_HEADERS,
+};
+
+/// Time between two consecutive simulated blocks.
+pub const BLOCK_INTERVAL_MILLIS: u64 = 120_000;
+
+struct SimBlock {
+    id: BlockId,
+    parent_id: BlockId,
+    height: u32,
+    timestamp: u64,
+    transactions: Vec<Transaction>,
+    /// Per-transaction changes to the UTXO set (spent boxes, created boxes) needed to undo the block.
+    undo: Vec<(Vec<ErgoBox>, Vec<BoxId>)>,
+}
+
+struct SimState {
+    genesis_timestamp: u64,
+    /// Incremented on every forced fork so that replacement blocks get fresh ids.
+    generation: u32,
+    blocks: Vec<SimBlock>,
+    mempool: Vec<Transaction>,
+    utxos: HashMap<BoxId, ErgoBox>,
+    tokens: HashMap<TokenId, TokenMintingInfo>,
+}
+
+impl SimState {
+    fn tip(&self) -> &SimBlock {
+        self.blocks.last().expect("Genesis block is always present")
+    }
+
+    fn block_id(&self, height: u32) -> BlockId {
+        let mut bytes = [0u8; 32];
+        bytes[..4].copy_from_slice(&height.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.generation.to_be_bytes());
+        BlockId(Digest32::from(bytes))
+    }
+
+    /// Boxes spendable by a new mempool tx: confirmed UTXOs plus outputs of pending txs.
+    fn mempool_outputs(&self) -> HashMap<BoxId, ErgoBox> {
+        self.mempool
+            .iter()
+            .flat_map(|tx| tx.outputs.iter().cloned())
+            .map(|bx| (bx.box_id(), bx))
+            .collect()
+    }
+
+    fn mempool_spent(&self) -> HashSet<BoxId> {
+        self.mempool
+            .iter()
+            .flat_map(|tx| tx.inputs.iter().map(|i| i.box_id))
+            .collect()
+    }
+
+    fn validate(&self, tx: &Transaction) -> Result<(), NodeSubmitTxError> {
+        let pending_outputs = self.mempool_outputs();
+        let missing_inputs = tx
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| !self.utxos.contains_key(&i.box_id) && !pending_outputs.contains_key(&i.box_id))
+            .map(|(ix, _)| ix as MissingIndex)
+            .collect::<Vec<_>>();
+        if !missing_inputs.is_empty() {
+            return Err(NodeSubmitTxError::MissingInputs(missing_inputs));
+        }
+        let mut spent = self.mempool_spent();
+        if !tx.inputs.iter().all(|i| spent.insert(i.box_id)) {
+            return Err(NodeSubmitTxError::DoubleSpend);
+        }
+        Ok(())
+    }
+
+    /// Drop mempool txs which are no longer valid against the current UTXO set.
+    fn revalidate_mempool(&mut self) {
+        let pending = std::mem::take(&mut self.mempool);
+        for tx in pending {
+            if self.validate(&tx).is_ok() {
+                self.mempool.push(tx);
+            } else {
+                trace!(target: "node_simulator", "Tx [{:?}] evicted from mempool", tx.id());
+            }
+        }
+    }
+
+    fn apply_block(&mut self, transactions: Vec<Transaction>) -> BlockId {
+        let height = self.tip().height + 1;
+        let parent_id = self.tip().id;
+        let id = self.block_id(height);
+        let timestamp =
+            self.genesis_timestamp + (height - self.blocks[0].height) as u64 * BLOCK_INTERVAL_MILLIS;
+        let mut undo = Vec::with_capacity(transactions.len());
+        for tx in &transactions {
+            let spent = tx
+                .inputs
+                .iter()
+                .filter_map(|i| self.utxos.remove(&i.box_id))
+                .collect::<Vec<_>>();
+            let mut created = Vec::new();
+            for bx in tx.outputs.iter() {
+                created.push(bx.box_id());
+                self.utxos.insert(bx.box_id(), bx.clone());
+            }
+            undo.push((spent, created));
+        }
+        let applied = transactions.iter().map(|tx| tx.id()).collect::<HashSet<_>>();
+        self.mempool.retain(|tx| !applied.contains(&tx.id()));
+        trace!(
+            target: "node_simulator",
+            "Block [{:?}] at height [{}] with {} txs mined",
+            id,
+            height,
+            transactions.len()
+        );
+        self.blocks.push(SimBlock {
+            id,
+            parent_id,
+            height,
+            timestamp,
+            transactions,
+            undo,
+        });
+        self.revalidate_mempool();
+        id
+    }
+
+    fn rollback_block(&mut self) -> Vec<Transaction> {
+        let SimBlock {
+            id,
+            transactions,
+            undo,
+            ..
+        } = self.blocks.pop().expect("Genesis block is always present");
+        for (spent, created) in undo.into_iter().rev() {
+            for box_id in created {
+                self.utxos.remove(&box_id);
+            }
+            for bx in spent {
+                self.utxos.insert(bx.box_id(), bx);
+            }
+        }
+        trace!(target: "node_simulator", "Block [{:?}] rolled back", id);
+        transactions
+    }
+}
+
+/// Deterministic in-process Ergo node.
+/// Keeps a block list, a mempool and a UTXO set, and implements both
+/// `ergo_chain_sync::client::node::ErgoNetwork` and `spectrum_offchain::network::ErgoNetwork`.
+/// Blocks are produced only when explicitly mined, so a test fully controls the chain.
+#[derive(Clone)]
+pub struct NodeSimulator {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl NodeSimulator {
+    /// Create a simulator whose chain consists of a single empty genesis block at `genesis_height`.
+    pub fn new(genesis_height: u32, genesis_timestamp: u64) -> Self {
+        let mut state = SimState {
+            genesis_timestamp,
+            generation: 0,
+            blocks: Vec::new(),
+            mempool: Vec::new(),
+            utxos: HashMap::new(),
+            tokens: HashMap::new(),
+        };
+        let genesis = SimBlock {
+            id: state.block_id(genesis_height),
+            parent_id: BlockId(Digest32::zero()),
+            height: genesis_height,
+            timestamp: genesis_timestamp,
+            transactions: Vec::new(),
+            undo: Vec::new(),
+        };
+        state.blocks.push(genesis);
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Add boxes to the UTXO set without an enclosing transaction.
+    pub fn add_utxos(&self, boxes: Vec<ErgoBox>) {
+        let mut state = self.state.lock();
+        for bx in boxes {
+            state.utxos.insert(bx.box_id(), bx);
+        }
+    }
+
+    pub fn register_token(&self, token_id: TokenId, info: TokenMintingInfo) {
+        self.state.lock().tokens.insert(token_id, info);
+    }
+
+    pub fn utxo(&self, box_id: BoxId) -> Option<ErgoBox> {
+        self.state.lock().utxos.get(&box_id).cloned()
+    }
+
+    /// Snapshot of the whole UTXO set of the current best chain.
+    pub fn utxos(&self) -> Vec<ErgoBox> {
+        self.state.lock().utxos.values().cloned().collect()
+    }
+
+    pub fn mempool(&self) -> Vec<Transaction> {
+        self.state.lock().mempool.clone()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.state.lock().tip().height
+    }
+
+    pub fn block_id_at(&self, height: u32) -> Option<BlockId> {
+        let state = self.state.lock();
+        state
+            .blocks
+            .iter()
+            .find(|blk| blk.height == height)
+            .map(|blk| blk.id)
+    }
+
+    /// Transactions included into the block at the given height of the current best chain.
+    pub fn block_transactions(&self, height: u32) -> Option<Vec<TxId>> {
+        let state = self.state.lock();
+        state
+            .blocks
+            .iter()
+            .find(|blk| blk.height == height)
+            .map(|blk| blk.transactions.iter().map(|tx| tx.id()).collect())
+    }
+
+    /// Mine a block containing all transactions from the mempool.
+    pub fn mine_block(&self) -> BlockId {
+        let mut state = self.state.lock();
+        let txs = state.mempool.clone();
+        state.apply_block(txs)
+    }
+
+    /// Mine a block containing exactly the given transactions, bypassing the mempool.
+    /// Mempool transactions conflicting with the block are evicted.
+    pub fn mine_block_with(&self, transactions: Vec<Transaction>) -> BlockId {
+        self.state.lock().apply_block(transactions)
+    }
+
+    /// Remove a transaction from the mempool as if it was evicted by the node.
+    pub fn evict_tx(&self, tx_id: TxId) {
+        let mut state = self.state.lock();
+        state.mempool.retain(|tx| tx.id() != tx_id);
+        state.revalidate_mempool();
+    }
+
+    /// Force a fork by discarding `depth` best blocks.
+    /// Blocks mined afterwards get ids different from the discarded ones at the same heights.
+    /// Transactions of the discarded blocks are returned in the order they were applied
+    /// and are not put back into the mempool.
+    pub fn fork(&self, depth: u32) -> Vec<Transaction> {
+        let mut state = self.state.lock();
+        let genesis_height = state.blocks[0].height;
+        let depth = depth.min(state.tip().height - genesis_height);
+        let mut discarded = Vec::new();
+        for _ in 0..depth {
+            let mut txs = state.rollback_block();
+            txs.append(&mut discarded);
+            discarded = txs;
+        }
+        state.generation += 1;
+        state.revalidate_mempool();
+        discarded
+    }
+}
+
+fn sim_header(blk: &SimBlock) -> Header {
+    Header {
+        version: 2,
+        id: blk.id,
+        parent_id: blk.parent_id,
+        ad_proofs_root: Digest32::zero(),
+        state_root: ADDigest::zero(),
+        transaction_root: Digest32::zero(),
+        timestamp: blk.timestamp,
+        n_bits: 0,
+        height: blk.height,
+        extension_root: Digest32::zero(),
+        autolykos_solution: AutolykosSolution {
+            miner_pk: Box::new(ec_point::generator()),
+            pow_onetime_pk: None,
+            nonce: vec![0u8; 8],
+            pow_distance: None,
+        },
+        votes: Votes([0u8; 3]),
+    }
+}
+
+#[async_trait(?Send)]
+impl ChainSyncNetwork for NodeSimulator {
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, node::Error> {
+        let state = self.state.lock();
+        state
+            .blocks
+            .iter()
+            .find(|blk| blk.height == height)
+            .map(|blk| FullBlock {
+                header: sim_header(blk),
+                block_transactions: BlockTransactions {
+                    transactions: blk.transactions.clone(),
+                },
+            })
+            .ok_or(node::Error::NoBlock)
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, node::Error> {
+        self.block_id_at(height).ok_or(node::Error::NoBlock)
+    }
+
+    async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, node::Error> {
+        let state = self.state.lock();
+        Ok(state.mempool.iter().skip(offset).take(limit).cloned().collect())
+    }
+
+    async fn get_best_height(&self) -> Result<u32, node::Error> {
+        Ok(self.height())
+    }
+}
+
+#[async_trait]
+impl ErgoNetwork for NodeSimulator {
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        let mut state = self.state.lock();
+        let tx_id = tx.id();
+        if state.mempool.iter().any(|t| t.id() == tx_id) {
+            return Ok(());
+        }
+        state.validate(&tx)?;
+        trace!(target: "node_simulator", "Tx [{:?}] accepted to mempool", tx_id);
+        state.mempool.push(tx);
+        Ok(())
+    }
+
+    async fn get_height(&self) -> u32 {
+        self.height()
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        Ok(self.state.lock().tokens.get(&token_id).cloned())
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        let state = self.state.lock();
+        let headers = state
+            .blocks
+            .iter()
+            .rev()
+            .take(NUM_LAST_HEADERS)
+            .map(sim_header)
+            .collect();
+        make_state_context(headers, state.tip().timestamp + BLOCK_INTERVAL_MILLIS)
+            .ok_or(ClientError("No blocks".into()))
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        let state = self.state.lock();
+        if state.mempool_spent().contains(&box_id) {
+            return Ok(None);
+        }
+        Ok(state
+            .utxos
+            .get(&box_id)
+            .cloned()
+            .or_else(|| state.mempool_outputs().remove(&box_id)))
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        Ok(self.state.lock().mempool.iter().any(|tx| tx.id() == tx_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ergo_lib::chain::transaction::{Transaction, TxIoVec};
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+    use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+    use futures::StreamExt;
+    use sigma_test_util::force_any_val;
+
+    use ergo_chain_sync::cache::chain_cache::InMemoryCache;
+    use ergo_chain_sync::{chain_sync_stream, ChainSync, ChainUpgrade, NO_PREFETCH};
+
+    use crate::network::simulator::NodeSimulator;
+    use crate::network::{DryRunConfig, ErgoNetwork, ErgoNetworkDryRun, NodeSubmitTxError, TokenMintingInfo};
+    use crate::transaction::{TransactionCandidate, UnsignedTransactionOps};
+
+    fn spend(inputs: Vec<ErgoBox>) -> Transaction {
+        TransactionCandidate::new(
+            TxIoVec::from_vec(
+                inputs
+                    .into_iter()
+                    .map(|bx| (bx, ContextExtension::empty()))
+                    .collect(),
+            )
+            .unwrap(),
+            None,
+            TxIoVec::from_vec(vec![force_any_val::<ErgoBoxCandidate>()]).unwrap(),
+        )
+        .into_tx_without_proofs()
+    }
+
+    #[tokio::test]
+    async fn missing_inputs_are_reported() {
+        let node = NodeSimulator::new(0, 0);
+        let known = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![known.clone()]);
+        let tx = spend(vec![
+            known,
+            force_any_val::<ErgoBox>(),
+            force_any_val::<ErgoBox>(),
+        ]);
+        let err = node.submit_tx(tx).await.unwrap_err();
+        assert_eq!(err, NodeSubmitTxError::MissingInputs(vec![1, 2]));
+    }
+
+    #[tokio::test]
+    async fn double_spend_is_reported() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        node.submit_tx(spend(vec![bx.clone()])).await.unwrap();
+        let err = node.submit_tx(spend(vec![bx])).await.unwrap_err();
+        assert_eq!(err, NodeSubmitTxError::DoubleSpend);
+    }
+
+    #[tokio::test]
+    async fn dry_run_never_broadcasts() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let export_dir = std::env::temp_dir();
+        let dry_run = ErgoNetworkDryRun::wrap(
+            node.clone(),
+            Some(DryRunConfig {
+                export_dir: Some(export_dir.clone()),
+            }),
+        );
+        let tx = spend(vec![bx]);
+        dry_run.submit_tx(tx.clone()).await.unwrap();
+        assert!(node.mempool().is_empty());
+        let export_path = export_dir.join(format!("{}.json", String::from(tx.id())));
+        let exported: Transaction = serde_json::from_slice(&std::fs::read(&export_path).unwrap()).unwrap();
+        assert_eq!(exported.id(), tx.id());
+        std::fs::remove_file(export_path).unwrap();
+        std::fs::remove_file(export_dir.join(format!("{}.validation.json", String::from(tx.id())))).unwrap();
+
+        let passthrough = ErgoNetworkDryRun::wrap(node.clone(), None);
+        passthrough.submit_tx(tx).await.unwrap();
+        assert_eq!(node.mempool().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn dry_run_exports_validation_result() {
+        let node = NodeSimulator::new(0, 0);
+        let export_dir = std::env::temp_dir();
+        let dry_run = ErgoNetworkDryRun::wrap(
+            node.clone(),
+            Some(DryRunConfig {
+                export_dir: Some(export_dir.clone()),
+            }),
+        );
+        let validation_of = |tx: &Transaction| {
+            let tx_id = String::from(tx.id());
+            let path = export_dir.join(format!("{}.validation.json", tx_id));
+            let validation: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+            std::fs::remove_file(path).unwrap();
+            std::fs::remove_file(export_dir.join(format!("{}.json", tx_id))).unwrap();
+            validation
+        };
+        // Invalid txs are still reported as submitted.
+        let tx_0 = spend(vec![force_any_val::<ErgoBox>()]);
+        dry_run.submit_tx(tx_0.clone()).await.unwrap();
+        let validation = validation_of(&tx_0);
+        assert_eq!(validation["valid"], false);
+        assert!(validation["error"].as_str().unwrap().contains("not in UTXO set"));
+        // Outputs of dry run txs are resolved when validating txs chained upon them.
+        let tx_1 = spend(vec![tx_0.outputs.first().clone()]);
+        dry_run.submit_tx(tx_1.clone()).await.unwrap();
+        let validation = validation_of(&tx_1);
+        assert!(!validation["error"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("not in UTXO set"));
+        assert!(node.mempool().is_empty());
+    }
+
+    #[tokio::test]
+    async fn mempool_chains_are_mined() {
+        let node = NodeSimulator::new(10, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx_0 = spend(vec![bx.clone()]);
+        let tx_1 = spend(vec![tx_0.outputs.first().clone()]);
+        node.submit_tx(tx_0.clone()).await.unwrap();
+        node.submit_tx(tx_1.clone()).await.unwrap();
+        node.mine_block();
+        assert_eq!(node.get_height().await, 11);
+        assert!(node.mempool().is_empty());
+        assert_eq!(node.block_transactions(11), Some(vec![tx_0.id(), tx_1.id()]));
+        assert!(node.utxo(bx.box_id()).is_none());
+        assert!(node.utxo(tx_1.outputs.first().box_id()).is_some());
+    }
+
+    #[tokio::test]
+    async fn fork_restores_utxos() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx.clone()]);
+        node.submit_tx(tx.clone()).await.unwrap();
+        node.mine_block();
+        let discarded = node.fork(1);
+        assert_eq!(discarded, vec![tx.clone()]);
+        assert_eq!(node.get_height().await, 0);
+        assert!(node.utxo(bx.box_id()).is_some());
+        assert!(node.utxo(tx.outputs.first().box_id()).is_none());
+    }
+
+    #[tokio::test]
+    async fn state_context_follows_tip() {
+        let node = NodeSimulator::new(0, 0);
+        let tip = node.mine_block();
+        let ctx = node.get_state_context().await.unwrap();
+        assert_eq!(ctx.pre_header.height, 2);
+        assert_eq!(ctx.pre_header.parent_id, tip);
+        assert_eq!(ctx.headers[0].height, 1);
+        assert_eq!(ctx.headers[9].height, 0);
+    }
+
+    #[tokio::test]
+    async fn token_minting_info() {
+        let node = NodeSimulator::new(0, 0);
+        let token_id = force_any_val();
+        node.register_token(
+            token_id,
+            TokenMintingInfo {
+                name: "LM".into(),
+                description: "Test token".into(),
+            },
+        );
+        let info = node.get_token_minting_info(token_id).await.unwrap().unwrap();
+        assert_eq!(info.name, "LM");
+        assert!(node
+            .get_token_minting_info(force_any_val())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn chain_sync_follows_forks() {
+        let node = NodeSimulator::new(0, 0);
+        node.mine_block();
+        node.mine_block();
+        let chain_sync = ChainSync::init(0, &node, InMemoryCache::new(), None, NO_PREFETCH).await;
+        let mut upgrades = Box::pin(chain_sync_stream(chain_sync));
+        for height in 0..=2 {
+            assert!(
+                matches!(upgrades.next().await, Some(ChainUpgrade::RollForward(blk)) if blk.height == height)
+            );
+        }
+        let discarded_id = node.block_id_at(2).unwrap();
+        node.fork(1);
+        node.mine_block();
+        node.mine_block();
+        assert!(
+            matches!(upgrades.next().await, Some(ChainUpgrade::RollBackward(blk)) if blk.id == discarded_id)
+        );
+        for height in 2..=3 {
+            assert!(
+                matches!(upgrades.next().await, Some(ChainUpgrade::RollForward(blk)) if blk.height == height)
+            );
+        }
+        assert_ne!(node.block_id_at(2), Some(discarded_id));
+    }
+
+    #[tokio::test]
+    async fn chain_sync_prefetches_blocks_in_order() {
+        let node = NodeSimulator::new(0, 0);
+        for _ in 0..10 {
+            node.mine_block();
+        }
+        let chain_sync = ChainSync::init(0, &node, InMemoryCache::new(), None, 4).await;
+        let mut upgrades = Box::pin(chain_sync_stream(chain_sync));
+        let sync = async {
+            let mut parent_id = None;
+            for height in 0..=10 {
+                match upgrades.next().await {
+                    Some(ChainUpgrade::RollForward(blk)) => {
+                        assert_eq!(blk.height, height);
+                        if let Some(parent_id) = parent_id {
+                            assert_eq!(blk.parent_id, parent_id);
+                        }
+                        parent_id = Some(blk.id);
+                    }
+                    upgr => panic!("Unexpected upgrade {:?}", upgr),
+                }
+            }
+            // Forks are still followed once blocks are no longer prefetched.
+            let discarded_id = node.block_id_at(10).unwrap();
+            node.fork(1);
+            node.mine_block();
+            node.mine_block();
+            assert!(
+                matches!(upgrades.next().await, Some(ChainUpgrade::RollBackward(blk)) if blk.id == discarded_id)
+            );
+            for height in 10..=11 {
+                assert!(
+                    matches!(upgrades.next().await, Some(ChainUpgrade::RollForward(blk)) if blk.height == height)
+                );
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), sync)
+            .await
+            .expect("Chain sync stalled");
+    }
+}
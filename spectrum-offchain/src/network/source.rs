@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergo_chain_types::BlockId;
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+
+use ergo_chain_sync::client::explorer::ErgoExplorerHttpClient;
+use ergo_chain_sync::client::model::FullBlock;
+use ergo_chain_sync::client::node::{self, ErgoNetwork as ChainSyncNetwork, ErgoNodeHttpClient};
+
+use crate::network::{ClientError, ErgoNetwork, NodeSubmitTxError, TokenMintingInfo};
+
+/// Either a full node or an explorer, so that both kinds of sources can be mixed
+/// in a `FailoverNetwork`.
+#[derive(Clone)]
+pub enum ChainDataSource {
+    Node(ErgoNodeHttpClient),
+    Explorer(ErgoExplorerHttpClient),
+}
+
+#[async_trait(?Send)]
+impl ChainSyncNetwork for ChainDataSource {
+    async fn get_block_at(&self, height: u32) -> Result<FullBlock, node::Error> {
+        match self {
+            ChainDataSource::Node(node) => node.get_block_at(height).await,
+            ChainDataSource::Explorer(explorer) => explorer.get_block_at(height).await,
+        }
+    }
+
+    async fn get_block_id_at(&self, height: u32) -> Result<BlockId, node::Error> {
+        match self {
+            ChainDataSource::Node(node) => node.get_block_id_at(height).await,
+            ChainDataSource::Explorer(explorer) => explorer.get_block_id_at(height).await,
+        }
+    }
+
+    async fn fetch_mempool(&self, offset: usize, limit: usize) -> Result<Vec<Transaction>, node::Error> {
+        match self {
+            ChainDataSource::Node(node) => node.fetch_mempool(offset, limit).await,
+            ChainDataSource::Explorer(explorer) => explorer.fetch_mempool(offset, limit).await,
+        }
+    }
+
+    async fn get_best_height(&self) -> Result<u32, node::Error> {
+        match self {
+            ChainDataSource::Node(node) => node.get_best_height().await,
+            ChainDataSource::Explorer(explorer) => explorer.get_best_height().await,
+        }
+    }
+}
+
+#[async_trait]
+impl ErgoNetwork for ChainDataSource {
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        match self {
+            ChainDataSource::Node(node) => node.submit_tx(tx).await,
+            ChainDataSource::Explorer(explorer) => explorer.submit_tx(tx).await,
+        }
+    }
+
+    async fn get_height(&self) -> u32 {
+        match self {
+            ChainDataSource::Node(node) => node.get_height().await,
+            ChainDataSource::Explorer(explorer) => explorer.get_height().await,
+        }
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        match self {
+            ChainDataSource::Node(node) => node.get_token_minting_info(token_id).await,
+            ChainDataSource::Explorer(explorer) => explorer.get_token_minting_info(token_id).await,
+        }
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        match self {
+            ChainDataSource::Node(node) => node.get_state_context().await,
+            ChainDataSource::Explorer(explorer) => explorer.get_state_context().await,
+        }
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        match self {
+            ChainDataSource::Node(node) => node.get_utxo(box_id).await,
+            ChainDataSource::Explorer(explorer) => explorer.get_utxo(box_id).await,
+        }
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        match self {
+            ChainDataSource::Node(node) => node.is_in_mempool(tx_id).await,
+            ChainDataSource::Explorer(explorer) => explorer.is_in_mempool(tx_id).await,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once, RwLock};
+
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use futures::{Stream, StreamExt};
+use log::{trace, warn};
+
+use ergo_chain_sync::ChainUpgrade;
+
+use crate::network::{ClientError, ErgoNetwork};
+
+/// Latest known state context of the network, shared between its consumers.
+#[derive(Clone)]
+pub struct StateContext {
+    ctx: Arc<RwLock<ErgoStateContext>>,
+    /// Whether the context was ever obtained from the network, the initial one is a placeholder.
+    synced: Arc<AtomicBool>,
+}
+
+impl StateContext {
+    pub fn new(initial: ErgoStateContext) -> Self {
+        Self {
+            ctx: Arc::new(RwLock::new(initial)),
+            synced: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn get(&self) -> ErgoStateContext {
+        self.ctx.read().unwrap().clone()
+    }
+
+    /// State context if it was obtained from the network already.
+    pub fn try_get(&self) -> Option<ErgoStateContext> {
+        self.synced.load(Ordering::Acquire).then(|| self.get())
+    }
+
+    pub fn set(&self, ctx: ErgoStateContext) {
+        *self.ctx.write().unwrap() = ctx;
+        self.synced.store(true, Ordering::Release);
+    }
+
+    /// Replace the state context with the one built from the last headers of the given network.
+    pub async fn refresh<TNetwork: ErgoNetwork>(&self, network: &TNetwork) -> Result<(), ClientError> {
+        let ctx = network.get_state_context().await?;
+        trace!(target: "state_context", "State context refreshed at height {}", ctx.pre_header.height);
+        self.set(ctx);
+        Ok(())
+    }
+}
+
+/// Refresh the given state context on every chain upgrade once the tip is reached.
+pub fn sync_state_context<'a, S, TNetwork>(
+    upstream: S,
+    network: &'a TNetwork,
+    state_context: StateContext,
+    tip_reached_signal: &'a Once,
+) -> impl Stream<Item = ChainUpgrade> + 'a
+where
+    S: Stream<Item = ChainUpgrade> + 'a,
+    TNetwork: ErgoNetwork + Sync,
+{
+    upstream.then(move |upgr| {
+        let state_context = state_context.clone();
+        async move {
+            if tip_reached_signal.is_completed() {
+                if let Err(err) = state_context.refresh(network).await {
+                    warn!(target: "state_context", "Failed to refresh state context due to {}", err);
+                }
+            }
+            upgr
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use ergo_chain_sync::model::Block;
+    use ergo_chain_sync::ChainUpgrade;
+    use futures::{stream, StreamExt};
+    use sigma_test_util::force_any_val;
+
+    use crate::network::simulator::NodeSimulator;
+    use crate::state_context::{sync_state_context, StateContext};
+
+    fn upgrade(height: u32) -> ChainUpgrade {
+        ChainUpgrade::RollForward(Block {
+            id: force_any_val(),
+            parent_id: force_any_val(),
+            height,
+            timestamp: 0,
+            transactions: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn state_context_follows_upgrades_at_tip() {
+        let node = NodeSimulator::new(0, 0);
+        let state_context = StateContext::new(force_any_val());
+        assert!(state_context.try_get().is_none());
+        state_context.refresh(&node).await.unwrap();
+        assert!(state_context.try_get().is_some());
+        assert_eq!(state_context.get().pre_header.height, 1);
+
+        let tip_reached = Once::new();
+        node.mine_block();
+        let upgrades = sync_state_context(
+            stream::iter(vec![upgrade(1)]),
+            &node,
+            state_context.clone(),
+            &tip_reached,
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(upgrades.len(), 1);
+        assert_eq!(state_context.get().pre_header.height, 1);
+
+        tip_reached.call_once(|| {});
+        node.mine_block();
+        sync_state_context(
+            stream::iter(vec![upgrade(2)]),
+            &node,
+            state_context.clone(),
+            &tip_reached,
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(state_context.get().pre_header.height, 3);
+    }
+}
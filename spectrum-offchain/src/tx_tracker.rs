@@ -0,0 +1,533 @@
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::{Transaction, TxId};
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox};
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use log::{info, trace, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::box_resolver::persistence::EntityRepo;
+use crate::data::OnChainEntity;
+use crate::network::{ClientError, ErgoNetwork, NodeSubmitTxError, TokenMintingInfo};
+
+pub mod process;
+
+/// Status of a submitted transaction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub enum TxStatus {
+    /// Accepted by the node, but not observed in mempool yet.
+    Submitted,
+    /// Observed in mempool.
+    InMempool,
+    /// Left mempool without being mined. Waits for rebroadcast.
+    Withdrawn,
+    /// Included into a block. Final unless the block is rolled back.
+    Confirmed,
+    /// Never mined. Predictions made upon the tx are rolled back. Final.
+    Dropped,
+}
+
+impl TxStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(self, TxStatus::Confirmed | TxStatus::Dropped)
+    }
+}
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxTrackerConfig {
+    /// Rebroadcast a tx which is not observed in mempool for this long.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub rebroadcast_after: Duration,
+    /// Tx is dropped once it has been rebroadcasted this many times.
+    pub max_rebroadcasts: u32,
+    /// Tx is dropped if it isn't mined in this time since the first submission.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub confirmation_timeout: Duration,
+}
+
+/// Undoes local effects (e.g. predicted states of entities) of a transaction which was never mined.
+#[async_trait(?Send)]
+pub trait Rollback {
+    async fn rollback(&self, tx: &Transaction);
+}
+
+/// Watches submitted transactions until they are either mined or dropped.
+#[async_trait(?Send)]
+pub trait TxTracker {
+    /// Start tracking the given submitted transaction.
+    fn track(&mut self, tx: Transaction);
+    /// Update status of a tracked transaction according to what is observed in mempool or ledger.
+    /// Returns `false` if the transaction is unknown.
+    fn observe(&mut self, tx_id: TxId, status: TxStatus) -> bool;
+    /// Rebroadcast transactions which dropped out of mempool,
+    /// drop and roll back transactions which are not mined in time and are no longer in mempool.
+    async fn revisit(&mut self);
+    /// Status of the given transaction. Final statuses are kept for a limited number of transactions.
+    fn status(&self, tx_id: TxId) -> Option<TxStatus>;
+}
+
+struct TrackedTx {
+    tx: Transaction,
+    status: TxStatus,
+    submitted_at: i64,
+    broadcasted_at: i64,
+    rebroadcasts: u32,
+}
+
+/// Number of finalized transactions whose statuses are remembered.
+const KEEP_FINALIZED_TXS: usize = 1024;
+
+pub struct TxTrackerService<TNetwork, TRollback> {
+    /// Used to rebroadcast txs. Must not be wrapped into `ErgoNetworkTxTracking`
+    /// backed by this tracker.
+    network: TNetwork,
+    rollback: TRollback,
+    conf: TxTrackerConfig,
+    tracked: HashMap<TxId, TrackedTx>,
+    finalized: HashMap<TxId, TxStatus>,
+    /// Finalized txs in the order they were finalized, the oldest ones are forgotten first.
+    finalized_queue: VecDeque<TxId>,
+}
+
+impl<TNetwork, TRollback> TxTrackerService<TNetwork, TRollback> {
+    pub fn new(network: TNetwork, rollback: TRollback, conf: TxTrackerConfig) -> Self {
+        Self {
+            network,
+            rollback,
+            conf,
+            tracked: HashMap::new(),
+            finalized: HashMap::new(),
+            finalized_queue: VecDeque::new(),
+        }
+    }
+
+    fn finalize(&mut self, tx_id: TxId, status: TxStatus) {
+        info!(target: "tx_tracker", "Tx [{:?}] finalized as {:?}", tx_id, status);
+        if self.finalized.insert(tx_id, status).is_none() {
+            self.finalized_queue.push_back(tx_id);
+            if self.finalized_queue.len() > KEEP_FINALIZED_TXS {
+                if let Some(oldest) = self.finalized_queue.pop_front() {
+                    self.finalized.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<TNetwork, TRollback> TxTracker for TxTrackerService<TNetwork, TRollback>
+where
+    TNetwork: ErgoNetwork,
+    TRollback: Rollback,
+{
+    fn track(&mut self, tx: Transaction) {
+        let tx_id = tx.id();
+        let ts_now = Utc::now().timestamp();
+        match self.tracked.get_mut(&tx_id) {
+            Some(tracked) => tracked.broadcasted_at = ts_now,
+            None => {
+                trace!(target: "tx_tracker", "Tracking tx [{:?}]", tx_id);
+                self.tracked.insert(
+                    tx_id,
+                    TrackedTx {
+                        tx,
+                        status: TxStatus::Submitted,
+                        submitted_at: ts_now,
+                        broadcasted_at: ts_now,
+                        rebroadcasts: 0,
+                    },
+                );
+            }
+        }
+    }
+
+    fn observe(&mut self, tx_id: TxId, status: TxStatus) -> bool {
+        if let Some(tracked) = self.tracked.get_mut(&tx_id) {
+            trace!(target: "tx_tracker", "Tx [{:?}]: {:?} -> {:?}", tx_id, tracked.status, status);
+            if status.is_final() {
+                self.tracked.remove(&tx_id);
+                self.finalize(tx_id, status);
+            } else {
+                tracked.status = status;
+            }
+            true
+        } else if let (Some(known_status), TxStatus::Confirmed) = (self.finalized.get_mut(&tx_id), status) {
+            // Tx was given up on, but got mined eventually.
+            *known_status = status;
+            info!(target: "tx_tracker", "Tx [{:?}] finalized as {:?}", tx_id, status);
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn revisit(&mut self) {
+        let ts_now = Utc::now().timestamp();
+        let mut to_drop = Vec::new();
+        let mut to_rebroadcast = Vec::new();
+        let mut expired = Vec::new();
+        for (tx_id, tracked) in self.tracked.iter() {
+            if ts_now - tracked.submitted_at > self.conf.confirmation_timeout.num_seconds() {
+                expired.push(*tx_id);
+            } else if tracked.status == TxStatus::Withdrawn
+                || (tracked.status == TxStatus::Submitted
+                    && ts_now - tracked.broadcasted_at >= self.conf.rebroadcast_after.num_seconds())
+            {
+                if tracked.rebroadcasts >= self.conf.max_rebroadcasts {
+                    to_drop.push(*tx_id);
+                } else {
+                    to_rebroadcast.push(*tx_id);
+                }
+            }
+        }
+        for tx_id in expired {
+            // Tx may still be mined as long as it stays in mempool.
+            match self.network.is_in_mempool(tx_id).await {
+                Ok(true) => {
+                    trace!(target: "tx_tracker", "Tx [{:?}] is not mined in time, but still in mempool", tx_id);
+                }
+                Ok(false) => to_drop.push(tx_id),
+                // Can't tell right now, check again on the next revisit.
+                Err(err) => {
+                    warn!(target: "tx_tracker", "Failed to look up tx [{:?}] in mempool due to {}", tx_id, err);
+                }
+            }
+        }
+        for tx_id in to_rebroadcast {
+            if let Some(tracked) = self.tracked.get_mut(&tx_id) {
+                trace!(target: "tx_tracker", "Rebroadcasting tx [{:?}]", tx_id);
+                match self.network.submit_tx(tracked.tx.clone()).await {
+                    Ok(_) => {
+                        tracked.status = TxStatus::Submitted;
+                        tracked.broadcasted_at = ts_now;
+                        tracked.rebroadcasts += 1;
+                    }
+                    // Node is not able to take the tx right now, try again later.
+                    Err(err) if err.is_transient() => {
+                        warn!(target: "tx_tracker", "Failed to rebroadcast tx [{:?}] due to {}", tx_id, err);
+                        tracked.broadcasted_at = ts_now;
+                    }
+                    Err(err) => {
+                        warn!(target: "tx_tracker", "Tx [{:?}] rejected on rebroadcast due to {}", tx_id, err);
+                        to_drop.push(tx_id);
+                    }
+                }
+            }
+        }
+        for tx_id in to_drop {
+            if let Some(tracked) = self.tracked.remove(&tx_id) {
+                self.rollback.rollback(&tracked.tx).await;
+                self.finalize(tx_id, TxStatus::Dropped);
+            }
+        }
+    }
+
+    fn status(&self, tx_id: TxId) -> Option<TxStatus> {
+        self.tracked
+            .get(&tx_id)
+            .map(|tracked| tracked.status)
+            .or_else(|| self.finalized.get(&tx_id).copied())
+    }
+}
+
+/// Rolls back predicted states of `TEntity` created by a transaction.
+pub struct DiscardPredictions<TEntity, TRepo> {
+    repo: Arc<Mutex<TRepo>>,
+    pd: PhantomData<TEntity>,
+}
+
+impl<TEntity, TRepo> DiscardPredictions<TEntity, TRepo> {
+    pub fn new(repo: Arc<Mutex<TRepo>>) -> Self {
+        Self {
+            repo,
+            pd: PhantomData,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<TEntity, TRepo> Rollback for DiscardPredictions<TEntity, TRepo>
+where
+    TEntity: OnChainEntity,
+    TEntity::TStateId: From<BoxId>,
+    TRepo: EntityRepo<TEntity>,
+{
+    async fn rollback(&self, tx: &Transaction) {
+        let mut repo = self.repo.lock().await;
+        for bx in tx.outputs.iter() {
+            repo.discard_predicted(TEntity::TStateId::from(bx.box_id())).await;
+        }
+    }
+}
+
+/// Registers every successfully submitted transaction in the given `TxTracker`.
+pub struct ErgoNetworkTxTracking<R, TTracker> {
+    inner: R,
+    tracker: Arc<Mutex<TTracker>>,
+}
+
+impl<R, TTracker> ErgoNetworkTxTracking<R, TTracker> {
+    pub fn wrap(network: R, tracker: Arc<Mutex<TTracker>>) -> Self {
+        Self {
+            inner: network,
+            tracker,
+        }
+    }
+}
+
+#[async_trait]
+impl<R, TTracker> ErgoNetwork for ErgoNetworkTxTracking<R, TTracker>
+where
+    R: ErgoNetwork + Send + Sync,
+    TTracker: TxTracker + Send + Sync,
+{
+    async fn submit_tx(&self, tx: Transaction) -> Result<(), NodeSubmitTxError> {
+        self.inner.submit_tx(tx.clone()).await?;
+        self.tracker.lock().await.track(tx);
+        Ok(())
+    }
+
+    async fn get_height(&self) -> u32 {
+        self.inner.get_height().await
+    }
+
+    async fn get_token_minting_info(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<TokenMintingInfo>, ClientError> {
+        self.inner.get_token_minting_info(token_id).await
+    }
+
+    async fn get_state_context(&self) -> Result<ErgoStateContext, ClientError> {
+        self.inner.get_state_context().await
+    }
+
+    async fn get_utxo(&self, box_id: BoxId) -> Result<Option<ErgoBox>, ClientError> {
+        self.inner.get_utxo(box_id).await
+    }
+
+    async fn is_in_mempool(&self, tx_id: TxId) -> Result<bool, ClientError> {
+        self.inner.is_in_mempool(tx_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Duration;
+    use ergo_lib::chain::transaction::{Transaction, TxIoVec};
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+    use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox, ErgoBoxCandidate};
+    use sigma_test_util::force_any_val;
+    use tokio::sync::Mutex;
+
+    use crate::box_resolver::in_memory::InMemoryEntityRepo;
+    use crate::box_resolver::persistence::tests::ErgoEntity;
+    use crate::box_resolver::persistence::EntityRepo;
+    use crate::data::unique_entity::{Confirmed, Predicted, Traced};
+    use crate::network::simulator::NodeSimulator;
+    use crate::network::{DryRunConfig, ErgoNetwork, ErgoNetworkDryRun};
+    use crate::transaction::{TransactionCandidate, UnsignedTransactionOps};
+
+    use super::{
+        DiscardPredictions, ErgoNetworkTxTracking, TxStatus, TxTracker, TxTrackerConfig, TxTrackerService,
+    };
+
+    fn spend(inputs: Vec<ErgoBox>) -> Transaction {
+        TransactionCandidate::new(
+            TxIoVec::from_vec(
+                inputs
+                    .into_iter()
+                    .map(|bx| (bx, ContextExtension::empty()))
+                    .collect(),
+            )
+            .unwrap(),
+            None,
+            TxIoVec::from_vec(vec![force_any_val::<ErgoBoxCandidate>()]).unwrap(),
+        )
+        .into_tx_without_proofs()
+    }
+
+    fn conf(max_rebroadcasts: u32, confirmation_timeout: Duration) -> TxTrackerConfig {
+        TxTrackerConfig {
+            rebroadcast_after: Duration::zero(),
+            max_rebroadcasts,
+            confirmation_timeout,
+        }
+    }
+
+    type Entities = Arc<Mutex<InMemoryEntityRepo<ErgoEntity>>>;
+
+    fn tracker(
+        node: &NodeSimulator,
+        conf: TxTrackerConfig,
+    ) -> (
+        TxTrackerService<NodeSimulator, DiscardPredictions<ErgoEntity, InMemoryEntityRepo<ErgoEntity>>>,
+        Entities,
+    ) {
+        let entities = Arc::new(Mutex::new(InMemoryEntityRepo::new()));
+        let rollback = DiscardPredictions::new(Arc::clone(&entities));
+        (TxTrackerService::new(node.clone(), rollback, conf), entities)
+    }
+
+    /// Put confirmed state of an entity along with a state predicted by the given tx.
+    async fn predict(entities: &Entities, confirmed: &ErgoBox, tx: &Transaction) -> ErgoEntity {
+        let token_id = force_any_val();
+        let predicted = ErgoEntity {
+            token_id,
+            box_id: tx.outputs.first().box_id(),
+        };
+        let mut repo = entities.lock().await;
+        repo.put_confirmed(Confirmed(ErgoEntity {
+            token_id,
+            box_id: confirmed.box_id(),
+        }))
+        .await;
+        repo.put_predicted(Traced {
+            state: Predicted(predicted.clone()),
+            prev_state_id: Some(confirmed.box_id()),
+        })
+        .await;
+        predicted
+    }
+
+    #[tokio::test]
+    async fn withdrawn_tx_is_rebroadcasted() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx]);
+        node.submit_tx(tx.clone()).await.unwrap();
+        let (mut tracker, _) = tracker(&node, conf(3, Duration::hours(1)));
+        tracker.track(tx.clone());
+        assert!(tracker.observe(tx.id(), TxStatus::InMempool));
+
+        node.evict_tx(tx.id());
+        tracker.observe(tx.id(), TxStatus::Withdrawn);
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Submitted));
+        assert_eq!(node.mempool(), vec![tx.clone()]);
+
+        node.mine_block();
+        tracker.observe(tx.id(), TxStatus::Confirmed);
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Confirmed));
+    }
+
+    #[tokio::test]
+    async fn rejected_tx_is_dropped_and_rolled_back() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx.clone()]);
+        let (mut tracker, entities) = tracker(&node, conf(3, Duration::hours(1)));
+        let predicted = predict(&entities, &bx, &tx).await;
+        tracker.track(tx.clone());
+
+        // Input is spent by a conflicting tx.
+        node.mine_block_with(vec![spend(vec![bx])]);
+        tracker.observe(tx.id(), TxStatus::Withdrawn);
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Dropped));
+        let link: Option<BoxId> = entities
+            .lock()
+            .await
+            .get_prediction_predecessor(predicted.box_id)
+            .await;
+        assert!(link.is_none());
+    }
+
+    #[tokio::test]
+    async fn tx_is_dropped_after_max_rebroadcasts() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx]);
+        let (mut tracker, _) = tracker(&node, conf(1, Duration::hours(1)));
+        tracker.track(tx.clone());
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Submitted));
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Dropped));
+    }
+
+    #[tokio::test]
+    async fn tx_is_dropped_after_timeout() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx.clone()]);
+        let (mut tracker, entities) = tracker(&node, conf(3, Duration::seconds(-1)));
+        predict(&entities, &bx, &tx).await;
+        tracker.track(tx.clone());
+        tracker.observe(tx.id(), TxStatus::InMempool);
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Dropped));
+
+        // Tx still can be mined after all.
+        assert!(tracker.observe(tx.id(), TxStatus::Confirmed));
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Confirmed));
+    }
+
+    #[tokio::test]
+    async fn tx_in_mempool_is_kept_after_timeout() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx.clone()]);
+        node.submit_tx(tx.clone()).await.unwrap();
+        let (mut tracker, entities) = tracker(&node, conf(3, Duration::seconds(-1)));
+        let predicted = predict(&entities, &bx, &tx).await;
+        tracker.track(tx.clone());
+        tracker.observe(tx.id(), TxStatus::InMempool);
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::InMempool));
+        let link: Option<BoxId> = entities
+            .lock()
+            .await
+            .get_prediction_predecessor(predicted.box_id)
+            .await;
+        assert!(link.is_some());
+
+        node.evict_tx(tx.id());
+        tracker.revisit().await;
+        assert_eq!(tracker.status(tx.id()), Some(TxStatus::Dropped));
+    }
+
+    #[tokio::test]
+    async fn predictions_of_dry_run_txs_are_rolled_back() {
+        let node = NodeSimulator::new(0, 0);
+        let bx = force_any_val::<ErgoBox>();
+        node.add_utxos(vec![bx.clone()]);
+        let tx = spend(vec![bx.clone()]);
+        let dry_run = || ErgoNetworkDryRun::wrap(node.clone(), Some(DryRunConfig { export_dir: None }));
+        let entities: Entities = Arc::new(Mutex::new(InMemoryEntityRepo::new()));
+        let tracker = Arc::new(Mutex::new(TxTrackerService::new(
+            dry_run(),
+            DiscardPredictions::new(Arc::clone(&entities)),
+            conf(1, Duration::hours(1)),
+        )));
+        let network = ErgoNetworkTxTracking::wrap(dry_run(), Arc::clone(&tracker));
+        let predicted = predict(&entities, &bx, &tx).await;
+        network.submit_tx(tx.clone()).await.unwrap();
+
+        // Tx never shows up in mempool, so the tracker gives up on it after rebroadcasts.
+        tracker.lock().await.revisit().await;
+        tracker.lock().await.revisit().await;
+        assert!(node.mempool().is_empty());
+        assert_eq!(tracker.lock().await.status(tx.id()), Some(TxStatus::Dropped));
+        let link: Option<BoxId> = entities
+            .lock()
+            .await
+            .get_prediction_predecessor(predicted.box_id)
+            .await;
+        assert!(link.is_none());
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::trace;
+use tokio::sync::Mutex;
+
+use crate::tx_tracker::TxTracker;
+
+/// Construct stream that periodically rebroadcasts or drops tracked transactions.
+pub fn tx_tracker_stream<'a, TTracker>(
+    tracker: Arc<Mutex<TTracker>>,
+    revisit_interval: Duration,
+    tip_reached_signal: &'a Once,
+) -> impl Stream<Item = ()> + 'a
+where
+    TTracker: TxTracker + 'a,
+{
+    stream::unfold((), move |_| {
+        let tracker = Arc::clone(&tracker);
+        async move {
+            if tip_reached_signal.is_completed() {
+                trace!(target: "tx_tracker", "Revisiting tracked txs ..");
+                tracker.lock().await.revisit().await;
+            }
+            Delay::new(revisit_interval).await;
+            Some(((), ()))
+        }
+    })
+}